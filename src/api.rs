@@ -1,5 +1,6 @@
 pub mod admission_review;
-mod api_error;
+pub(crate) mod api_error;
+mod audit_batch;
 pub(crate) mod handlers;
 mod raw_review;
 mod service;
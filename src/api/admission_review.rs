@@ -1,6 +1,27 @@
+use anyhow::{Result, anyhow};
 use policy_evaluator::admission_request::AdmissionRequest;
 use policy_evaluator::admission_response::AdmissionResponse;
 
+const ADMISSION_REVIEW_KIND: &str = "AdmissionReview";
+
+/// The versions of the `AdmissionReview` API understood by Policy Server. The response is
+/// always sent with the version of the request.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AdmissionReviewVersion {
+    #[default]
+    V1,
+    V1Beta1,
+}
+
+impl AdmissionReviewVersion {
+    pub fn api_version(&self) -> &'static str {
+        match self {
+            AdmissionReviewVersion::V1 => "admission.k8s.io/v1",
+            AdmissionReviewVersion::V1Beta1 => "admission.k8s.io/v1beta1",
+        }
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AdmissionReviewRequest {
@@ -13,6 +34,51 @@ pub struct AdmissionReviewRequest {
     pub request: AdmissionRequest,
 }
 
+impl AdmissionReviewRequest {
+    /// Returns the version of the review, or an error when its kind or its `apiVersion`
+    /// are not supported
+    pub fn version(&self) -> Result<AdmissionReviewVersion> {
+        if self.kind.as_deref() != Some(ADMISSION_REVIEW_KIND) {
+            return Err(anyhow!(
+                "unsupported kind '{}', expected '{}'",
+                self.kind.as_deref().unwrap_or_default(),
+                ADMISSION_REVIEW_KIND
+            ));
+        }
+
+        [AdmissionReviewVersion::V1, AdmissionReviewVersion::V1Beta1]
+            .into_iter()
+            .find(|version| self.api_version.as_deref() == Some(version.api_version()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unsupported apiVersion '{}'",
+                    self.api_version.as_deref().unwrap_or_default()
+                )
+            })
+    }
+
+    /// Returns the admission request of the review. The API servers speaking v1beta1 can
+    /// omit the `requestKind`, `requestResource` and `requestSubResource` fields: they are
+    /// the same as `kind`, `resource` and `subResource` in that case.
+    pub fn into_admission_request(self, version: AdmissionReviewVersion) -> AdmissionRequest {
+        let mut request = self.request;
+
+        if version == AdmissionReviewVersion::V1Beta1 {
+            if request.request_kind.is_none() {
+                request.request_kind = Some(request.kind.clone());
+            }
+            if request.request_resource.is_none() {
+                request.request_resource = Some(request.resource.clone());
+            }
+            if request.request_sub_resource.is_none() {
+                request.request_sub_resource = request.sub_resource.clone();
+            }
+        }
+
+        request
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AdmissionReviewResponse {
@@ -26,11 +92,95 @@ pub struct AdmissionReviewResponse {
 }
 
 impl AdmissionReviewResponse {
-    pub fn new(response: AdmissionResponse) -> Self {
+    pub fn new(version: AdmissionReviewVersion, response: AdmissionResponse) -> Self {
         AdmissionReviewResponse {
-            api_version: Some(String::from("admission.k8s.io/v1")),
-            kind: Some(String::from("AdmissionReview")),
+            api_version: Some(version.api_version().to_owned()),
+            kind: Some(String::from(ADMISSION_REVIEW_KIND)),
             response,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+
+    #[rstest]
+    #[case::v1(
+        Some("AdmissionReview"),
+        Some("admission.k8s.io/v1"),
+        Some(AdmissionReviewVersion::V1)
+    )]
+    #[case::v1beta1(
+        Some("AdmissionReview"),
+        Some("admission.k8s.io/v1beta1"),
+        Some(AdmissionReviewVersion::V1Beta1)
+    )]
+    #[case::unknown_api_version(Some("AdmissionReview"), Some("admission.k8s.io/v2"), None)]
+    #[case::missing_api_version(Some("AdmissionReview"), None, None)]
+    #[case::unknown_kind(Some("ConversionReview"), Some("admission.k8s.io/v1"), None)]
+    #[case::missing_kind(None, Some("admission.k8s.io/v1"), None)]
+    fn detect_version(
+        #[case] kind: Option<&str>,
+        #[case] api_version: Option<&str>,
+        #[case] expected: Option<AdmissionReviewVersion>,
+    ) {
+        let admission_review = AdmissionReviewRequest {
+            kind: kind.map(str::to_owned),
+            api_version: api_version.map(str::to_owned),
+            ..build_admission_review_request()
+        };
+
+        assert_eq!(expected, admission_review.version().ok());
+    }
+
+    #[rstest]
+    #[case::v1(AdmissionReviewVersion::V1, None)]
+    #[case::v1beta1(AdmissionReviewVersion::V1Beta1, Some("deployments"))]
+    fn fill_v1beta1_request_fields(
+        #[case] version: AdmissionReviewVersion,
+        #[case] expected_request_resource: Option<&str>,
+    ) {
+        let mut admission_review = build_admission_review_request();
+        admission_review.request.request_kind = None;
+        admission_review.request.request_resource = None;
+        admission_review.request.request_sub_resource = None;
+
+        let request = admission_review.into_admission_request(version);
+
+        assert_eq!(
+            expected_request_resource,
+            request
+                .request_resource
+                .as_ref()
+                .map(|resource| resource.resource.as_str())
+        );
+        assert_eq!(
+            expected_request_resource.map(|_| "scale"),
+            request.request_sub_resource.as_deref()
+        );
+        assert_eq!(
+            expected_request_resource.is_some(),
+            request.request_kind.is_some()
+        );
+    }
+
+    #[test]
+    fn respond_with_the_version_of_the_request() {
+        let response = AdmissionReviewResponse::new(
+            AdmissionReviewVersion::V1Beta1,
+            AdmissionResponse {
+                uid: "hello".to_owned(),
+                allowed: true,
+                ..Default::default()
+            },
+        );
+
+        let response = serde_json::to_value(response).unwrap();
+        assert_eq!(response["apiVersion"], "admission.k8s.io/v1beta1");
+        assert_eq!(response["kind"], "AdmissionReview");
+        assert_eq!(response["response"]["uid"], "hello");
+    }
+}
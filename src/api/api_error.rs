@@ -1,11 +1,101 @@
+use std::fmt;
+
 use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse};
+use policy_evaluator::admission_response::{AdmissionResponse, StatusCause};
 use serde_json::json;
+use tracing::Span;
+
+use crate::{evaluation::policy_failure::PolicyFailure, metrics};
+
+/// A stable, machine-readable identifier of the errors returned by the API. Clients
+/// should rely on it rather than on the message, which can change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ErrorCode {
+    /// The requested policy doesn't exist
+    PolicyNotFound,
+    /// The policy exceeded the time it's allowed to run for
+    PolicyTimeout,
+    /// The WebAssembly module of the policy trapped
+    PolicyTrap,
+    /// The expression of the policy group failed, for example because it exceeded the
+    /// limits of the engine
+    PolicyGroupExpressionFailed,
+    /// The policy could not be initialized, for example because of invalid settings
+    PolicyInitializationFailed,
+    /// The request is malformed
+    InvalidRequest,
+    /// The server cannot accept more evaluations
+    Overloaded,
+    /// The request doesn't carry valid credentials
+    Unauthorized,
+    /// Any other failure
+    Internal,
+}
+
+impl ErrorCode {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::PolicyNotFound => "policy_not_found",
+            ErrorCode::PolicyTimeout => "policy_timeout",
+            ErrorCode::PolicyTrap => "policy_trap",
+            ErrorCode::PolicyGroupExpressionFailed => "policy_group_expression_failed",
+            ErrorCode::PolicyInitializationFailed => "policy_initialization_failed",
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::Overloaded => "overloaded",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    /// Report the code inside of the rejection of a request, as the reason of a cause of its
+    /// status. The clients can tell the failures of the policies apart from their
+    /// rejections.
+    pub(crate) fn add_to_rejection(&self, response: &mut AdmissionResponse) {
+        response
+            .status
+            .get_or_insert_with(Default::default)
+            .details
+            .get_or_insert_with(Default::default)
+            .causes
+            .push(StatusCause {
+                reason: Some(self.as_str().to_owned()),
+                ..Default::default()
+            });
+    }
+
+    /// Record the code on the current span and in the metrics
+    pub(crate) fn record(&self, policy_id: Option<&str>) {
+        Span::current().record("error_code", self.as_str());
+        metrics::add_policy_evaluation_error(&metrics::PolicyEvaluationError {
+            policy_name: policy_id.map(str::to_owned),
+            error_code: self.to_string(),
+        });
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl From<PolicyFailure> for ErrorCode {
+    fn from(failure: PolicyFailure) -> Self {
+        match failure {
+            PolicyFailure::Timeout => ErrorCode::PolicyTimeout,
+            PolicyFailure::Trap => ErrorCode::PolicyTrap,
+            PolicyFailure::PolicyGroupExpression => ErrorCode::PolicyGroupExpressionFailed,
+        }
+    }
+}
 
 #[derive(Debug)]
 /// An error that can be returned by the API
 /// and will be converted into a JSON response.
 pub(crate) struct ApiError {
     pub(crate) status: StatusCode,
+    pub(crate) code: ErrorCode,
+    /// A description of the error, that must not leak the internals of the server
     pub(crate) message: String,
 }
 
@@ -13,6 +103,7 @@ impl From<JsonRejection> for ApiError {
     fn from(rejection: JsonRejection) -> Self {
         Self {
             status: rejection.status(),
+            code: ErrorCode::InvalidRequest,
             message: rejection.body_text(),
         }
     }
@@ -23,8 +114,31 @@ impl IntoResponse for ApiError {
         let payload = json!({
             "message": self.message,
             "status": self.status.as_u16(),
+            "code": self.code.as_str(),
         });
 
         (self.status, axum::Json(payload)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_error_code_to_rejection() {
+        let mut response = AdmissionResponse::reject("uid".to_owned(), "boom".to_owned(), 500);
+
+        ErrorCode::PolicyTimeout.add_to_rejection(&mut response);
+
+        let status = response.status.expect("should have a status");
+        assert_eq!(Some("boom".to_owned()), status.message);
+        assert_eq!(
+            vec![StatusCause {
+                reason: Some("policy_timeout".to_owned()),
+                ..Default::default()
+            }],
+            status.details.expect("should have details").causes
+        );
+    }
+}
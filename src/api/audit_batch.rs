@@ -0,0 +1,236 @@
+use policy_evaluator::{
+    admission_request::AdmissionRequest, admission_response::AdmissionResponse,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::evaluation::{POLICY_GROUP_MEMBER_ALLOWED, POLICY_GROUP_MEMBER_CAUSE_PREFIX};
+
+/// Many resources to be audited with a single call. The resources are evaluated by the
+/// policies of the batch, and by their own ones.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub(crate) struct AuditBatchRequest {
+    /// The policies evaluating all the resources of the batch
+    #[serde(default)]
+    pub(crate) policies: Vec<String>,
+    pub(crate) resources: Vec<AuditBatchResource>,
+}
+
+/// A resource of an audit batch, described by the admission request that would have
+/// created it
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub(crate) struct AuditBatchResource {
+    /// The policies evaluating only this resource
+    #[serde(default)]
+    pub(crate) policies: Vec<String>,
+    pub(crate) request: AdmissionRequest,
+}
+
+impl AuditBatchResource {
+    /// Returns the policies evaluating the resource: the ones of the batch, followed by the
+    /// ones of the resource. Each policy is returned only once.
+    pub(crate) fn policies(&self, batch_policies: &[String]) -> Vec<String> {
+        let mut policies: Vec<String> = Vec::new();
+        for policy in batch_policies.iter().chain(self.policies.iter()) {
+            if !policies.contains(policy) {
+                policies.push(policy.clone());
+            }
+        }
+        policies
+    }
+}
+
+/// The outcome of the audit of a resource, by all its policies
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditBatchResult {
+    /// The UID of the admission request describing the resource
+    pub(crate) uid: String,
+    pub(crate) kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    /// One result per policy, in the order of the policies
+    pub(crate) results: Vec<AuditPolicyResult>,
+}
+
+/// The outcome of the audit of a resource by a single policy. Either the response of the
+/// policy, or the error that prevented the evaluation. When a policy group rejects the
+/// resource, the outcome of each of its evaluated members is reported too.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditPolicyResult {
+    pub(crate) policy_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) response: Option<AdmissionResponse>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) members: Vec<AuditPolicyGroupMemberResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<AuditPolicyError>,
+}
+
+impl AuditPolicyResult {
+    pub(crate) fn from_response(policy_id: String, response: AdmissionResponse) -> Self {
+        let members = AuditPolicyGroupMemberResult::from_response(&response);
+        AuditPolicyResult {
+            policy_id,
+            response: Some(response),
+            members,
+            error: None,
+        }
+    }
+
+    pub(crate) fn from_error(policy_id: String, error: AuditPolicyError) -> Self {
+        AuditPolicyResult {
+            policy_id,
+            response: None,
+            members: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// The outcome of a member of a policy group. The members of nested policy groups are
+/// identified by their path, like `nested-group/member`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct AuditPolicyGroupMemberResult {
+    pub(crate) member: String,
+    pub(crate) allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+}
+
+impl AuditPolicyGroupMemberResult {
+    /// Returns the outcome of the members of a policy group, as reported by the causes of
+    /// its response
+    fn from_response(response: &AdmissionResponse) -> Vec<Self> {
+        let Some(details) = response
+            .status
+            .as_ref()
+            .and_then(|status| status.details.as_ref())
+        else {
+            return Vec::new();
+        };
+
+        details
+            .causes
+            .iter()
+            .filter_map(|cause| {
+                let path = cause
+                    .field
+                    .as_deref()?
+                    .strip_prefix(POLICY_GROUP_MEMBER_CAUSE_PREFIX)?;
+                Some(AuditPolicyGroupMemberResult {
+                    member: path.split(".policies.").collect::<Vec<_>>().join("/"),
+                    allowed: cause.reason.as_deref() == Some(POLICY_GROUP_MEMBER_ALLOWED),
+                    message: cause.message.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct AuditPolicyError {
+    /// The code of the API error, see `ErrorCode`
+    pub(crate) code: String,
+    pub(crate) message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    #[rstest]
+    #[case::batch_policies(&["a", "b"], &[], &["a", "b"])]
+    #[case::resource_policies(&[], &["c"], &["c"])]
+    #[case::both(&["a", "b"], &["c"], &["a", "b", "c"])]
+    #[case::duplicated_policies(&["a", "b"], &["b", "c", "c"], &["a", "b", "c"])]
+    fn policies_of_a_resource(
+        #[case] batch_policies: &[&str],
+        #[case] resource_policies: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let mut audit_batch: AuditBatchRequest = serde_json::from_value(json!({
+            "policies": batch_policies,
+            "resources": [{
+                "policies": resource_policies,
+                "request": crate::test_utils::build_admission_review_request().request,
+            }],
+        }))
+        .unwrap();
+        let resource = audit_batch.resources.pop().unwrap();
+
+        assert_eq!(expected.to_vec(), resource.policies(&audit_batch.policies));
+    }
+
+    #[test]
+    fn policy_group_members_of_a_response() {
+        let response: AdmissionResponse = serde_json::from_value(json!({
+            "uid": "uid",
+            "allowed": false,
+            "status": {
+                "message": "the group rejected the request",
+                "code": 400,
+                "details": {
+                    "causes": [
+                        {
+                            "field": "spec.policies.happy",
+                            "reason": "Allowed",
+                        },
+                        {
+                            "field": "spec.policies.nested",
+                            "message": "the nested group rejected the request",
+                            "reason": "Denied",
+                        },
+                        {
+                            "field": "spec.policies.nested.policies.unhappy",
+                            "message": "failing as expected",
+                            "reason": "Denied",
+                        },
+                        {
+                            "field": "spec.containers",
+                            "message": "not a member",
+                        },
+                    ],
+                },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                AuditPolicyGroupMemberResult {
+                    member: "happy".to_owned(),
+                    allowed: true,
+                    message: None,
+                },
+                AuditPolicyGroupMemberResult {
+                    member: "nested".to_owned(),
+                    allowed: false,
+                    message: Some("the nested group rejected the request".to_owned()),
+                },
+                AuditPolicyGroupMemberResult {
+                    member: "nested/unhappy".to_owned(),
+                    allowed: false,
+                    message: Some("failing as expected".to_owned()),
+                },
+            ],
+            AuditPolicyGroupMemberResult::from_response(&response)
+        );
+    }
+
+    #[test]
+    fn reject_unknown_fields() {
+        let audit_batch = serde_json::from_value::<AuditBatchRequest>(json!({
+            "policy": "a",
+            "resources": [],
+        }));
+
+        assert!(audit_batch.is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use axum::{
     Json,
+    body::Body,
     extract::{self, FromRequest, Query},
     http::{StatusCode, header},
     response::IntoResponse,
@@ -9,20 +10,26 @@ use policy_evaluator::{
     admission_response_handler::errors::EvaluationError, policy_evaluator::ValidateRequest,
 };
 
+use futures::{Stream, StreamExt, future, stream};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::task;
-use tracing::{Span, debug, error};
+use std::{convert::Infallible, sync::Arc};
+use tokio::{task, time};
+use tracing::{Instrument, Span, debug, error};
 
 use crate::profiling::ReportGenerationError;
 use crate::{
     api::{
-        admission_review::{AdmissionReviewRequest, AdmissionReviewResponse},
-        api_error::ApiError,
-        raw_review::{RawReviewRequest, RawReviewResponse},
-        service::{RequestOrigin, evaluate},
+        admission_review::{
+            AdmissionReviewRequest, AdmissionReviewResponse, AdmissionReviewVersion,
+        },
+        api_error::{ApiError, ErrorCode},
+        audit_batch::{AuditBatchRequest, AuditBatchResult, AuditPolicyError, AuditPolicyResult},
+        raw_review::{BatchLimits, RawReviewRequest, RawReviewResponse, parse_raw_review_batch},
+        service::{RequestOrigin, evaluate, evaluate_matching_policies},
         state::ApiServerState,
     },
+    break_glass::{self, BreakGlassActivation, BreakGlassStatus},
+    evaluation::EvaluationEnvironment,
     profiling,
 };
 
@@ -49,6 +56,7 @@ impl<T: Serialize> IntoResponse for JsonExtractor<T> {
         request_uid=tracing::field::Empty,
         host=crate::config::HOSTNAME.as_str(),
         policy_id=policy_id.as_str(),
+        policy_variant=tracing::field::Empty,
         name=tracing::field::Empty,
         namespace=tracing::field::Empty,
         operation=tracing::field::Empty,
@@ -63,30 +71,147 @@ impl<T: Serialize> IntoResponse for JsonExtractor<T> {
         mutated=tracing::field::Empty,
         response_code=tracing::field::Empty,
         response_message=tracing::field::Empty,
+        error_code=tracing::field::Empty,
     ),
     skip_all)]
 ///  Run a validation in "audit" mode.
 pub(crate) async fn audit_handler(
     extract::State(state): extract::State<Arc<ApiServerState>>,
     extract::Path(policy_id): extract::Path<String>,
-    extract::Json(admission_review): extract::Json<AdmissionReviewRequest>,
+    JsonExtractor(admission_review): JsonExtractor<AdmissionReviewRequest>,
 ) -> Result<Json<AdmissionReviewResponse>, (StatusCode, ApiError)> {
     debug!(admission_review = %serde_json::to_string(&admission_review).unwrap().as_str());
 
-    populate_span_with_admission_request_data(&admission_review.request);
+    let version = admission_review_version(Some(&policy_id), &admission_review)?;
+    let adm_req = admission_review.into_admission_request(version);
+    populate_span_with_admission_request_data(&adm_req);
 
     let response = acquire_semaphore_and_evaluate(
         state,
         policy_id,
-        ValidateRequest::AdmissionRequest(Box::new(admission_review.request)),
+        ValidateRequest::AdmissionRequest(Box::new(adm_req)),
         RequestOrigin::Audit,
     )
-    .await
-    .map_err(handle_evaluation_error)?;
+    .await?;
 
     populate_span_with_policy_evaluation_results(&response);
 
-    Ok(Json(AdmissionReviewResponse::new(response)))
+    Ok(Json(AdmissionReviewResponse::new(version, response)))
+}
+
+#[tracing::instrument(
+    name = "audit_batch",
+    fields(
+        host=crate::config::HOSTNAME.as_str(),
+        resources=tracing::field::Empty,
+        policy_variant=tracing::field::Empty,
+        error_code=tracing::field::Empty,
+    ),
+    skip_all)]
+/// Audit many resources with a single call. The results are streamed as newline-delimited
+/// JSON, one line per resource, in the order of the resources. The resources are evaluated
+/// concurrently, see `audit_batch_results`.
+pub(crate) async fn audit_batch_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    JsonExtractor(audit_batch): JsonExtractor<AuditBatchRequest>,
+) -> Result<impl IntoResponse, (StatusCode, ApiError)> {
+    Span::current().record("resources", audit_batch.resources.len());
+
+    if let Some(index) = audit_batch
+        .resources
+        .iter()
+        .position(|resource| resource.policies(&audit_batch.policies).is_empty())
+    {
+        return Err(evaluation_error(
+            None,
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidRequest,
+            format!("resource {index} is not evaluated by any policy"),
+        ));
+    }
+
+    let span = Span::current();
+    let pool_size = state.pool_size;
+    let results = audit_batch_results(audit_batch, pool_size, move |policy_id, adm_req| {
+        let state = state.clone();
+        async move {
+            match acquire_semaphore_and_evaluate(
+                state,
+                policy_id.clone(),
+                ValidateRequest::AdmissionRequest(Box::new(adm_req)),
+                RequestOrigin::Audit,
+            )
+            .await
+            {
+                Ok(response) => AuditPolicyResult::from_response(policy_id, response),
+                Err((_, api_error)) => AuditPolicyResult::from_error(
+                    policy_id,
+                    AuditPolicyError {
+                        code: api_error.code.to_string(),
+                        message: api_error.message,
+                    },
+                ),
+            }
+        }
+        .instrument(span.clone())
+    })
+    .map(|audit_batch_result| {
+        let mut line = serde_json::to_vec(&audit_batch_result)
+            .expect("an AuditBatchResult can always be serialized");
+        line.push(b'\n');
+        Ok::<_, Infallible>(line)
+    });
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(results),
+    ))
+}
+
+/// Evaluate each resource of an audit batch by each of its policies. The evaluations of the
+/// whole batch are run concurrently, at most `pool_size` at a time: a resource evaluated by
+/// many policies doesn't queue more evaluations than the workers can run. The results are
+/// regrouped per resource, in the order of the resources and of their policies.
+fn audit_batch_results<F, Fut>(
+    audit_batch: AuditBatchRequest,
+    pool_size: usize,
+    evaluate_policy: F,
+) -> impl Stream<Item = AuditBatchResult>
+where
+    F: Fn(String, AdmissionRequest) -> Fut,
+    Fut: Future<Output = AuditPolicyResult>,
+{
+    let batch_policies = audit_batch.policies;
+    stream::iter(audit_batch.resources)
+        .flat_map(move |resource| {
+            let policies = resource.policies(&batch_policies);
+            let policy_count = policies.len();
+            let adm_req = Arc::new(resource.request);
+            stream::iter(
+                policies
+                    .into_iter()
+                    .map(move |policy_id| (adm_req.clone(), policy_count, policy_id)),
+            )
+        })
+        .map(move |(adm_req, policy_count, policy_id)| {
+            let result = evaluate_policy(policy_id, adm_req.as_ref().clone());
+            async move { (adm_req, policy_count, result.await) }
+        })
+        .buffered(pool_size)
+        // the results of a resource are consecutive, the resource is complete once all its
+        // policies are done
+        .scan(Vec::new(), |results, (adm_req, policy_count, result)| {
+            results.push(result);
+            let audit_batch_result = (results.len() == policy_count).then(|| AuditBatchResult {
+                uid: adm_req.uid.clone(),
+                kind: adm_req.kind.kind.clone(),
+                namespace: adm_req.namespace.clone(),
+                name: adm_req.name.clone(),
+                results: std::mem::take(results),
+            });
+            future::ready(Some(audit_batch_result))
+        })
+        .filter_map(future::ready)
 }
 
 // note about tracing: we are manually adding the `policy_id` field
@@ -100,6 +225,7 @@ pub(crate) async fn audit_handler(
         request_uid=tracing::field::Empty,
         host=crate::config::HOSTNAME.as_str(),
         policy_id=policy_id.as_str(),
+        policy_variant=tracing::field::Empty,
         name=tracing::field::Empty,
         namespace=tracing::field::Empty,
         operation=tracing::field::Empty,
@@ -114,6 +240,7 @@ pub(crate) async fn audit_handler(
         mutated=tracing::field::Empty,
         response_code=tracing::field::Empty,
         response_message=tracing::field::Empty,
+        error_code=tracing::field::Empty,
     ),
     skip_all)]
 /// Validate a request against a policy.
@@ -124,20 +251,65 @@ pub(crate) async fn validate_handler(
 ) -> Result<Json<AdmissionReviewResponse>, (StatusCode, ApiError)> {
     debug!(admission_review = %serde_json::to_string(&admission_review).unwrap().as_str());
 
-    populate_span_with_admission_request_data(&admission_review.request);
+    let version = admission_review_version(Some(&policy_id), &admission_review)?;
+    let adm_req = admission_review.into_admission_request(version);
+    populate_span_with_admission_request_data(&adm_req);
 
     let response = acquire_semaphore_and_evaluate(
         state,
         policy_id,
-        ValidateRequest::AdmissionRequest(Box::new(admission_review.request)),
+        ValidateRequest::AdmissionRequest(Box::new(adm_req)),
         RequestOrigin::Validate,
     )
-    .await
-    .map_err(handle_evaluation_error)?;
+    .await?;
 
     populate_span_with_policy_evaluation_results(&response);
 
-    Ok(Json(AdmissionReviewResponse::new(response)))
+    Ok(Json(AdmissionReviewResponse::new(version, response)))
+}
+
+#[tracing::instrument(
+    name = "validation_all",
+    fields(
+        request_uid=tracing::field::Empty,
+        host=crate::config::HOSTNAME.as_str(),
+        policies=tracing::field::Empty,
+        name=tracing::field::Empty,
+        namespace=tracing::field::Empty,
+        operation=tracing::field::Empty,
+        subresource=tracing::field::Empty,
+        kind_group=tracing::field::Empty,
+        kind_version=tracing::field::Empty,
+        kind=tracing::field::Empty,
+        resource_group=tracing::field::Empty,
+        resource_version=tracing::field::Empty,
+        resource=tracing::field::Empty,
+        allowed=tracing::field::Empty,
+        mutated=tracing::field::Empty,
+        response_code=tracing::field::Empty,
+        response_message=tracing::field::Empty,
+        error_code=tracing::field::Empty,
+    ),
+    skip_all)]
+/// Validate a request against all the policies whose rules match it.
+pub(crate) async fn validate_all_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    JsonExtractor(admission_review): JsonExtractor<AdmissionReviewRequest>,
+) -> Result<Json<AdmissionReviewResponse>, (StatusCode, ApiError)> {
+    debug!(admission_review = %serde_json::to_string(&admission_review).unwrap().as_str());
+
+    let version = admission_review_version(None, &admission_review)?;
+    let adm_req = admission_review.into_admission_request(version);
+    populate_span_with_admission_request_data(&adm_req);
+
+    let response = acquire_semaphore_and_run(state, None, move |evaluation_environment| {
+        evaluate_matching_policies(evaluation_environment, &adm_req)
+    })
+    .await?;
+
+    populate_span_with_policy_evaluation_results(&response);
+
+    Ok(Json(AdmissionReviewResponse::new(version, response)))
 }
 
 #[tracing::instrument(
@@ -146,16 +318,18 @@ pub(crate) async fn validate_handler(
         request_uid=tracing::field::Empty,
         host=crate::config::HOSTNAME.as_str(),
         policy_id=policy_id.as_str(),
+        policy_variant=tracing::field::Empty,
         allowed=tracing::field::Empty,
         mutated=tracing::field::Empty,
         response_code=tracing::field::Empty,
         response_message=tracing::field::Empty,
+        error_code=tracing::field::Empty,
     ),
     skip_all)]
 pub(crate) async fn validate_raw_handler(
     extract::State(state): extract::State<Arc<ApiServerState>>,
     extract::Path(policy_id): extract::Path<String>,
-    extract::Json(raw_review): extract::Json<RawReviewRequest>,
+    JsonExtractor(raw_review): JsonExtractor<RawReviewRequest>,
 ) -> Result<Json<RawReviewResponse>, (StatusCode, ApiError)> {
     debug!(raw_review = %serde_json::to_string(&raw_review).unwrap().as_str());
 
@@ -165,14 +339,103 @@ pub(crate) async fn validate_raw_handler(
         ValidateRequest::Raw(raw_review.request),
         RequestOrigin::Validate,
     )
-    .await
-    .map_err(handle_evaluation_error)?;
+    .await?;
 
     populate_span_with_policy_evaluation_results(&response);
 
     Ok(Json(RawReviewResponse::new(response)))
 }
 
+#[tracing::instrument(
+    name = "validation_raw_batch",
+    fields(
+        host=crate::config::HOSTNAME.as_str(),
+        policy_id=policy_id.as_str(),
+        policy_variant=tracing::field::Empty,
+        error_code=tracing::field::Empty,
+    ),
+    skip_all)]
+/// Validate a batch of raw requests, provided either as a JSON array or as newline-delimited
+/// JSON. One `RawReviewResponse` per line is streamed back, in the order of the requests.
+/// The requests are evaluated concurrently, bounded by the size of the worker pool. The
+/// requests that cannot be evaluated are rejected with their error code, see
+/// `ErrorCode::add_to_rejection`.
+pub(crate) async fn validate_raw_batch_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    extract::Path(policy_id): extract::Path<String>,
+    body: Body,
+) -> Result<impl IntoResponse, (StatusCode, ApiError)> {
+    // Unknown policies are reported once, before reading the body. The policies that could
+    // not be registered are known: like with `/validate_raw`, each request of the batch is
+    // rejected with their initialization error.
+    policy_id
+        .parse()
+        .and_then(|id| {
+            if state
+                .evaluation_environment
+                .get_policy_initialization_error(&id)
+                .is_some()
+            {
+                return Ok(());
+            }
+            state
+                .evaluation_environment
+                .get_policy_mode(&id)
+                .map(|_| ())
+        })
+        .map_err(|e| handle_evaluation_error(Some(&policy_id), e))?;
+
+    let span = Span::current();
+    let pool_size = state.pool_size;
+    let responses = parse_raw_review_batch(body.into_data_stream(), BatchLimits::default())
+        .map(move |raw_review| {
+            let state = state.clone();
+            let policy_id = policy_id.clone();
+            async move {
+                let response = match raw_review {
+                    Ok(raw_review) => {
+                        let validate_request = ValidateRequest::Raw(raw_review.request);
+                        let uid = validate_request.uid().to_owned();
+                        acquire_semaphore_and_evaluate(
+                            state,
+                            policy_id,
+                            validate_request,
+                            RequestOrigin::Validate,
+                        )
+                        .await
+                        .unwrap_or_else(|(status, api_error)| {
+                            let mut response =
+                                AdmissionResponse::reject(uid, api_error.message, status.as_u16());
+                            api_error.code.add_to_rejection(&mut response);
+                            response
+                        })
+                    }
+                    Err(message) => {
+                        let mut response = AdmissionResponse::reject(
+                            String::new(),
+                            message,
+                            StatusCode::BAD_REQUEST.as_u16(),
+                        );
+                        ErrorCode::InvalidRequest.add_to_rejection(&mut response);
+                        response
+                    }
+                };
+
+                let mut line = serde_json::to_vec(&RawReviewResponse::new(response))
+                    .expect("a RawReviewResponse can always be serialized");
+                line.push(b'\n');
+                Ok::<_, Infallible>(line)
+            }
+            .instrument(span.clone())
+        })
+        .buffered(pool_size);
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(responses),
+    ))
+}
+
 pub(crate) async fn readiness_handler() -> StatusCode {
     StatusCode::OK
 }
@@ -253,38 +516,187 @@ pub(crate) async fn pprof_get_heap()
     Ok((headers, pprof))
 }
 
+/// Report the state of the break-glass override
+pub(crate) async fn break_glass_status_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    headers: header::HeaderMap,
+) -> Result<Json<BreakGlassStatus>, (StatusCode, ApiError)> {
+    authorize_break_glass_request(&state, &headers)?;
+
+    break_glass::status()
+        .map(Json)
+        .map_err(handle_break_glass_error)
+}
+
+/// Force the policies into monitor mode, until the override expires or is deactivated
+pub(crate) async fn break_glass_activate_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    headers: header::HeaderMap,
+    JsonExtractor(activation): JsonExtractor<BreakGlassActivation>,
+) -> Result<Json<BreakGlassStatus>, (StatusCode, ApiError)> {
+    authorize_break_glass_request(&state, &headers)?;
+    activation.validate().map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            ApiError {
+                status: StatusCode::BAD_REQUEST,
+                code: ErrorCode::InvalidRequest,
+                message: e.to_string(),
+            },
+        )
+    })?;
+
+    break_glass::activate(activation)
+        .map(Json)
+        .map_err(handle_break_glass_error)
+}
+
+/// Restore the mode of the policies
+pub(crate) async fn break_glass_deactivate_handler(
+    extract::State(state): extract::State<Arc<ApiServerState>>,
+    headers: header::HeaderMap,
+) -> Result<Json<BreakGlassStatus>, (StatusCode, ApiError)> {
+    authorize_break_glass_request(&state, &headers)?;
+
+    break_glass::deactivate()
+        .map(Json)
+        .map_err(handle_break_glass_error)
+}
+
+/// Ensure the request carries the bearer token of the break-glass endpoint. The tokens are
+/// compared in constant time.
+fn authorize_break_glass_request(
+    state: &ApiServerState,
+    headers: &header::HeaderMap,
+) -> Result<(), (StatusCode, ApiError)> {
+    let expected = state.break_glass_token.as_deref().unwrap_or_default();
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    let authorized = !expected.is_empty()
+        && token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0;
+    if authorized {
+        return Ok(());
+    }
+
+    Err((
+        StatusCode::UNAUTHORIZED,
+        ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            code: ErrorCode::Unauthorized,
+            message: "invalid break-glass token".to_owned(),
+        },
+    ))
+}
+
 async fn acquire_semaphore_and_evaluate(
     state: Arc<ApiServerState>,
     policy_id: String,
     validate_request: ValidateRequest,
     request_origin: RequestOrigin,
-) -> Result<AdmissionResponse, EvaluationError> {
-    let _permit = state
-        .semaphore
-        .acquire()
-        .await
-        .expect("semaphore acquire failed");
+) -> Result<AdmissionResponse, (StatusCode, ApiError)> {
+    let evaluated_policy_id = policy_id.clone();
+    acquire_semaphore_and_run(
+        state,
+        Some(&evaluated_policy_id),
+        move |evaluation_environment| {
+            evaluate(
+                evaluation_environment,
+                &policy_id,
+                &validate_request,
+                request_origin,
+            )
+        },
+    )
+    .await
+}
+
+/// Run the given evaluation on a blocking thread, once the semaphore grants the permission.
+/// The errors are attributed to the given policy, when the evaluation targets a single one.
+async fn acquire_semaphore_and_run<F>(
+    state: Arc<ApiServerState>,
+    policy_id: Option<&str>,
+    evaluation: F,
+) -> Result<AdmissionResponse, (StatusCode, ApiError)>
+where
+    F: FnOnce(Arc<EvaluationEnvironment>) -> Result<AdmissionResponse, EvaluationError>
+        + Send
+        + 'static,
+{
+    let acquire = state.semaphore.acquire();
+    let acquired = match state.queue_timeout {
+        Some(queue_timeout) => time::timeout(queue_timeout, acquire).await,
+        None => Ok(acquire.await),
+    };
+    let _permit = match acquired {
+        Ok(Ok(permit)) => permit,
+        Ok(Err(e)) => {
+            error!("cannot acquire the evaluation permit: {}", e);
+            return Err(evaluation_error(
+                policy_id,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::Internal,
+                "Something went wrong".to_owned(),
+            ));
+        }
+        Err(_) => {
+            return Err(evaluation_error(
+                policy_id,
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorCode::Overloaded,
+                "the server cannot accept more evaluations".to_owned(),
+            ));
+        }
+    };
 
     let state = state.clone();
     let span = Span::current();
     let response = task::spawn_blocking(move || {
         let _enter = span.enter();
 
-        evaluate(
-            state.evaluation_environment.clone(),
-            &policy_id,
-            &validate_request,
-            request_origin,
-        )
+        evaluation(state.evaluation_environment.clone())
     })
     .await
-    .expect("task::spawn_blocking failed")?;
+    .map_err(|e| {
+        error!("evaluation task failed: {}", e);
+        evaluation_error(
+            policy_id,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::Internal,
+            "Something went wrong".to_owned(),
+        )
+    })?
+    .map_err(|e| handle_evaluation_error(policy_id, e))?;
 
     debug!(response =? &response, "policy evaluated");
 
     Ok(response)
 }
 
+/// Returns the version of the given review. The reviews of unknown kinds or versions are
+/// rejected, instead of being evaluated as if they were `admission.k8s.io/v1` ones.
+fn admission_review_version(
+    policy_id: Option<&str>,
+    admission_review: &AdmissionReviewRequest,
+) -> Result<AdmissionReviewVersion, (StatusCode, ApiError)> {
+    admission_review.version().map_err(|e| {
+        evaluation_error(
+            policy_id,
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidRequest,
+            e.to_string(),
+        )
+    })
+}
+
 fn populate_span_with_admission_request_data(adm_req: &AdmissionRequest) {
     Span::current().record("kind", adm_req.kind.kind.as_str());
     Span::current().record("kind_group", adm_req.kind.group.as_str());
@@ -318,27 +730,78 @@ fn populate_span_with_policy_evaluation_results(response: &AdmissionResponse) {
     }
 }
 
-fn handle_evaluation_error(error: EvaluationError) -> (StatusCode, ApiError) {
-    match error {
+/// Convert the evaluation error into an API error. The details of the failures happening
+/// inside of the server are logged, they are not returned to the client.
+fn handle_evaluation_error(
+    policy_id: Option<&str>,
+    error: EvaluationError,
+) -> (StatusCode, ApiError) {
+    let (status, code, message) = match &error {
         EvaluationError::PolicyNotFound(_) => (
             StatusCode::NOT_FOUND,
-            ApiError {
-                status: StatusCode::NOT_FOUND,
-                message: error.to_string(),
-            },
+            ErrorCode::PolicyNotFound,
+            error.to_string(),
         ),
-        err => {
-            error!("Evaluation error: {}", err);
-
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ApiError {
-                    status: StatusCode::INTERNAL_SERVER_ERROR,
-                    message: "Something went wrong".to_owned(),
-                },
-            )
-        }
+        EvaluationError::InvalidPolicyId(_) => (
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidRequest,
+            error.to_string(),
+        ),
+        // The requests evaluated by a policy that could not be initialized are rejected with
+        // the error code, see `evaluate`. These errors are raised outside of that evaluation,
+        // like while verifying a mutation.
+        EvaluationError::PolicyInitialization(_) | EvaluationError::BootstrapFailure(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::PolicyInitializationFailed,
+            "the policy could not be initialized".to_owned(),
+        ),
+        // These errors are raised while rehydrating the policy, before evaluating it. The
+        // timeouts and the traps are reported by the error code of the rejection, see
+        // `evaluate`
+        EvaluationError::WebAssemblyError(_) | EvaluationError::CannotRehydratePolicyGroup(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::PolicyInitializationFailed,
+            "the policy could not be initialized".to_owned(),
+        ),
+    };
+    if status.is_server_error() {
+        error!(error_code = %code, "Evaluation error: {}", error);
     }
+
+    evaluation_error(policy_id, status, code, message)
+}
+
+/// Build the error returned when a request cannot be evaluated. The error code is recorded
+/// on the current span and in the metrics.
+fn evaluation_error(
+    policy_id: Option<&str>,
+    status: StatusCode,
+    code: ErrorCode,
+    message: String,
+) -> (StatusCode, ApiError) {
+    code.record(policy_id);
+
+    (
+        status,
+        ApiError {
+            status,
+            code,
+            message,
+        },
+    )
+}
+
+fn handle_break_glass_error(error: anyhow::Error) -> (StatusCode, ApiError) {
+    error!("break-glass error: {}", error);
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: ErrorCode::Internal,
+            message: "Something went wrong".to_owned(),
+        },
+    )
 }
 
 fn handle_pprof_error(error: ReportGenerationError) -> (StatusCode, ApiError) {
@@ -348,7 +811,216 @@ fn handle_pprof_error(error: ReportGenerationError) -> (StatusCode, ApiError) {
         StatusCode::INTERNAL_SERVER_ERROR,
         ApiError {
             status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: ErrorCode::Internal,
             message: "Something went wrong".to_owned(),
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    use crate::{api::audit_batch::AuditBatchResource, test_utils::build_admission_review_request};
+
+    fn api_server_state(permits: usize) -> Arc<ApiServerState> {
+        Arc::new(ApiServerState {
+            semaphore: Semaphore::new(permits),
+            pool_size: permits,
+            queue_timeout: Some(Duration::from_millis(10)),
+            evaluation_environment: Arc::new(EvaluationEnvironment::default()),
+            break_glass_token: None,
+        })
+    }
+
+    #[rstest]
+    #[case::policy_not_found(
+        EvaluationError::PolicyNotFound("policy".to_owned()),
+        StatusCode::NOT_FOUND,
+        ErrorCode::PolicyNotFound
+    )]
+    #[case::invalid_policy_id(
+        EvaluationError::InvalidPolicyId("policy/".to_owned()),
+        StatusCode::BAD_REQUEST,
+        ErrorCode::InvalidRequest
+    )]
+    #[case::policy_initialization(
+        EvaluationError::PolicyInitialization("invalid settings".to_owned()),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorCode::PolicyInitializationFailed
+    )]
+    #[case::bootstrap_failure(
+        EvaluationError::BootstrapFailure("cannot download".to_owned()),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorCode::PolicyInitializationFailed
+    )]
+    #[case::rehydration_failure(
+        EvaluationError::WebAssemblyError("cannot deserialize the module".to_owned()),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorCode::PolicyInitializationFailed
+    )]
+    #[case::policy_group_rehydration_failure(
+        EvaluationError::CannotRehydratePolicyGroup("group".to_owned()),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorCode::PolicyInitializationFailed
+    )]
+    fn evaluation_error_codes(
+        #[case] error: EvaluationError,
+        #[case] expected_status: StatusCode,
+        #[case] expected_code: ErrorCode,
+    ) {
+        let (status, api_error) = handle_evaluation_error(Some("policy"), error);
+
+        assert_eq!(expected_status, status);
+        assert_eq!(expected_status, api_error.status);
+        assert_eq!(expected_code, api_error.code);
+    }
+
+    #[tokio::test]
+    async fn evaluation_is_rejected_when_no_worker_becomes_free() {
+        let state = api_server_state(1);
+        let _busy_worker = state.semaphore.acquire().await.unwrap();
+
+        let (status, api_error) = acquire_semaphore_and_run(state.clone(), Some("policy"), |_| {
+            panic!("the evaluation should not run")
+        })
+        .await
+        .expect_err("the server should be overloaded");
+
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+        assert_eq!(ErrorCode::Overloaded, api_error.code);
+    }
+
+    #[tokio::test]
+    async fn evaluation_failing_unexpectedly_is_an_internal_error() {
+        let state = api_server_state(1);
+
+        let (status, api_error) = acquire_semaphore_and_run(state.clone(), Some("policy"), |_| {
+            panic!("unexpected failure")
+        })
+        .await
+        .expect_err("the evaluation should fail");
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status);
+        assert_eq!(ErrorCode::Internal, api_error.code);
+        assert_eq!("Something went wrong", api_error.message);
+    }
+
+    #[rstest]
+    #[case::missing_token(None)]
+    #[case::wrong_token(Some("Bearer wrong"))]
+    #[case::not_a_bearer_token(Some("secret"))]
+    fn break_glass_requests_without_the_token_are_unauthorized(
+        #[case] authorization: Option<&str>,
+    ) {
+        let state = ApiServerState {
+            break_glass_token: Some("secret".to_owned()),
+            ..Arc::into_inner(api_server_state(1)).unwrap()
+        };
+        let mut headers = header::HeaderMap::new();
+        if let Some(authorization) = authorization {
+            headers.insert(header::AUTHORIZATION, authorization.parse().unwrap());
+        }
+
+        let (status, api_error) = authorize_break_glass_request(&state, &headers)
+            .expect_err("the request should not be authorized");
+
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!(ErrorCode::Unauthorized, api_error.code);
+    }
+
+    #[tokio::test]
+    async fn evaluation_runs_once_a_worker_is_free() {
+        let state = api_server_state(1);
+
+        let response = acquire_semaphore_and_run(state.clone(), Some("policy"), |_| {
+            Ok(AdmissionResponse {
+                allowed: true,
+                ..Default::default()
+            })
+        })
+        .await
+        .expect("the evaluation should run");
+
+        assert!(response.allowed);
+        assert_eq!(1, state.semaphore.available_permits());
+    }
+
+    #[tokio::test]
+    async fn audit_batch_evaluations_are_bounded_by_the_pool_size() {
+        let pool_size = 2;
+        let resource = |uid: &str, policies: Vec<String>| {
+            let mut request = build_admission_review_request().request;
+            request.uid = uid.to_owned();
+            AuditBatchResource { policies, request }
+        };
+        // more policies than workers, for each resource
+        let audit_batch = AuditBatchRequest {
+            policies: (1..=5).map(|i| format!("policy{i}")).collect(),
+            resources: vec![
+                resource("resource1", Vec::new()),
+                resource("resource2", vec!["policy6".to_owned()]),
+                resource("resource3", vec!["policy1".to_owned()]),
+            ],
+        };
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let audit_batch_results: Vec<AuditBatchResult> =
+            audit_batch_results(audit_batch, pool_size, |policy_id, adm_req| {
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                async move {
+                    let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(running, Ordering::SeqCst);
+                    time::sleep(Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    AuditPolicyResult::from_response(
+                        policy_id,
+                        AdmissionResponse {
+                            uid: adm_req.uid,
+                            allowed: true,
+                            ..Default::default()
+                        },
+                    )
+                }
+            })
+            .collect()
+            .await;
+
+        assert_eq!(pool_size, max_in_flight.load(Ordering::SeqCst));
+        let evaluated: Vec<(String, Vec<String>)> = audit_batch_results
+            .into_iter()
+            .map(|audit_batch_result| {
+                let policy_ids = audit_batch_result
+                    .results
+                    .into_iter()
+                    .map(|result| {
+                        assert_eq!(
+                            audit_batch_result.uid,
+                            result.response.expect("should have a response").uid
+                        );
+                        result.policy_id
+                    })
+                    .collect();
+                (audit_batch_result.uid, policy_ids)
+            })
+            .collect();
+        let policies =
+            |ids: &[usize]| -> Vec<String> { ids.iter().map(|i| format!("policy{i}")).collect() };
+        assert_eq!(
+            vec![
+                ("resource1".to_owned(), policies(&[1, 2, 3, 4, 5])),
+                ("resource2".to_owned(), policies(&[1, 2, 3, 4, 5, 6])),
+                ("resource3".to_owned(), policies(&[1, 2, 3, 4, 5])),
+            ],
+            evaluated
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use std::{collections::VecDeque, fmt};
+
+use axum::body::Bytes;
+use futures::{Stream, StreamExt, stream};
 use policy_evaluator::admission_response::AdmissionResponse;
 use serde::{Deserialize, Serialize};
 
@@ -18,3 +22,314 @@ impl RawReviewResponse {
         RawReviewResponse { response }
     }
 }
+
+/// The maximum size of the body of a batch of raw review requests
+const RAW_REVIEW_BATCH_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// The maximum size of each raw review request of a newline-delimited batch, the same as
+/// the one of the requests sent to `/validate_raw`
+const RAW_REVIEW_BATCH_LINE_LIMIT: usize = 2 * 1024 * 1024;
+
+/// The limits protecting the server from the batches that don't fit in memory
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BatchLimits {
+    /// The maximum size of the whole body
+    pub(crate) body: usize,
+    /// The maximum size of a line of a newline-delimited batch
+    pub(crate) line: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        BatchLimits {
+            body: RAW_REVIEW_BATCH_BODY_LIMIT,
+            line: RAW_REVIEW_BATCH_LINE_LIMIT,
+        }
+    }
+}
+
+/// The formats accepted by the batch endpoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BatchFormat {
+    /// A JSON array of requests
+    Array,
+    /// One request per line
+    NewlineDelimited,
+}
+
+struct BatchParser<S> {
+    body: S,
+    limits: BatchLimits,
+    format: Option<BatchFormat>,
+    buffer: Vec<u8>,
+    /// The number of bytes of the body received so far
+    received: usize,
+    /// The beginning of the current line exceeded the limit, the rest of the line is dropped
+    skipping_line: bool,
+    parsed: VecDeque<Result<RawReviewRequest, String>>,
+    done: bool,
+}
+
+impl<S> BatchParser<S> {
+    /// Parse the complete lines received so far. With `flush`, the trailing line is
+    /// parsed too, even if it isn't terminated by a newline.
+    ///
+    /// The lines exceeding the limit produce an error, without being kept in memory.
+    fn parse_lines(&mut self, flush: bool) {
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            if std::mem::take(&mut self.skipping_line) {
+                continue;
+            }
+            self.parse_line(&line);
+        }
+        if self.buffer.len() > self.limits.line {
+            if !self.skipping_line {
+                self.push_line_too_long();
+            }
+            self.skipping_line = true;
+            self.buffer.clear();
+        }
+        if flush {
+            let line = std::mem::take(&mut self.buffer);
+            if !std::mem::take(&mut self.skipping_line) {
+                self.parse_line(&line);
+            }
+        }
+    }
+
+    fn parse_line(&mut self, line: &[u8]) {
+        if line.trim_ascii().is_empty() {
+            return;
+        }
+        if line.len() > self.limits.line {
+            self.push_line_too_long();
+            return;
+        }
+        self.parsed.push_back(
+            serde_json::from_slice(line).map_err(|e| format!("invalid raw review request: {e}")),
+        );
+    }
+
+    fn push_line_too_long(&mut self) {
+        self.parsed.push_back(Err(format!(
+            "the raw review request exceeds the limit of {} bytes",
+            self.limits.line
+        )));
+    }
+
+    fn parse_array(&mut self) {
+        match serde_json::from_slice::<Vec<RawReviewRequest>>(&self.buffer) {
+            Ok(requests) => self.parsed.extend(requests.into_iter().map(Ok)),
+            Err(e) => self
+                .parsed
+                .push_back(Err(format!("invalid raw review requests: {e}"))),
+        }
+        self.buffer.clear();
+    }
+}
+
+/// Split the body of a batch into the raw review requests it holds. The body is either a
+/// JSON array, which is parsed once it has been completely received, or a stream of
+/// newline-delimited JSON documents, which are parsed as soon as each line is received.
+///
+/// A line that cannot be parsed, or that exceeds the limit, produces an error, the following
+/// lines are still parsed. A body exceeding the limit produces an error, and the parsing stops.
+pub(crate) fn parse_raw_review_batch<S, E>(
+    body: S,
+    limits: BatchLimits,
+) -> impl Stream<Item = Result<RawReviewRequest, String>>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: fmt::Display,
+{
+    let parser = BatchParser {
+        body,
+        limits,
+        format: None,
+        buffer: Vec::new(),
+        received: 0,
+        skipping_line: false,
+        parsed: VecDeque::new(),
+        done: false,
+    };
+
+    stream::unfold(parser, |mut parser| async move {
+        loop {
+            if let Some(request) = parser.parsed.pop_front() {
+                return Some((request, parser));
+            }
+            if parser.done {
+                return None;
+            }
+
+            match parser.body.next().await {
+                Some(Ok(chunk)) => {
+                    parser.received += chunk.len();
+                    if parser.received > parser.limits.body {
+                        parser.parsed.push_back(Err(format!(
+                            "the request body exceeds the limit of {} bytes",
+                            parser.limits.body
+                        )));
+                        parser.buffer.clear();
+                        parser.done = true;
+                        continue;
+                    }
+                    parser.buffer.extend_from_slice(&chunk);
+                    if parser.format.is_none() {
+                        parser.format = parser
+                            .buffer
+                            .iter()
+                            .find(|byte| !byte.is_ascii_whitespace())
+                            .map(|byte| match byte {
+                                b'[' => BatchFormat::Array,
+                                _ => BatchFormat::NewlineDelimited,
+                            });
+                    }
+                    if parser.format == Some(BatchFormat::NewlineDelimited) {
+                        parser.parse_lines(false);
+                    }
+                }
+                Some(Err(e)) => {
+                    parser
+                        .parsed
+                        .push_back(Err(format!("cannot read the request body: {e}")));
+                    parser.done = true;
+                }
+                None => {
+                    match parser.format {
+                        Some(BatchFormat::Array) => parser.parse_array(),
+                        Some(BatchFormat::NewlineDelimited) => parser.parse_lines(true),
+                        None => {}
+                    }
+                    parser.done = true;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::convert::Infallible;
+
+    async fn parse(chunks: &[&str]) -> Vec<Result<serde_json::Value, String>> {
+        parse_with_limits(chunks, BatchLimits::default()).await
+    }
+
+    async fn parse_with_limits(
+        chunks: &[&str],
+        limits: BatchLimits,
+    ) -> Vec<Result<serde_json::Value, String>> {
+        let body = stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok::<_, Infallible>(Bytes::from(chunk.to_string())))
+                .collect::<Vec<_>>(),
+        );
+
+        parse_raw_review_batch(body, limits)
+            .map(|request| request.map(|request| request.request))
+            .collect()
+            .await
+    }
+
+    #[rstest]
+    #[case::array(&[r#"[{"request": {"id": 1}}, {"request": {"id": 2}}]"#])]
+    #[case::array_split_across_chunks(&[r#" [{"request": {"id""#, r#": 1}}, {"request": {"id": 2}}]"#])]
+    #[case::newline_delimited(&["{\"request\": {\"id\": 1}}\n{\"request\": {\"id\": 2}}\n"])]
+    #[case::newline_delimited_without_trailing_newline(&["{\"request\": {\"id\": 1}}\n{\"request\": {\"id\": 2}}"])]
+    #[case::newline_delimited_split_across_chunks(&["{\"request\": {\"i", "d\": 1}}\n\n{\"request\"", ": {\"id\": 2}}\n"])]
+    #[tokio::test]
+    async fn parse_batch(#[case] chunks: &[&str]) {
+        let requests = parse(chunks).await;
+
+        assert_eq!(
+            vec![
+                Ok(serde_json::json!({"id": 1})),
+                Ok(serde_json::json!({"id": 2}))
+            ],
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_parsing_after_an_invalid_line() {
+        let requests =
+            parse(&["{\"request\": {\"id\": 1}}\nnot json\n{\"request\": {\"id\": 2}}\n"]).await;
+
+        assert_eq!(3, requests.len());
+        assert!(requests[1].is_err());
+        assert_eq!(Ok(serde_json::json!({"id": 2})), requests[2]);
+    }
+
+    #[rstest]
+    #[case::empty_body(&[])]
+    #[case::blank_body(&["\n  \n"])]
+    #[case::empty_array(&["[]"])]
+    #[tokio::test]
+    async fn parse_empty_batch(#[case] chunks: &[&str]) {
+        assert!(parse(chunks).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn invalid_array() {
+        let requests = parse(&[r#"[{"request": {"id": 1}}"#]).await;
+
+        assert_eq!(1, requests.len());
+        assert!(requests[0].is_err());
+    }
+
+    #[rstest]
+    #[case::array(&[r#"[{"request": {"id": 1}}, "#, r#"{"request": {"id": 2}}]"#], 0)]
+    #[case::newline_delimited(&["{\"request\": {\"id\": 1}}\n", "{\"request\": {\"id\": 2}}\n"], 1)]
+    #[tokio::test]
+    async fn body_exceeding_the_limit(#[case] chunks: &[&str], #[case] parsed_requests: usize) {
+        let limits = BatchLimits {
+            body: chunks[0].len(),
+            line: 1024,
+        };
+
+        let requests = parse_with_limits(chunks, limits).await;
+
+        assert_eq!(parsed_requests + 1, requests.len());
+        assert!(requests[..parsed_requests].iter().all(Result::is_ok));
+        assert_eq!(
+            Err(format!(
+                "the request body exceeds the limit of {} bytes",
+                chunks[0].len()
+            )),
+            requests[parsed_requests]
+        );
+    }
+
+    #[rstest]
+    #[case::complete_line(&["{\"request\": {\"id\": 1, \"padding\": \"too long\"}}\n{\"request\": {\"id\": 2}}\n"])]
+    #[case::line_split_across_chunks(&[
+        "{\"request\": {\"id\": 1, ",
+        "\"padding\": \"too long\"",
+        "}}\n{\"request\": {\"id\": 2}}",
+    ])]
+    #[tokio::test]
+    async fn line_exceeding_the_limit(#[case] chunks: &[&str]) {
+        let limits = BatchLimits {
+            body: 1024,
+            line: r#"{"request": {"id": 2}}"#.len() + 1,
+        };
+
+        let requests = parse_with_limits(chunks, limits).await;
+
+        assert_eq!(
+            vec![
+                Err(format!(
+                    "the raw review request exceeds the limit of {} bytes",
+                    limits.line
+                )),
+                Ok(serde_json::json!({"id": 2}))
+            ],
+            requests
+        );
+    }
+}
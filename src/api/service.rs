@@ -1,15 +1,49 @@
-use std::{fmt, sync::Arc};
+use std::{collections::HashMap, fmt, sync::Arc};
 
+use anyhow::anyhow;
+use chrono::Utc;
 use policy_evaluator::{
-    admission_response::AdmissionResponse,
+    admission_request::AdmissionRequest,
+    admission_response::{AdmissionResponse, AdmissionResponseStatus, PatchType},
     admission_response_handler::{
         AdmissionResponseHandler, errors::EvaluationError, policy_id::PolicyID,
+        policy_mode::PolicyMode,
     },
     policy_evaluator::ValidateRequest,
 };
-use tokio::time::Instant;
+use tokio::time::{Duration, Instant};
+use tracing::Span;
 
-use crate::{evaluation::EvaluationEnvironment, metrics};
+use crate::{
+    api::api_error::ErrorCode,
+    break_glass,
+    decision_log::{self, DecisionRecord},
+    decision_webhook,
+    evaluation::{
+        EvaluationEnvironment, exemptions::Exemption, patch, policy_failure::PolicyResponse,
+        policy_variant::PolicyVariant,
+    },
+    metrics,
+};
+
+/// The audit annotation describing why the request has been accepted without evaluating the
+/// policy
+const EXEMPTION_AUDIT_ANNOTATION: &str = "exemption";
+
+/// The audit annotation added to the responses of the policies forced into monitor mode by
+/// the break-glass override
+const BREAK_GLASS_AUDIT_ANNOTATION: &str = "break-glass";
+
+/// The audit annotation describing the rejection a policy running in monitor mode would have
+/// returned
+const MONITOR_MODE_VIOLATION_AUDIT_ANNOTATION: &str = "monitor-mode-violation";
+
+/// The audit annotations attributing the decision to the policy that took it
+const POLICY_ID_AUDIT_ANNOTATION: &str = "policy-id";
+const POLICY_MODE_AUDIT_ANNOTATION: &str = "policy-mode";
+const POLICY_MODULE_DIGEST_AUDIT_ANNOTATION: &str = "policy-module-digest";
+const DECISION_AUDIT_ANNOTATION: &str = "decision";
+const EVALUATION_LATENCY_AUDIT_ANNOTATION: &str = "evaluation-latency-ms";
 
 pub(crate) enum RequestOrigin {
     Validate,
@@ -34,65 +68,190 @@ pub(crate) fn evaluate(
     let start_time = Instant::now();
     let policy_id: PolicyID = policy_id.parse()?;
 
-    // Early check for requests from special namespaces
-    if let ValidateRequest::AdmissionRequest(adm_req) = validate_request
-        && let Some(ref req_namespace) = adm_req.namespace
-        && evaluation_environment
-            .should_always_accept_requests_made_inside_of_namespace(req_namespace)
-    {
-        // Record metrics for requests from special namespaces
-        let policy_evaluation_metric = metrics::PolicyEvaluation {
-            policy_name: policy_id.to_string(),
-            policy_mode: evaluation_environment.get_policy_mode(&policy_id)?.into(),
-            resource_namespace: adm_req.clone().namespace,
-            resource_kind: adm_req.clone().request_kind.unwrap_or_default().kind,
-            resource_request_operation: adm_req.clone().operation,
-            accepted: true,
-            mutated: false,
-            request_origin: request_origin.to_string(),
-            error_code: None,
+    // The policies that could not be registered are not known by the other lookups, they
+    // reject the requests with their initialization error. Like for the registered policies,
+    // the exempted requests are accepted first.
+    if let Some(error) = evaluation_environment.get_policy_initialization_error(&policy_id) {
+        let exemption = match validate_request {
+            ValidateRequest::AdmissionRequest(adm_req) => evaluation_environment
+                .get_request_exemption(&policy_id, adm_req)
+                .map(|exemption| (adm_req, exemption)),
+            ValidateRequest::Raw(_) => None,
         };
-        metrics::record_policy_latency(start_time.elapsed(), &policy_evaluation_metric);
-        metrics::add_policy_evaluation(&policy_evaluation_metric);
+        let admission_response = match exemption {
+            Some((adm_req, exemption)) => {
+                // The mode of the policy is unknown, its settings could not be read
+                let policy_evaluation_metric = metrics::PolicyEvaluation {
+                    policy_name: policy_id.to_string(),
+                    policy_mode: String::new(),
+                    policy_variant: PolicyVariant::Stable.to_string(),
+                    resource_namespace: adm_req.namespace.clone(),
+                    resource_kind: adm_req.request_kind.clone().unwrap_or_default().kind,
+                    resource_request_operation: adm_req.operation.clone(),
+                    accepted: true,
+                    mutated: false,
+                    request_origin: request_origin.to_string(),
+                    dry_run: adm_req.dry_run.unwrap_or(false),
+                    error_code: None,
+                    exemption_reason: Some(exemption.reason.to_string()),
+                    break_glass: false,
+                };
+                metrics::record_policy_latency(start_time.elapsed(), &policy_evaluation_metric);
+                metrics::add_policy_evaluation(&policy_evaluation_metric);
 
-        return Ok(AdmissionResponse {
-            uid: validate_request.uid().to_owned(),
-            allowed: true,
-            status: None,
-            patch: None,
-            audit_annotations: None,
-            warnings: None,
-            patch_type: None,
+                exempted_response(validate_request.uid(), Some(exemption))
+            }
+            None => {
+                metrics::add_policy_evaluation(&metrics::PolicyInitializationError {
+                    policy_name: policy_id.to_string(),
+                    initialization_error: error.clone(),
+                });
+
+                policy_initialization_rejection(&policy_id, validate_request.uid(), error)
+            }
+        };
+        // The mode of the policy is unknown, its settings could not be read
+        record_decision(&DecisionRecord {
+            policy_id: policy_id.to_string(),
+            policy_variant: PolicyVariant::Stable.to_string(),
+            request_origin: request_origin.to_string(),
+            raw_allowed: admission_response.allowed,
+            latency_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+            ..DecisionRecord::new(validate_request, &admission_response)
         });
+
+        return Ok(admission_response);
+    }
+
+    let policy_variant =
+        evaluation_environment.select_policy_variant(&policy_id, validate_request)?;
+    Span::current().record("policy_variant", policy_variant.to_string().as_str());
+
+    let mut policy_mode = evaluation_environment.get_policy_mode(&policy_id)?;
+    if let Some(scheduled_policy_mode) =
+        evaluation_environment.get_scheduled_policy_mode(&policy_id, Utc::now())
+    {
+        policy_mode = scheduled_policy_mode;
+    }
+    // During an incident, the break-glass override forces the policies into monitor mode
+    let break_glass = policy_mode == PolicyMode::Protect
+        && break_glass::forces_monitor_mode(&policy_id.to_string());
+    if break_glass {
+        policy_mode = PolicyMode::Monitor;
+    }
+
+    // Early check for exempted requests, or that are not relevant for the policy
+    if let ValidateRequest::AdmissionRequest(adm_req) = validate_request {
+        let exemption = evaluation_environment.get_request_exemption(&policy_id, adm_req);
+        if exemption.is_some()
+            || !evaluation_environment.should_evaluate_request(&policy_id, adm_req)
+        {
+            // Record metrics for requests that are accepted without evaluating the policy
+            let policy_evaluation_metric = metrics::PolicyEvaluation {
+                policy_name: policy_id.to_string(),
+                policy_mode: policy_mode.clone().into(),
+                policy_variant: policy_variant.to_string(),
+                resource_namespace: adm_req.clone().namespace,
+                resource_kind: adm_req.clone().request_kind.unwrap_or_default().kind,
+                resource_request_operation: adm_req.clone().operation,
+                accepted: true,
+                mutated: false,
+                request_origin: request_origin.to_string(),
+                dry_run: adm_req.dry_run.unwrap_or(false),
+                error_code: None,
+                exemption_reason: exemption
+                    .as_ref()
+                    .map(|exemption| exemption.reason.to_string()),
+                break_glass,
+            };
+            metrics::record_policy_latency(start_time.elapsed(), &policy_evaluation_metric);
+            metrics::add_policy_evaluation(&policy_evaluation_metric);
+
+            let admission_response = exempted_response(validate_request.uid(), exemption);
+            record_decision(&DecisionRecord {
+                policy_id: policy_id.to_string(),
+                policy_mode: policy_evaluation_metric.policy_mode,
+                policy_variant: policy_variant.to_string(),
+                request_origin: request_origin.to_string(),
+                raw_allowed: true,
+                latency_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+                break_glass,
+                ..DecisionRecord::new(validate_request, &admission_response)
+            });
+
+            return Ok(admission_response);
+        }
     }
 
-    let vanilla_validation_response = match evaluation_environment
+    let PolicyResponse {
+        response: vanilla_validation_response,
+        failure: policy_failure,
+    } = match evaluation_environment
         .clone()
-        .validate(&policy_id, validate_request)
+        .validate(policy_variant.policy_id(&policy_id), validate_request)
     {
-        Ok(validation_response) => validation_response,
+        Ok(policy_response) => policy_response,
         Err(EvaluationError::PolicyInitialization(error)) => {
             let policy_initialization_error_metric = metrics::PolicyInitializationError {
-                policy_name: policy_id.to_string(),
+                policy_name: policy_variant.policy_id(&policy_id).to_string(),
                 initialization_error: error.to_string(),
             };
 
             metrics::add_policy_evaluation(&policy_initialization_error_metric);
 
-            return Ok(AdmissionResponse::reject(
-                validate_request.uid().to_owned(),
-                error.to_string(),
-                500,
-            ));
+            let admission_response = policy_initialization_rejection(
+                policy_variant.policy_id(&policy_id),
+                validate_request.uid(),
+                error,
+            );
+            record_decision(&DecisionRecord {
+                policy_id: policy_id.to_string(),
+                policy_mode: policy_mode.clone().into(),
+                policy_variant: policy_variant.to_string(),
+                request_origin: request_origin.to_string(),
+                raw_allowed: false,
+                latency_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+                break_glass,
+                ..DecisionRecord::new(validate_request, &admission_response)
+            });
+
+            return Ok(admission_response);
         }
 
         Err(error) => return Err(error),
     };
+    // The failures of the policy are classified by the evaluation environment, before the
+    // custom rejection message of the policy replaces the message describing them
+    let policy_failure = policy_failure.map(ErrorCode::from);
+    if let Some(policy_failure) = policy_failure {
+        policy_failure.record(Some(&policy_variant.policy_id(&policy_id).to_string()));
+    }
+
+    // A patch that cannot be applied must not reach the API server, where the error
+    // would be opaque
+    let vanilla_validation_response = match validate_request {
+        ValidateRequest::AdmissionRequest(adm_req)
+            if vanilla_validation_response.patch.is_some() =>
+        {
+            match verify_mutation(
+                &evaluation_environment,
+                policy_variant.policy_id(&policy_id),
+                adm_req,
+                &vanilla_validation_response,
+            )? {
+                Some(error) => AdmissionResponse::reject(adm_req.uid.clone(), error, 500),
+                None => vanilla_validation_response,
+            }
+        }
+        _ => vanilla_validation_response,
+    };
 
-    let policy_mode = evaluation_environment.get_policy_mode(&policy_id)?;
     let allowed_to_mutate = evaluation_environment.get_policy_allowed_to_mutate(&policy_id)?;
-    let custom_rejection_message =
-        evaluation_environment.get_policy_custom_rejection_message(&policy_id)?;
+    let custom_rejection_message = evaluation_environment
+        .get_policy_custom_rejection_message(&policy_id)?
+        .map(|message_template| {
+            message_template.render(validate_request, &vanilla_validation_response)
+        });
 
     let policy_evaluation_duration = start_time.elapsed();
     let accepted = vanilla_validation_response.allowed;
@@ -103,6 +262,24 @@ pub(crate) fn evaluate(
         None
     };
 
+    // Policies running in monitor mode accept the requests they would have rejected
+    let monitor_mode_violation = (matches!(request_origin, RequestOrigin::Validate)
+        && policy_mode == PolicyMode::Monitor
+        && !accepted
+        && evaluation_environment.should_warn_on_monitor_mode_violations())
+    .then(|| {
+        let message = custom_rejection_message
+            .clone()
+            .or_else(|| {
+                vanilla_validation_response
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.message.clone())
+            })
+            .unwrap_or_default();
+        format!("policy {policy_id} running in monitor mode would reject the request: {message}")
+    });
+
     let admission_response_handler = AdmissionResponseHandler::new(
         &policy_id,
         &policy_mode,
@@ -110,25 +287,63 @@ pub(crate) fn evaluate(
         custom_rejection_message,
     );
 
-    let validation_response = match request_origin {
+    let mut validation_response = match request_origin {
         RequestOrigin::Validate => {
             admission_response_handler.process_response(vanilla_validation_response)
         }
         RequestOrigin::Audit => vanilla_validation_response,
     };
+    if let Some(policy_failure) = policy_failure
+        && !validation_response.allowed
+    {
+        policy_failure.add_to_rejection(&mut validation_response);
+    }
+    if break_glass {
+        validation_response
+            .audit_annotations
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                BREAK_GLASS_AUDIT_ANNOTATION.to_owned(),
+                "policy forced into monitor mode".to_owned(),
+            );
+    }
+    if let Some(monitor_mode_violation) = monitor_mode_violation {
+        validation_response
+            .warnings
+            .get_or_insert_with(Vec::new)
+            .push(monitor_mode_violation.clone());
+        validation_response
+            .audit_annotations
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                MONITOR_MODE_VIOLATION_AUDIT_ANNOTATION.to_owned(),
+                monitor_mode_violation,
+            );
+    }
+    add_policy_audit_annotations(
+        &mut validation_response,
+        &policy_id,
+        &policy_mode,
+        evaluation_environment.get_policy_module_digest(policy_variant.policy_id(&policy_id)),
+        policy_evaluation_duration,
+    );
 
     match validate_request {
         ValidateRequest::AdmissionRequest(adm_req) => {
             let policy_evaluation_metric = metrics::PolicyEvaluation {
                 policy_name: policy_id.to_string(),
-                policy_mode: policy_mode.into(),
+                policy_mode: policy_mode.clone().into(),
+                policy_variant: policy_variant.to_string(),
                 resource_namespace: adm_req.clone().namespace,
                 resource_kind: adm_req.clone().request_kind.unwrap_or_default().kind,
                 resource_request_operation: adm_req.clone().operation,
                 accepted,
                 mutated,
                 request_origin: request_origin.to_string(),
+                dry_run: adm_req.dry_run.unwrap_or(false),
                 error_code,
+                exemption_reason: None,
+                break_glass,
             };
             metrics::record_policy_latency(policy_evaluation_duration, &policy_evaluation_metric);
             metrics::add_policy_evaluation(&policy_evaluation_metric);
@@ -136,10 +351,12 @@ pub(crate) fn evaluate(
         ValidateRequest::Raw(_) => {
             let raw_policy_evaluation_metric = metrics::RawPolicyEvaluation {
                 policy_name: policy_id.to_string(),
-                policy_mode: policy_mode.into(),
+                policy_mode: policy_mode.clone().into(),
+                policy_variant: policy_variant.to_string(),
                 accepted,
                 mutated,
                 error_code,
+                break_glass,
             };
             metrics::record_policy_latency(
                 policy_evaluation_duration,
@@ -148,13 +365,316 @@ pub(crate) fn evaluate(
             metrics::add_policy_evaluation(&raw_policy_evaluation_metric);
         }
     };
+
+    record_decision(&DecisionRecord {
+        policy_id: policy_id.to_string(),
+        policy_mode: policy_mode.into(),
+        policy_variant: policy_variant.to_string(),
+        request_origin: request_origin.to_string(),
+        raw_allowed: accepted,
+        latency_ms: policy_evaluation_duration.as_secs_f64() * 1000.0,
+        break_glass,
+        ..DecisionRecord::new(validate_request, &validation_response)
+    });
+
     Ok(validation_response)
 }
 
+/// Build the response accepting a request without evaluating the policy, because it's
+/// exempted or not relevant for the policy. The exemption is reported by an audit annotation.
+fn exempted_response(uid: &str, exemption: Option<Exemption>) -> AdmissionResponse {
+    AdmissionResponse {
+        uid: uid.to_owned(),
+        allowed: true,
+        status: None,
+        patch: None,
+        audit_annotations: exemption.map(|exemption| {
+            HashMap::from([(EXEMPTION_AUDIT_ANNOTATION.to_owned(), exemption.to_string())])
+        }),
+        warnings: None,
+        patch_type: None,
+    }
+}
+
+/// Build the rejection of a request evaluated by a policy that could not be initialized. The
+/// error code is reported by the rejection, and recorded on the current span and in the
+/// metrics.
+fn policy_initialization_rejection(
+    policy_id: &PolicyID,
+    uid: &str,
+    error: String,
+) -> AdmissionResponse {
+    let error_code = ErrorCode::PolicyInitializationFailed;
+    error_code.record(Some(&policy_id.to_string()));
+
+    let mut admission_response = AdmissionResponse::reject(uid.to_owned(), error, 500);
+    error_code.add_to_rejection(&mut admission_response);
+    admission_response
+}
+
+/// Attribute the decision to the exact build of the policy that took it, so that it can be
+/// found inside of the Kubernetes audit logs. The module digest is not available for policy
+/// groups and mutation pipelines, which are made of multiple modules.
+fn add_policy_audit_annotations(
+    admission_response: &mut AdmissionResponse,
+    policy_id: &PolicyID,
+    policy_mode: &PolicyMode,
+    module_digest: Option<String>,
+    evaluation_duration: Duration,
+) {
+    let decision = if admission_response.allowed {
+        "allowed"
+    } else {
+        "rejected"
+    };
+    let audit_annotations = admission_response
+        .audit_annotations
+        .get_or_insert_with(HashMap::new);
+
+    audit_annotations.extend([
+        (POLICY_ID_AUDIT_ANNOTATION.to_owned(), policy_id.to_string()),
+        (
+            POLICY_MODE_AUDIT_ANNOTATION.to_owned(),
+            policy_mode.clone().into(),
+        ),
+        (DECISION_AUDIT_ANNOTATION.to_owned(), decision.to_owned()),
+        (
+            EVALUATION_LATENCY_AUDIT_ANNOTATION.to_owned(),
+            format!("{:.3}", evaluation_duration.as_secs_f64() * 1000.0),
+        ),
+    ]);
+    if let Some(module_digest) = module_digest {
+        audit_annotations.insert(
+            POLICY_MODULE_DIGEST_AUDIT_ANNOTATION.to_owned(),
+            format!("sha256:{module_digest}"),
+        );
+    }
+}
+
+/// Ensure the patch returned by the policy can be applied to the object of the request,
+/// and that it doesn't change any of the fields the policy is not allowed to mutate.
+/// When enabled, the policy is evaluated a second time against the patched object, to
+/// ensure the mutation is idempotent.
+///
+/// Returns a description of the problem when the mutation is not valid.
+fn verify_mutation(
+    evaluation_environment: &EvaluationEnvironment,
+    policy_id: &PolicyID,
+    adm_req: &AdmissionRequest,
+    response: &AdmissionResponse,
+) -> Result<Option<String>, EvaluationError> {
+    let Some(response_patch) = &response.patch else {
+        return Ok(None);
+    };
+    let Some(object) = &adm_req.object else {
+        return Ok(Some(format!(
+            "policy {policy_id} returned a patch, but the request doesn't have an object"
+        )));
+    };
+
+    let mut patched_object = object.clone();
+    if let Err(e) = patch::apply_patch(&mut patched_object, response_patch) {
+        return Ok(Some(format!(
+            "policy {policy_id} returned a patch that cannot be applied: {e}"
+        )));
+    }
+
+    if let Some(mutation_deny_paths) =
+        evaluation_environment.get_policy_mutation_deny_paths(policy_id)?
+        && let Some(path) = mutation_deny_paths.first_changed_path(object, &patched_object)
+    {
+        return Ok(Some(format!(
+            "policy {policy_id} is not allowed to mutate {path}"
+        )));
+    }
+
+    if !evaluation_environment.should_verify_mutation_idempotency() {
+        return Ok(None);
+    }
+
+    let mut patched_req = adm_req.clone();
+    patched_req.object = Some(patched_object.clone());
+    let second_response = evaluation_environment
+        .validate(
+            policy_id,
+            &ValidateRequest::AdmissionRequest(Box::new(patched_req)),
+        )?
+        .response;
+    if !second_response.allowed {
+        return Ok(Some(format!(
+            "policy {policy_id} rejects the object produced by its own mutation"
+        )));
+    }
+    if let Some(second_patch) = &second_response.patch {
+        let mut twice_patched_object = patched_object.clone();
+        if patch::apply_patch(&mut twice_patched_object, second_patch).is_err()
+            || twice_patched_object != patched_object
+        {
+            return Ok(Some(format!(
+                "the mutation of policy {policy_id} is not idempotent"
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Evaluate the request against all the policies whose rules match it, and combine their
+/// responses into a single one.
+///
+/// The policies are evaluated one after the other, following their order: each policy
+/// evaluates the object as mutated by the previous ones, like the steps of a mutation
+/// pipeline. The request is rejected when at least one of the policies rejects it.
+/// Otherwise, the returned patch describes the changes made by all the mutating policies.
+pub(crate) fn evaluate_matching_policies(
+    evaluation_environment: Arc<EvaluationEnvironment>,
+    adm_req: &AdmissionRequest,
+) -> Result<AdmissionResponse, EvaluationError> {
+    let policy_ids = evaluation_environment.get_policies_matching_request(adm_req);
+    Span::current().record(
+        "policies",
+        policy_ids
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+            .as_str(),
+    );
+
+    let mut evaluated_req = adm_req.clone();
+    let mut responses = Vec::with_capacity(policy_ids.len());
+    for policy_id in policy_ids {
+        let response = evaluate(
+            evaluation_environment.clone(),
+            &policy_id.to_string(),
+            &ValidateRequest::AdmissionRequest(Box::new(evaluated_req.clone())),
+            RequestOrigin::Validate,
+        )?;
+        if response.allowed
+            && let Some(response_patch) = &response.patch
+        {
+            let applied = match evaluated_req.object.as_mut() {
+                Some(object) => patch::apply_patch(object, response_patch),
+                None => Err(anyhow!("the request doesn't have an object to patch")),
+            };
+            if let Err(e) = applied {
+                return Ok(AdmissionResponse::reject(
+                    adm_req.uid.clone(),
+                    format!("cannot compose the mutation of {policy_id}: {e}"),
+                    500,
+                ));
+            }
+        }
+        responses.push((policy_id, response));
+    }
+
+    Ok(combine_admission_responses(
+        adm_req,
+        evaluated_req.object.as_ref(),
+        responses,
+    ))
+}
+
+/// Merge the responses given by multiple policies to the same request. The mutated object
+/// is the one produced by all the patches of the responses.
+fn combine_admission_responses(
+    adm_req: &AdmissionRequest,
+    mutated_object: Option<&serde_json::Value>,
+    responses: Vec<(PolicyID, AdmissionResponse)>,
+) -> AdmissionResponse {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut audit_annotations: HashMap<String, String> = HashMap::new();
+    let mut rejections: Vec<(PolicyID, Option<AdmissionResponseStatus>)> = Vec::new();
+
+    for (policy_id, response) in responses {
+        for warning in response.warnings.unwrap_or_default() {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+        // The policies report the same audit annotations, like `policy-id`, they are kept
+        // apart by the ID of the policy
+        audit_annotations.extend(
+            response
+                .audit_annotations
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, value)| (format!("{policy_id}.{key}"), value)),
+        );
+
+        if !response.allowed {
+            rejections.push((policy_id, response.status));
+        }
+    }
+
+    let mut admission_response = AdmissionResponse {
+        uid: adm_req.uid.clone(),
+        allowed: true,
+        warnings: (!warnings.is_empty()).then_some(warnings),
+        audit_annotations: (!audit_annotations.is_empty()).then_some(audit_annotations),
+        ..Default::default()
+    };
+
+    if !rejections.is_empty() {
+        let message = rejections
+            .iter()
+            .map(|(policy_id, status)| {
+                let message = status
+                    .as_ref()
+                    .and_then(|status| status.message.as_deref())
+                    .unwrap_or("rejected");
+                format!("{policy_id}: {message}")
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        // The code and the details are the ones of the first rejection
+        let mut status = rejections
+            .into_iter()
+            .find_map(|(_, status)| status)
+            .unwrap_or_default();
+        status.message = Some(message);
+
+        admission_response.allowed = false;
+        admission_response.status = Some(status);
+        return admission_response;
+    }
+
+    match (adm_req.object.as_ref(), mutated_object) {
+        (Some(original_object), Some(mutated_object)) if original_object != mutated_object => {
+            match patch::encode_diff(original_object, mutated_object) {
+                Ok(patch) => {
+                    admission_response.patch_type = Some(PatchType::JSONPatch);
+                    admission_response.patch = Some(patch);
+                    admission_response
+                }
+                Err(error) => {
+                    AdmissionResponse::reject(adm_req.uid.clone(), error.to_string(), 500)
+                }
+            }
+        }
+        _ => admission_response,
+    }
+}
+
+/// Send the given decision to all the decision sinks
+fn record_decision(record: &DecisionRecord) {
+    decision_log::record_decision(record);
+    decision_webhook::notify_decision(record);
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
 
+    use crate::evaluation::{
+        exemptions::{Exemption, ExemptionReason, ExemptionScope},
+        message_template::MessageTemplate,
+        mutation_deny_paths::MutationDenyPaths,
+        policy_failure::PolicyFailure,
+        policy_variant::PolicyVariant,
+    };
     use crate::test_utils::build_admission_review_request;
     use policy_evaluator::admission_response_handler::{
         policy_id::PolicyID, policy_mode::PolicyMode,
@@ -171,6 +691,9 @@ mod tests {
         policy_mode: PolicyMode,
     ) -> EvaluationEnvironment {
         let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
         mock_evaluation_environment
             .expect_validate()
             .returning(|_policy_id, request| {
@@ -178,18 +701,31 @@ mod tests {
                     uid: request.uid().to_owned(),
                     allowed: true,
                     ..Default::default()
-                })
+                }
+                .into())
             });
 
         mock_evaluation_environment
             .expect_get_policy_mode()
             .returning(move |_policy_id| Ok(policy_mode.clone()));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .returning(|_policy_id, _now| None);
+        mock_evaluation_environment
+            .expect_get_policy_module_digest()
+            .returning(|_policy_id| Some("1234abcd".to_owned()));
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
         mock_evaluation_environment
             .expect_get_policy_allowed_to_mutate()
             .returning(|_policy_id| Ok(false));
         mock_evaluation_environment
-            .expect_should_always_accept_requests_made_inside_of_namespace()
-            .returning(|_namespace| false);
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| None);
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(|_policy_id, _request| true);
         mock_evaluation_environment
             .expect_get_policy_custom_rejection_message()
             .returning(|_policy_id| Ok(None));
@@ -201,32 +737,65 @@ mod tests {
     struct RejectionDetails {
         message: String,
         code: u16,
+        failure: Option<PolicyFailure>,
     }
 
     fn create_evaluation_environment_that_reject_request(
         policy_mode: PolicyMode,
+        scheduled_policy_mode: Option<PolicyMode>,
         rejection_details: RejectionDetails,
         allowed_namespace: String,
+        evaluate_request: bool,
+        monitor_mode_warnings: bool,
     ) -> EvaluationEnvironment {
         let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
         mock_evaluation_environment
             .expect_validate()
             .returning(move |_policy_id, request| {
-                Ok(AdmissionResponse::reject(
-                    request.uid().to_owned(),
-                    rejection_details.message.clone(),
-                    rejection_details.code,
-                ))
+                Ok(PolicyResponse {
+                    response: AdmissionResponse::reject(
+                        request.uid().to_owned(),
+                        rejection_details.message.clone(),
+                        rejection_details.code,
+                    ),
+                    failure: rejection_details.failure,
+                })
             });
         mock_evaluation_environment
             .expect_get_policy_mode()
             .returning(move |_policy_id| Ok(policy_mode.clone()));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .returning(move |_policy_id, _now| scheduled_policy_mode.clone());
+        mock_evaluation_environment
+            .expect_get_policy_module_digest()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
         mock_evaluation_environment
             .expect_get_policy_allowed_to_mutate()
             .returning(|_policy_id| Ok(false));
         mock_evaluation_environment
-            .expect_should_always_accept_requests_made_inside_of_namespace()
-            .returning(move |namespace| namespace == allowed_namespace);
+            .expect_get_request_exemption()
+            .returning(move |_policy_id, request| {
+                (request.namespace.as_deref() == Some(allowed_namespace.as_str())).then(|| {
+                    Exemption {
+                        scope: ExemptionScope::Global,
+                        reason: ExemptionReason::Namespace,
+                        value: allowed_namespace.clone(),
+                    }
+                })
+            });
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(move |_policy_id, _request| evaluate_request);
+        mock_evaluation_environment
+            .expect_should_warn_on_monitor_mode_violations()
+            .returning(move || monitor_mode_warnings);
         mock_evaluation_environment
             .expect_get_policy_custom_rejection_message()
             .returning(|_policy_id| Ok(None));
@@ -245,7 +814,7 @@ mod tests {
         #[case] request_origin: RequestOrigin,
     ) {
         let evaluation_environment =
-            create_evaluation_environment_that_accepts_request(policy_mode);
+            create_evaluation_environment_that_accepts_request(policy_mode.clone());
         let policy_id = "test_policy1";
         let validate_request =
             ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
@@ -258,27 +827,64 @@ mod tests {
         )
         .unwrap();
         assert!(response.allowed);
+
+        let audit_annotations = response.audit_annotations.expect("should be set");
+        let policy_mode: String = policy_mode.into();
+        assert_eq!(
+            Some(&policy_id.to_owned()),
+            audit_annotations.get(POLICY_ID_AUDIT_ANNOTATION)
+        );
+        assert_eq!(
+            Some(&policy_mode),
+            audit_annotations.get(POLICY_MODE_AUDIT_ANNOTATION)
+        );
+        assert_eq!(
+            Some(&"sha256:1234abcd".to_owned()),
+            audit_annotations.get(POLICY_MODULE_DIGEST_AUDIT_ANNOTATION)
+        );
+        assert_eq!(
+            Some(&"allowed".to_owned()),
+            audit_annotations.get(DECISION_AUDIT_ANNOTATION)
+        );
+        assert!(audit_annotations.contains_key(EVALUATION_LATENCY_AUDIT_ANNOTATION));
     }
 
     #[rstest]
     #[test]
-    #[case(PolicyMode::Protect, RequestOrigin::Validate, false)]
-    #[case(PolicyMode::Monitor, RequestOrigin::Validate, true)]
-    #[case(PolicyMode::Protect, RequestOrigin::Audit, false)]
-    #[case(PolicyMode::Monitor, RequestOrigin::Audit, false)]
+    #[case(PolicyMode::Protect, None, RequestOrigin::Validate, false)]
+    #[case(PolicyMode::Monitor, None, RequestOrigin::Validate, true)]
+    #[case(PolicyMode::Protect, None, RequestOrigin::Audit, false)]
+    #[case(PolicyMode::Monitor, None, RequestOrigin::Audit, false)]
+    #[case::scheduled_monitor_mode(
+        PolicyMode::Protect,
+        Some(PolicyMode::Monitor),
+        RequestOrigin::Validate,
+        true
+    )]
+    #[case::scheduled_protect_mode(
+        PolicyMode::Monitor,
+        Some(PolicyMode::Protect),
+        RequestOrigin::Validate,
+        false
+    )]
     fn evaluate_policy_evaluator_rejects_request(
         #[case] policy_mode: PolicyMode,
+        #[case] scheduled_policy_mode: Option<PolicyMode>,
         #[case] request_origin: RequestOrigin,
         #[case] accept: bool,
     ) {
         let rejection_details = RejectionDetails {
             message: "boom".to_string(),
             code: 500,
+            failure: None,
         };
         let evaluation_environment = create_evaluation_environment_that_reject_request(
             policy_mode,
+            scheduled_policy_mode,
             rejection_details.clone(),
             "".to_string(),
+            true,
+            false,
         );
         let validate_request =
             ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
@@ -322,67 +928,1173 @@ mod tests {
         assert!(response.allowed);
     }
 
+    #[rstest]
+    #[case::warnings_enabled(true)]
+    #[case::warnings_disabled(false)]
+    fn evaluate_policy_in_monitor_mode_warns_about_violations(#[case] monitor_mode_warnings: bool) {
+        let evaluation_environment = create_evaluation_environment_that_reject_request(
+            PolicyMode::Monitor,
+            None,
+            RejectionDetails {
+                message: "boom".to_string(),
+                code: 500,
+                failure: None,
+            },
+            "".to_string(),
+            true,
+            monitor_mode_warnings,
+        );
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        let expected_warning =
+            "policy test_policy1 running in monitor mode would reject the request: boom";
+        if monitor_mode_warnings {
+            assert_eq!(Some(vec![expected_warning.to_owned()]), response.warnings);
+            assert_eq!(
+                Some(expected_warning),
+                response
+                    .audit_annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.get(MONITOR_MODE_VIOLATION_AUDIT_ANNOTATION))
+                    .map(String::as_str)
+            );
+        } else {
+            assert!(response.warnings.is_none());
+            assert!(
+                !response
+                    .audit_annotations
+                    .expect("should be set")
+                    .contains_key(MONITOR_MODE_VIOLATION_AUDIT_ANNOTATION)
+            );
+        }
+    }
+
     #[test]
-    fn evaluate_policy_evaluator_rejects_request_raw() {
-        let rejection_details = RejectionDetails {
-            message: "boom".to_string(),
-            code: 500,
-        };
+    fn evaluate_policy_reports_unprefixed_audit_annotations() {
         let evaluation_environment = create_evaluation_environment_that_reject_request(
             PolicyMode::Protect,
-            rejection_details.clone(),
+            None,
+            RejectionDetails {
+                message: "boom".to_string(),
+                code: 500,
+                failure: None,
+            },
             "".to_string(),
+            true,
+            false,
         );
-        let request = serde_json::json!(r#"{"foo": "bar"}"#);
-        let validate_request = ValidateRequest::Raw(request.clone());
-        let policy_id = "test_policy1";
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
 
         let response = evaluate(
             Arc::new(evaluation_environment),
-            policy_id,
+            "test_policy1",
             &validate_request,
             RequestOrigin::Validate,
         )
         .unwrap();
 
-        assert!(!response.allowed);
-        let response_status = response.status.expect("should be set");
-        assert_eq!(response_status.message, Some(rejection_details.message));
-        assert_eq!(response_status.code, Some(rejection_details.code));
+        let audit_annotations = response.audit_annotations.expect("should be set");
+        assert_eq!(
+            HashSet::from([
+                POLICY_ID_AUDIT_ANNOTATION,
+                POLICY_MODE_AUDIT_ANNOTATION,
+                DECISION_AUDIT_ANNOTATION,
+                EVALUATION_LATENCY_AUDIT_ANNOTATION,
+            ]),
+            audit_annotations.keys().map(String::as_str).collect()
+        );
+        assert_eq!(
+            Some("rejected"),
+            audit_annotations
+                .get(DECISION_AUDIT_ANNOTATION)
+                .map(String::as_str)
+        );
     }
 
     #[rstest]
-    #[test]
-    #[case(RequestOrigin::Validate)]
-    #[case(RequestOrigin::Audit)]
-    fn evaluate_policy_evaluator_rejects_request_but_request_originated_from_allowed_namespace(
+    #[case::protect_mode(PolicyMode::Protect, None, RequestOrigin::Validate, false)]
+    #[case::scheduled_monitor_mode(
+        PolicyMode::Protect,
+        Some(PolicyMode::Monitor),
+        RequestOrigin::Validate,
+        true
+    )]
+    #[case::scheduled_protect_mode(
+        PolicyMode::Monitor,
+        Some(PolicyMode::Protect),
+        RequestOrigin::Validate,
+        false
+    )]
+    #[case::audit_request(PolicyMode::Monitor, None, RequestOrigin::Audit, false)]
+    fn evaluate_warns_about_monitor_mode_violations_of_the_effective_mode(
+        #[case] policy_mode: PolicyMode,
+        #[case] scheduled_policy_mode: Option<PolicyMode>,
         #[case] request_origin: RequestOrigin,
+        #[case] expect_warning: bool,
     ) {
-        let allowed_namespace = "kubewarden_special".to_string();
-        let rejection_details = RejectionDetails {
-            message: "boom".to_string(),
-            code: 500,
-        };
         let evaluation_environment = create_evaluation_environment_that_reject_request(
-            PolicyMode::Protect,
-            rejection_details.clone(),
-            allowed_namespace.clone(),
+            policy_mode,
+            scheduled_policy_mode,
+            RejectionDetails {
+                message: "boom".to_string(),
+                code: 500,
+                failure: None,
+            },
+            "".to_string(),
+            true,
+            true,
         );
-        let mut request = build_admission_review_request().request;
-        request.namespace = Some(allowed_namespace.clone());
-        let validate_request = ValidateRequest::AdmissionRequest(Box::new(request));
-
-        let policy_id = "test_policy1";
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
 
         let response = evaluate(
             Arc::new(evaluation_environment),
-            policy_id,
+            "test_policy1",
             &validate_request,
             request_origin,
         )
         .unwrap();
 
+        let monitor_mode_violation = response
+            .audit_annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(MONITOR_MODE_VIOLATION_AUDIT_ANNOTATION));
+        if expect_warning {
+            assert!(response.allowed);
+            assert_eq!(
+                Some(vec![
+                    "policy test_policy1 running in monitor mode would reject the request: boom"
+                        .to_owned()
+                ]),
+                response.warnings
+            );
+            assert!(monitor_mode_violation.is_some());
+        } else {
+            assert!(response.warnings.is_none());
+            assert!(monitor_mode_violation.is_none());
+        }
+    }
+
+    #[test]
+    fn evaluate_policy_in_monitor_mode_warns_with_the_custom_rejection_message() {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_validate()
+            .returning(|_policy_id, request| {
+                Ok(
+                    AdmissionResponse::reject(request.uid().to_owned(), "boom".to_string(), 500)
+                        .into(),
+                )
+            });
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Monitor));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .returning(|_policy_id, _now| None);
+        mock_evaluation_environment
+            .expect_get_policy_module_digest()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
+        mock_evaluation_environment
+            .expect_get_policy_allowed_to_mutate()
+            .returning(|_policy_id| Ok(false));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| None);
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(|_policy_id, _request| true);
+        mock_evaluation_environment
+            .expect_should_warn_on_monitor_mode_violations()
+            .returning(|| true);
+        mock_evaluation_environment
+            .expect_get_policy_custom_rejection_message()
+            .returning(|_policy_id| {
+                Ok(Some(
+                    MessageTemplate::new("{{ .kind }} {{ .name }} rejected: {{ .policyMessage }}")
+                        .unwrap(),
+                ))
+            });
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
         assert!(response.allowed);
-        assert!(response.status.is_none());
+        assert_eq!(
+            Some(vec![
+                "policy test_policy1 running in monitor mode would reject the request: Scale my-deployment rejected: boom"
+                    .to_owned()
+            ]),
+            response.warnings
+        );
+    }
+
+    #[rstest]
+    #[case::exempted_request(true)]
+    #[case::initialization_error(false)]
+    fn evaluate_computes_the_effective_policy_mode_once(#[case] exempted: bool) {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_validate()
+            .returning(|_policy_id, _request| {
+                Err(EvaluationError::PolicyInitialization("boom".to_string()))
+            });
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .times(1)
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .times(1)
+            .returning(|_policy_id, _now| Some(PolicyMode::Monitor));
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(move |_policy_id, _request| {
+                exempted.then(|| Exemption {
+                    scope: ExemptionScope::Global,
+                    reason: ExemptionReason::Namespace,
+                    value: "my-namespace".to_string(),
+                })
+            });
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(|_policy_id, _request| true);
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert_eq!(exempted, response.allowed);
+        if !exempted {
+            assert_eq!(
+                Some("policy_initialization_failed".to_string()),
+                rejection_error_code(&response)
+            );
+        }
+    }
+
+    #[test]
+    fn evaluate_rejects_the_requests_of_a_policy_that_could_not_be_registered() {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| Some("invalid mutationDenyPaths".to_string()));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| None);
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .times(0);
+        mock_evaluation_environment.expect_validate().times(0);
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(!response.allowed);
+        assert_eq!(
+            Some("policy_initialization_failed".to_string()),
+            rejection_error_code(&response)
+        );
+        let status = response.status.expect("should have a status");
+        assert_eq!(Some(500), status.code);
+        assert_eq!(
+            Some("invalid mutationDenyPaths".to_string()),
+            status.message
+        );
+    }
+
+    #[test]
+    fn evaluate_accepts_the_exempted_requests_of_a_policy_that_could_not_be_registered() {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| Some("invalid mutationDenyPaths".to_string()));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| {
+                Some(Exemption {
+                    scope: ExemptionScope::Global,
+                    reason: ExemptionReason::Namespace,
+                    value: "kube-system".to_string(),
+                })
+            });
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .times(0);
+        mock_evaluation_environment.expect_validate().times(0);
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert!(response.status.is_none());
+        assert!(
+            response
+                .audit_annotations
+                .expect("should have audit annotations")
+                .contains_key(EXEMPTION_AUDIT_ANNOTATION)
+        );
+    }
+
+    /// Returns the error code reported by a rejection, see `ErrorCode::add_to_rejection`
+    fn rejection_error_code(response: &AdmissionResponse) -> Option<String> {
+        response
+            .status
+            .as_ref()?
+            .details
+            .as_ref()?
+            .causes
+            .iter()
+            .find_map(|cause| cause.reason.clone())
+    }
+
+    #[rstest]
+    #[case::timeout(
+        Some(PolicyFailure::Timeout),
+        PolicyMode::Protect,
+        Some("policy_timeout")
+    )]
+    #[case::trap(Some(PolicyFailure::Trap), PolicyMode::Protect, Some("policy_trap"))]
+    #[case::policy_group_expression_failure(
+        Some(PolicyFailure::PolicyGroupExpression),
+        PolicyMode::Protect,
+        Some("policy_group_expression_failed")
+    )]
+    #[case::rejection(None, PolicyMode::Protect, None)]
+    #[case::monitor_mode(Some(PolicyFailure::Timeout), PolicyMode::Monitor, None)]
+    fn evaluate_reports_the_error_code_of_policy_failures(
+        #[case] failure: Option<PolicyFailure>,
+        #[case] policy_mode: PolicyMode,
+        #[case] expected_error_code: Option<&str>,
+    ) {
+        let evaluation_environment = create_evaluation_environment_that_reject_request(
+            policy_mode,
+            None,
+            RejectionDetails {
+                // the error code doesn't depend on the message of the rejection
+                message: "internal server error: boom".to_string(),
+                code: 500,
+                failure,
+            },
+            "".to_string(),
+            true,
+            false,
+        );
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert_eq!(
+            expected_error_code.map(str::to_owned),
+            rejection_error_code(&response)
+        );
+    }
+
+    #[test]
+    fn evaluate_policy_evaluator_rejects_request_with_message_template() {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_validate()
+            .returning(|_policy_id, request| {
+                Ok(
+                    AdmissionResponse::reject(request.uid().to_owned(), "boom".to_string(), 500)
+                        .into(),
+                )
+            });
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .returning(|_policy_id, _now| None);
+        mock_evaluation_environment
+            .expect_get_policy_module_digest()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
+        mock_evaluation_environment
+            .expect_get_policy_allowed_to_mutate()
+            .returning(|_policy_id| Ok(false));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| None);
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(|_policy_id, _request| true);
+        mock_evaluation_environment
+            .expect_get_policy_custom_rejection_message()
+            .returning(|_policy_id| {
+                Ok(Some(
+                    MessageTemplate::new("{{ .kind }} {{ .name }} rejected: {{ .policyMessage }}")
+                        .unwrap(),
+                ))
+            });
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(mock_evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(!response.allowed);
+        assert_eq!(
+            Some("Scale my-deployment rejected: boom".to_owned()),
+            response.status.expect("should be set").message
+        );
+    }
+
+    #[test]
+    fn evaluate_policy_evaluator_rejects_request_raw() {
+        let rejection_details = RejectionDetails {
+            message: "boom".to_string(),
+            code: 500,
+            failure: None,
+        };
+        let evaluation_environment = create_evaluation_environment_that_reject_request(
+            PolicyMode::Protect,
+            None,
+            rejection_details.clone(),
+            "".to_string(),
+            true,
+            false,
+        );
+        let request = serde_json::json!(r#"{"foo": "bar"}"#);
+        let validate_request = ValidateRequest::Raw(request.clone());
+        let policy_id = "test_policy1";
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            policy_id,
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(!response.allowed);
+        let response_status = response.status.expect("should be set");
+        assert_eq!(response_status.message, Some(rejection_details.message));
+        assert_eq!(response_status.code, Some(rejection_details.code));
+    }
+
+    #[rstest]
+    #[test]
+    #[case(RequestOrigin::Validate)]
+    #[case(RequestOrigin::Audit)]
+    fn evaluate_policy_evaluator_rejects_request_but_request_originated_from_allowed_namespace(
+        #[case] request_origin: RequestOrigin,
+    ) {
+        let allowed_namespace = "kubewarden_special".to_string();
+        let rejection_details = RejectionDetails {
+            message: "boom".to_string(),
+            code: 500,
+            failure: None,
+        };
+        let evaluation_environment = create_evaluation_environment_that_reject_request(
+            PolicyMode::Protect,
+            None,
+            rejection_details.clone(),
+            allowed_namespace.clone(),
+            true,
+            false,
+        );
+        let mut request = build_admission_review_request().request;
+        request.namespace = Some(allowed_namespace.clone());
+        let validate_request = ValidateRequest::AdmissionRequest(Box::new(request));
+
+        let policy_id = "test_policy1";
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            policy_id,
+            &validate_request,
+            request_origin,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert!(response.status.is_none());
+        assert_eq!(
+            Some(HashMap::from([(
+                EXEMPTION_AUDIT_ANNOTATION.to_owned(),
+                "namespace 'kubewarden_special' is exempted globally".to_owned()
+            )])),
+            response.audit_annotations
+        );
+    }
+
+    #[rstest]
+    #[test]
+    #[case(RequestOrigin::Validate)]
+    #[case(RequestOrigin::Audit)]
+    fn evaluate_policy_evaluator_rejects_request_but_request_does_not_match_policy(
+        #[case] request_origin: RequestOrigin,
+    ) {
+        let rejection_details = RejectionDetails {
+            message: "boom".to_string(),
+            code: 500,
+            failure: None,
+        };
+        let evaluation_environment = create_evaluation_environment_that_reject_request(
+            PolicyMode::Protect,
+            None,
+            rejection_details,
+            "".to_string(),
+            false,
+            false,
+        );
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "test_policy1",
+            &validate_request,
+            request_origin,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert!(response.status.is_none());
+    }
+
+    fn encode_patch(patch: serde_json::Value) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(&patch).unwrap())
+    }
+
+    /// Create an evaluation environment where all the given policies match the request, each
+    /// one of them answers with the given response
+    fn create_evaluation_environment_with_matching_policies(
+        responses: HashMap<&'static str, AdmissionResponse>,
+    ) -> EvaluationEnvironment {
+        let policy_ids = responses.keys().copied().collect();
+        create_evaluation_environment_with_matching_policy_evaluations(
+            policy_ids,
+            move |policy_id, _request| responses[policy_id.to_string().as_str()].clone(),
+        )
+    }
+
+    /// Create an evaluation environment where the given policies match the request, sorted
+    /// by name. The response of each policy is computed by `evaluate_policy`.
+    fn create_evaluation_environment_with_matching_policy_evaluations(
+        policy_ids: Vec<&'static str>,
+        evaluate_policy: impl Fn(&PolicyID, &ValidateRequest) -> AdmissionResponse + Send + 'static,
+    ) -> EvaluationEnvironment {
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
+        let mut policy_ids: Vec<PolicyID> = policy_ids
+            .into_iter()
+            .map(|policy_id| PolicyID::Policy(policy_id.to_string()))
+            .collect();
+        policy_ids.sort_by_key(|policy_id| policy_id.to_string());
+
+        mock_evaluation_environment
+            .expect_get_policies_matching_request()
+            .returning(move |_request| policy_ids.clone());
+        mock_evaluation_environment
+            .expect_should_verify_mutation_idempotency()
+            .returning(|| false);
+        mock_evaluation_environment
+            .expect_get_policy_mutation_deny_paths()
+            .returning(|_policy_id| Ok(None));
+        mock_evaluation_environment
+            .expect_validate()
+            .returning(move |policy_id, request| {
+                Ok(AdmissionResponse {
+                    uid: request.uid().to_owned(),
+                    ..evaluate_policy(policy_id, request)
+                }
+                .into())
+            });
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .returning(|_policy_id, _now| None);
+        mock_evaluation_environment
+            .expect_get_policy_module_digest()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
+        mock_evaluation_environment
+            .expect_get_policy_allowed_to_mutate()
+            .returning(|_policy_id| Ok(true));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| None);
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(|_policy_id, _request| true);
+        mock_evaluation_environment
+            .expect_get_policy_custom_rejection_message()
+            .returning(|_policy_id| Ok(None));
+
+        mock_evaluation_environment
+    }
+
+    fn mutating_response(patch: serde_json::Value, warning: &str) -> AdmissionResponse {
+        AdmissionResponse {
+            allowed: true,
+            patch_type: Some(PatchType::JSONPatch),
+            patch: Some(encode_patch(patch)),
+            warnings: Some(vec![warning.to_string()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evaluate_matching_policies_without_matches() {
+        let evaluation_environment =
+            create_evaluation_environment_with_matching_policies(HashMap::new());
+        let request = build_admission_review_request().request;
+
+        let response =
+            evaluate_matching_policies(Arc::new(evaluation_environment), &request).unwrap();
+
+        assert!(response.allowed);
+        assert_eq!(request.uid, response.uid);
+        assert!(response.patch.is_none());
+    }
+
+    #[test]
+    fn evaluate_matching_policies_composes_patches() {
+        let evaluation_environment = create_evaluation_environment_with_matching_policies(
+            HashMap::from([
+                (
+                    "add-labels",
+                    mutating_response(
+                        serde_json::json!([{"op": "add", "path": "/metadata", "value": {"labels": {"app": "web"}}}]),
+                        "labels added",
+                    ),
+                ),
+                (
+                    "set-replicas",
+                    mutating_response(
+                        serde_json::json!([{"op": "add", "path": "/spec", "value": {"replicas": 3}}]),
+                        "labels added",
+                    ),
+                ),
+                (
+                    "validating",
+                    AdmissionResponse {
+                        allowed: true,
+                        warnings: Some(vec!["deprecated field".to_string()]),
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        );
+        let request = build_admission_review_request().request;
+
+        let response =
+            evaluate_matching_policies(Arc::new(evaluation_environment), &request).unwrap();
+
+        assert!(response.allowed);
+        assert_eq!(
+            Some(vec![
+                "labels added".to_string(),
+                "deprecated field".to_string()
+            ]),
+            response.warnings
+        );
+        assert_eq!(Some(PatchType::JSONPatch), response.patch_type);
+        let mut object = request.object.clone().unwrap();
+        patch::apply_patch(&mut object, &response.patch.unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "apiVersion": "autoscaling/v1",
+                "kind": "Scale",
+                "metadata": {"labels": {"app": "web"}},
+                "spec": {"replicas": 3}
+            }),
+            object
+        );
+    }
+
+    #[test]
+    fn evaluate_matching_policies_rejects_when_one_policy_rejects() {
+        let evaluation_environment =
+            create_evaluation_environment_with_matching_policies(HashMap::from([
+                (
+                    "add-labels",
+                    mutating_response(
+                        serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}]),
+                        "labels added",
+                    ),
+                ),
+                (
+                    "no-privileged",
+                    AdmissionResponse::reject(String::new(), "privileged".to_string(), 400),
+                ),
+                (
+                    "no-scale",
+                    AdmissionResponse::reject(String::new(), "cannot scale".to_string(), 403),
+                ),
+            ]));
+        let request = build_admission_review_request().request;
+
+        let response =
+            evaluate_matching_policies(Arc::new(evaluation_environment), &request).unwrap();
+
+        assert!(!response.allowed);
+        assert!(response.patch.is_none());
+        assert_eq!(Some(vec!["labels added".to_string()]), response.warnings);
+        let status = response.status.expect("should be set");
+        assert_eq!(
+            Some("no-privileged: privileged; no-scale: cannot scale".to_string()),
+            status.message
+        );
+        assert_eq!(Some(400), status.code);
+    }
+
+    #[test]
+    fn evaluate_matching_policies_keeps_the_audit_annotations_of_each_policy() {
+        let evaluation_environment =
+            create_evaluation_environment_with_matching_policies(HashMap::from([
+                (
+                    "allow-all",
+                    AdmissionResponse {
+                        allowed: true,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "no-scale",
+                    AdmissionResponse::reject(String::new(), "cannot scale".to_string(), 403),
+                ),
+            ]));
+        let request = build_admission_review_request().request;
+
+        let response =
+            evaluate_matching_policies(Arc::new(evaluation_environment), &request).unwrap();
+
+        assert!(!response.allowed);
+        let audit_annotations = response.audit_annotations.expect("should be set");
+        for (policy_id, decision) in [("allow-all", "allowed"), ("no-scale", "rejected")] {
+            assert_eq!(
+                Some(policy_id),
+                audit_annotations
+                    .get(&format!("{policy_id}.{POLICY_ID_AUDIT_ANNOTATION}"))
+                    .map(String::as_str)
+            );
+            assert_eq!(
+                Some(decision),
+                audit_annotations
+                    .get(&format!("{policy_id}.{DECISION_AUDIT_ANNOTATION}"))
+                    .map(String::as_str)
+            );
+            assert!(audit_annotations.contains_key(&format!(
+                "{policy_id}.{EVALUATION_LATENCY_AUDIT_ANNOTATION}"
+            )));
+        }
+        assert!(!audit_annotations.contains_key(POLICY_ID_AUDIT_ANNOTATION));
+        assert!(!audit_annotations.contains_key(DECISION_AUDIT_ANNOTATION));
+    }
+
+    #[test]
+    fn evaluate_matching_policies_evaluates_the_object_mutated_by_the_previous_policies() {
+        let evaluation_environment = create_evaluation_environment_with_matching_policy_evaluations(
+            vec!["add-labels", "require-labels"],
+            |policy_id, request| {
+                if policy_id.to_string() == "add-labels" {
+                    return mutating_response(
+                        serde_json::json!([{"op": "add", "path": "/metadata", "value": {"labels": {"app": "web"}}}]),
+                        "labels added",
+                    );
+                }
+                let ValidateRequest::AdmissionRequest(adm_req) = request else {
+                    unreachable!()
+                };
+                let labeled = adm_req
+                    .object
+                    .as_ref()
+                    .and_then(|object| object.pointer("/metadata/labels/app"))
+                    .is_some();
+                AdmissionResponse {
+                    allowed: labeled,
+                    ..Default::default()
+                }
+            },
+        );
+        let request = build_admission_review_request().request;
+
+        let response =
+            evaluate_matching_policies(Arc::new(evaluation_environment), &request).unwrap();
+
+        assert!(response.allowed);
+        let mut object = request.object.clone().unwrap();
+        patch::apply_patch(&mut object, &response.patch.unwrap()).unwrap();
+        assert_eq!(
+            Some(&serde_json::json!("web")),
+            object.pointer("/metadata/labels/app")
+        );
+    }
+
+    #[test]
+    fn evaluate_matching_policies_rejects_a_patch_that_does_not_apply_to_the_mutated_object() {
+        // each patch can be applied to the original object, but not on top of the other one
+        let evaluation_environment =
+            create_evaluation_environment_with_matching_policies(HashMap::from([
+                (
+                    "remove-kind",
+                    mutating_response(
+                        serde_json::json!([{"op": "remove", "path": "/kind"}]),
+                        "kind removed",
+                    ),
+                ),
+                (
+                    "replace-kind",
+                    mutating_response(
+                        serde_json::json!([{"op": "replace", "path": "/kind", "value": "Other"}]),
+                        "kind replaced",
+                    ),
+                ),
+            ]));
+        let request = build_admission_review_request().request;
+
+        let response =
+            evaluate_matching_policies(Arc::new(evaluation_environment), &request).unwrap();
+
+        assert!(!response.allowed);
+        let status = response.status.expect("should be set");
+        assert_eq!(Some(500), status.code);
+        assert!(
+            status.message.unwrap().contains(
+                "replace-kind: policy replace-kind returned a patch that cannot be applied"
+            )
+        );
+    }
+
+    /// Create an evaluation environment where the policy mutates the request. The policy
+    /// returns the given patches, one per evaluation, the last one is then repeated
+    fn create_evaluation_environment_that_mutates_request(
+        patches: Vec<serde_json::Value>,
+        verify_mutation_idempotency: bool,
+        mutation_deny_paths: &[&str],
+    ) -> EvaluationEnvironment {
+        create_evaluation_environment_that_returns_responses(
+            patches
+                .into_iter()
+                .map(|patch| mutating_response(patch, "mutated"))
+                .collect(),
+            verify_mutation_idempotency,
+            mutation_deny_paths,
+        )
+    }
+
+    /// Create an evaluation environment where the policy returns the given responses, one
+    /// per evaluation, the last one is then repeated
+    fn create_evaluation_environment_that_returns_responses(
+        responses: Vec<AdmissionResponse>,
+        verify_mutation_idempotency: bool,
+        mutation_deny_paths: &[&str],
+    ) -> EvaluationEnvironment {
+        let mutation_deny_paths = (!mutation_deny_paths.is_empty()).then(|| {
+            let patterns: Vec<String> = mutation_deny_paths.iter().map(|p| p.to_string()).collect();
+            MutationDenyPaths::new(&patterns).unwrap()
+        });
+        let mut mock_evaluation_environment = EvaluationEnvironment::default();
+        mock_evaluation_environment
+            .expect_get_policy_initialization_error()
+            .returning(|_policy_id| None);
+        let evaluations = std::sync::atomic::AtomicUsize::new(0);
+        mock_evaluation_environment
+            .expect_validate()
+            .returning(move |_policy_id, request| {
+                let evaluation = evaluations.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(AdmissionResponse {
+                    uid: request.uid().to_owned(),
+                    ..responses[evaluation.min(responses.len() - 1)].clone()
+                }
+                .into())
+            });
+        mock_evaluation_environment
+            .expect_should_verify_mutation_idempotency()
+            .returning(move || verify_mutation_idempotency);
+        mock_evaluation_environment
+            .expect_get_policy_mutation_deny_paths()
+            .returning(move |_policy_id| Ok(mutation_deny_paths.clone()));
+        mock_evaluation_environment
+            .expect_get_policy_mode()
+            .returning(|_policy_id| Ok(PolicyMode::Protect));
+        mock_evaluation_environment
+            .expect_get_scheduled_policy_mode()
+            .returning(|_policy_id, _now| None);
+        mock_evaluation_environment
+            .expect_get_policy_module_digest()
+            .returning(|_policy_id| None);
+        mock_evaluation_environment
+            .expect_select_policy_variant()
+            .returning(|_policy_id, _request| Ok(PolicyVariant::Stable));
+        mock_evaluation_environment
+            .expect_get_policy_allowed_to_mutate()
+            .returning(|_policy_id| Ok(true));
+        mock_evaluation_environment
+            .expect_get_request_exemption()
+            .returning(|_policy_id, _request| None);
+        mock_evaluation_environment
+            .expect_should_evaluate_request()
+            .returning(|_policy_id, _request| true);
+        mock_evaluation_environment
+            .expect_get_policy_custom_rejection_message()
+            .returning(|_policy_id| Ok(None));
+
+        mock_evaluation_environment
+    }
+
+    #[rstest]
+    #[case::valid_patch(
+        vec![serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}])],
+        false,
+        &[],
+        None
+    )]
+    #[case::patch_cannot_be_applied(
+        vec![serde_json::json!([{"op": "remove", "path": "/spec"}])],
+        false,
+        &[],
+        Some("returned a patch that cannot be applied")
+    )]
+    #[case::idempotent_patch(
+        vec![serde_json::json!([{"op": "add", "path": "/metadata", "value": {"labels": {"app": "web"}}}])],
+        true,
+        &[],
+        None
+    )]
+    #[case::not_idempotent_patch(
+        vec![
+            serde_json::json!([{"op": "add", "path": "/metadata", "value": {"generation": 1}}]),
+            serde_json::json!([{"op": "add", "path": "/metadata", "value": {"generation": 2}}]),
+        ],
+        true,
+        &[],
+        Some("is not idempotent")
+    )]
+    #[case::second_patch_cannot_be_applied(
+        vec![
+            serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}]),
+            serde_json::json!([{"op": "test", "path": "/metadata", "value": "unexpected"}]),
+        ],
+        true,
+        &[],
+        Some("is not idempotent")
+    )]
+    #[case::idempotency_not_verified(
+        vec![
+            serde_json::json!([{"op": "add", "path": "/metadata", "value": {"generation": 1}}]),
+            serde_json::json!([{"op": "add", "path": "/metadata", "value": {"generation": 2}}]),
+        ],
+        false,
+        &[],
+        None
+    )]
+    #[case::denied_path_mutated(
+        vec![serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}])],
+        false,
+        &["/metadata"],
+        Some("is not allowed to mutate /metadata")
+    )]
+    #[case::denied_path_not_mutated(
+        vec![serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}])],
+        false,
+        &["/spec/**"],
+        None
+    )]
+    fn evaluate_verifies_mutation(
+        #[case] patches: Vec<serde_json::Value>,
+        #[case] verify_mutation_idempotency: bool,
+        #[case] mutation_deny_paths: &[&str],
+        #[case] expected_error: Option<&str>,
+    ) {
+        let evaluation_environment = create_evaluation_environment_that_mutates_request(
+            patches,
+            verify_mutation_idempotency,
+            mutation_deny_paths,
+        );
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "mutating-policy",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        match expected_error {
+            None => {
+                assert!(response.allowed);
+                assert!(response.patch.is_some());
+            }
+            Some(expected_error) => {
+                assert!(!response.allowed);
+                assert!(response.patch.is_none());
+                let status = response.status.expect("should be set");
+                assert_eq!(Some(500), status.code);
+                assert!(status.message.unwrap().contains(expected_error));
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_rejects_a_patch_of_a_request_without_object() {
+        let evaluation_environment = create_evaluation_environment_that_mutates_request(
+            vec![serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}])],
+            true,
+            &[],
+        );
+        let mut request = build_admission_review_request().request;
+        request.object = None;
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "mutating-policy",
+            &ValidateRequest::AdmissionRequest(Box::new(request)),
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(!response.allowed);
+        assert!(response.patch.is_none());
+        let status = response.status.expect("should be set");
+        assert_eq!(Some(500), status.code);
+        assert!(
+            status
+                .message
+                .unwrap()
+                .contains("returned a patch, but the request doesn't have an object")
+        );
+    }
+
+    #[test]
+    fn evaluate_rejects_a_mutation_rejected_by_its_policy() {
+        let evaluation_environment = create_evaluation_environment_that_returns_responses(
+            vec![
+                mutating_response(
+                    serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}]),
+                    "mutated",
+                ),
+                AdmissionResponse::reject("".to_owned(), "boom".to_owned(), 400),
+            ],
+            true,
+            &[],
+        );
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "mutating-policy",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(!response.allowed);
+        assert!(response.patch.is_none());
+        let status = response.status.expect("should be set");
+        assert_eq!(Some(500), status.code);
+        assert!(
+            status
+                .message
+                .unwrap()
+                .contains("rejects the object produced by its own mutation")
+        );
+    }
+
+    #[test]
+    fn evaluate_does_not_verify_the_mutation_of_raw_requests() {
+        // The second patch would make the verification fail, it must not be requested
+        let evaluation_environment = create_evaluation_environment_that_mutates_request(
+            vec![
+                serde_json::json!([{"op": "add", "path": "/metadata", "value": {"generation": 1}}]),
+                serde_json::json!([{"op": "add", "path": "/metadata", "value": {"generation": 2}}]),
+            ],
+            true,
+            &["/metadata"],
+        );
+        let validate_request = ValidateRequest::Raw(serde_json::json!({"uid": "raw-request"}));
+
+        let response = evaluate(
+            Arc::new(evaluation_environment),
+            "mutating-policy",
+            &validate_request,
+            RequestOrigin::Validate,
+        )
+        .unwrap();
+
+        assert!(response.allowed);
+        assert!(response.patch.is_some());
     }
 }
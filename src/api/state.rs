@@ -1,9 +1,16 @@
 use tokio::sync::Semaphore;
 
 use crate::evaluation::EvaluationEnvironment;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 pub(crate) struct ApiServerState {
     pub(crate) semaphore: Semaphore,
+    /// The number of evaluations that can run at the same time
+    pub(crate) pool_size: usize,
+    /// The maximum time a request waits for the permission to be evaluated, no limit when not
+    /// set
+    pub(crate) queue_timeout: Option<Duration>,
     pub(crate) evaluation_environment: Arc<EvaluationEnvironment>,
+    /// The bearer token required by the break-glass endpoint
+    pub(crate) break_glass_token: Option<String>,
 }
@@ -0,0 +1,410 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::PathBuf,
+    sync::{OnceLock, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time;
+use tracing::{info, warn};
+
+static BREAK_GLASS: OnceLock<BreakGlass> = OnceLock::new();
+
+/// How often the sentinel file is checked
+const SENTINEL_FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The longest an override can last. The override is meant to unblock the cluster during
+/// an incident, not to disable the policies
+pub(crate) const MAX_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Configuration of the break-glass switch, forcing the policies into monitor mode
+#[derive(Clone, Debug)]
+pub struct BreakGlassConfig {
+    /// The bearer token required by the admin endpoint. The endpoint is not exposed when
+    /// the token is not set
+    pub token: Option<String>,
+    /// The override is activated when this file is created, and deactivated when it's removed
+    pub sentinel_file: Option<PathBuf>,
+    /// How long the override lasts when the activation doesn't define it
+    pub default_ttl: Duration,
+}
+
+/// Initialize the global break-glass switch and start watching the sentinel file, when
+/// configured.
+///
+/// This must be invoked from within a tokio runtime.
+pub fn setup_break_glass(config: BreakGlassConfig) -> Result<()> {
+    BREAK_GLASS
+        .set(BreakGlass::new(config.default_ttl))
+        .map_err(|_| anyhow!("break-glass switch has already been initialized"))?;
+
+    if let Some(sentinel_file) = config.sentinel_file {
+        tokio::spawn(watch_sentinel_file(sentinel_file));
+    }
+
+    Ok(())
+}
+
+/// Returns true when the given policy has to be evaluated in monitor mode, regardless of
+/// its configuration. This is always false when the switch has not been initialized.
+pub(crate) fn forces_monitor_mode(policy_id: &str) -> bool {
+    BREAK_GLASS
+        .get()
+        .is_some_and(|break_glass| break_glass.forces_monitor_mode(policy_id, SystemTime::now()))
+}
+
+/// Activate the override on behalf of the admin endpoint
+pub(crate) fn activate(activation: BreakGlassActivation) -> Result<BreakGlassStatus> {
+    let break_glass = BREAK_GLASS
+        .get()
+        .ok_or_else(|| anyhow!("break-glass switch has not been initialized"))?;
+    let now = SystemTime::now();
+    break_glass.activate(activation, BreakGlassSource::Api, now)?;
+
+    Ok(break_glass.status(now))
+}
+
+/// Deactivate the override on behalf of the admin endpoint, regardless of how it has
+/// been activated
+pub(crate) fn deactivate() -> Result<BreakGlassStatus> {
+    let break_glass = BREAK_GLASS
+        .get()
+        .ok_or_else(|| anyhow!("break-glass switch has not been initialized"))?;
+    break_glass.deactivate(None);
+
+    Ok(break_glass.status(SystemTime::now()))
+}
+
+/// Returns the current state of the override
+pub(crate) fn status() -> Result<BreakGlassStatus> {
+    BREAK_GLASS
+        .get()
+        .map(|break_glass| break_glass.status(SystemTime::now()))
+        .ok_or_else(|| anyhow!("break-glass switch has not been initialized"))
+}
+
+/// Describes the override, as sent to the admin endpoint or written inside of the sentinel
+/// file
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub(crate) struct BreakGlassActivation {
+    /// The policies forced into monitor mode. All the policies are affected when empty
+    #[serde(default)]
+    pub(crate) policies: BTreeSet<String>,
+    /// How long the override lasts
+    pub(crate) ttl_seconds: Option<u64>,
+    /// Why the override has been activated
+    pub(crate) reason: Option<String>,
+}
+
+impl BreakGlassActivation {
+    /// Ensure the override doesn't last longer than `MAX_TTL`
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self.ttl_seconds {
+            Some(ttl_seconds) if ttl_seconds > MAX_TTL.as_secs() => Err(anyhow!(
+                "ttlSeconds cannot be greater than {}",
+                MAX_TTL.as_secs()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the override has been activated
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BreakGlassSource {
+    Api,
+    File,
+}
+
+/// The state of the override, as returned by the admin endpoint
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BreakGlassStatus {
+    pub(crate) active: bool,
+    /// The policies forced into monitor mode, all of them when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) policies: Option<BTreeSet<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<BreakGlassSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) reason: Option<String>,
+    /// RFC 3339 timestamp of the expiration of the override
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) expires_at: Option<String>,
+}
+
+struct BreakGlassOverride {
+    activation: BreakGlassActivation,
+    source: BreakGlassSource,
+    expires_at: SystemTime,
+}
+
+struct BreakGlass {
+    default_ttl: Duration,
+    state: RwLock<Option<BreakGlassOverride>>,
+}
+
+impl BreakGlass {
+    fn new(default_ttl: Duration) -> Self {
+        BreakGlass {
+            default_ttl,
+            state: RwLock::new(None),
+        }
+    }
+
+    /// Activate the override, replacing the previous one. The TTL is counted from `since`.
+    /// The previous override is kept when the activation is not valid
+    fn activate(
+        &self,
+        activation: BreakGlassActivation,
+        source: BreakGlassSource,
+        since: SystemTime,
+    ) -> Result<()> {
+        activation.validate()?;
+        let ttl = activation
+            .ttl_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(self.default_ttl);
+        let expires_at = since
+            .checked_add(ttl)
+            .ok_or_else(|| anyhow!("the break-glass override cannot expire after {ttl:?}"))?;
+        warn!(
+            policies = ?activation.policies,
+            reason = activation.reason.as_deref().unwrap_or_default(),
+            ?source,
+            ttl_seconds = ttl.as_secs(),
+            "break-glass override activated, the policies are forced into monitor mode"
+        );
+
+        *self.state.write().expect("break-glass lock poisoned") = Some(BreakGlassOverride {
+            activation,
+            source,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Deactivate the override. When a source is given, the override is deactivated only
+    /// when it has been activated by the same source
+    fn deactivate(&self, source: Option<BreakGlassSource>) {
+        let mut state = self.state.write().expect("break-glass lock poisoned");
+        if state
+            .as_ref()
+            .is_some_and(|current| source.is_none_or(|source| current.source == source))
+        {
+            info!("break-glass override deactivated");
+            *state = None;
+        }
+    }
+
+    fn forces_monitor_mode(&self, policy_id: &str, now: SystemTime) -> bool {
+        self.state
+            .read()
+            .expect("break-glass lock poisoned")
+            .as_ref()
+            .is_some_and(|current| {
+                now < current.expires_at
+                    && (current.activation.policies.is_empty()
+                        || current.activation.policies.contains(policy_id))
+            })
+    }
+
+    fn status(&self, now: SystemTime) -> BreakGlassStatus {
+        match self
+            .state
+            .read()
+            .expect("break-glass lock poisoned")
+            .as_ref()
+        {
+            Some(current) if now < current.expires_at => BreakGlassStatus {
+                active: true,
+                policies: (!current.activation.policies.is_empty())
+                    .then(|| current.activation.policies.clone()),
+                source: Some(current.source),
+                reason: current.activation.reason.clone(),
+                expires_at: Some(
+                    DateTime::<Utc>::from(current.expires_at)
+                        .to_rfc3339_opts(SecondsFormat::Secs, true),
+                ),
+            },
+            _ => BreakGlassStatus::default(),
+        }
+    }
+}
+
+/// Activate the override when the sentinel file is created or modified, and deactivate it
+/// when the file is removed. The TTL is counted from the last modification of the file.
+async fn watch_sentinel_file(sentinel_file: PathBuf) {
+    let Some(break_glass) = BREAK_GLASS.get() else {
+        return;
+    };
+    let mut last_modified: Option<SystemTime> = None;
+    let mut interval = time::interval(SENTINEL_FILE_POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified = fs::metadata(&sentinel_file).and_then(|metadata| metadata.modified());
+        match modified {
+            Ok(modified) if last_modified != Some(modified) => {
+                last_modified = Some(modified);
+                let activated = read_sentinel_file(&sentinel_file).and_then(|activation| {
+                    break_glass.activate(activation, BreakGlassSource::File, modified)
+                });
+                if let Err(error) = activated {
+                    warn!(
+                        ?error,
+                        ?sentinel_file,
+                        "cannot activate break-glass override from the sentinel file"
+                    );
+                }
+            }
+            Ok(_) => {}
+            Err(_) => {
+                if last_modified.take().is_some() {
+                    break_glass.deactivate(Some(BreakGlassSource::File));
+                }
+            }
+        }
+    }
+}
+
+/// An empty sentinel file forces all the policies into monitor mode, using the default TTL
+fn read_sentinel_file(sentinel_file: &PathBuf) -> Result<BreakGlassActivation> {
+    let contents = fs::read_to_string(sentinel_file)?;
+    if contents.trim().is_empty() {
+        return Ok(BreakGlassActivation::default());
+    }
+
+    Ok(serde_yaml::from_str(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+    fn activation(policies: &[&str], ttl_seconds: Option<u64>) -> BreakGlassActivation {
+        BreakGlassActivation {
+            policies: policies.iter().map(|p| p.to_string()).collect(),
+            ttl_seconds,
+            reason: Some("incident".to_owned()),
+        }
+    }
+
+    #[rstest]
+    #[case::all_policies(activation(&[], None), "policy", Duration::ZERO, true)]
+    #[case::selected_policy(activation(&["policy"], None), "policy", Duration::ZERO, true)]
+    #[case::other_policy(activation(&["policy"], None), "other", Duration::ZERO, false)]
+    #[case::default_ttl_expired(activation(&[], None), "policy", DEFAULT_TTL, false)]
+    #[case::custom_ttl(activation(&[], Some(120)), "policy", DEFAULT_TTL, true)]
+    fn force_monitor_mode(
+        #[case] activation: BreakGlassActivation,
+        #[case] policy_id: &str,
+        #[case] elapsed: Duration,
+        #[case] expected: bool,
+    ) {
+        let break_glass = BreakGlass::new(DEFAULT_TTL);
+        let now = SystemTime::now();
+        break_glass
+            .activate(activation, BreakGlassSource::Api, now)
+            .unwrap();
+
+        assert_eq!(
+            expected,
+            break_glass.forces_monitor_mode(policy_id, now + elapsed)
+        );
+    }
+
+    #[test]
+    fn not_active_by_default() {
+        let break_glass = BreakGlass::new(DEFAULT_TTL);
+
+        assert!(!break_glass.forces_monitor_mode("policy", SystemTime::now()));
+        assert_eq!(
+            BreakGlassStatus::default(),
+            break_glass.status(SystemTime::now())
+        );
+    }
+
+    #[rstest]
+    #[case::any_source(None, false)]
+    #[case::same_source(Some(BreakGlassSource::File), false)]
+    #[case::other_source(Some(BreakGlassSource::Api), true)]
+    fn deactivate(#[case] source: Option<BreakGlassSource>, #[case] still_active: bool) {
+        let break_glass = BreakGlass::new(DEFAULT_TTL);
+        let now = SystemTime::now();
+        break_glass
+            .activate(activation(&[], None), BreakGlassSource::File, now)
+            .unwrap();
+
+        break_glass.deactivate(source);
+
+        assert_eq!(still_active, break_glass.forces_monitor_mode("policy", now));
+    }
+
+    #[rstest]
+    #[case::above_the_max_ttl(Some(MAX_TTL.as_secs() + 1))]
+    #[case::overflowing_ttl(Some(u64::MAX))]
+    fn reject_activation_with_too_long_ttl(#[case] ttl_seconds: Option<u64>) {
+        let break_glass = BreakGlass::new(DEFAULT_TTL);
+        let now = SystemTime::now();
+        break_glass
+            .activate(activation(&["policy"], None), BreakGlassSource::File, now)
+            .unwrap();
+
+        assert!(
+            break_glass
+                .activate(activation(&[], ttl_seconds), BreakGlassSource::Api, now)
+                .is_err()
+        );
+        // The previous override is kept
+        assert_eq!(Some(BreakGlassSource::File), break_glass.status(now).source);
+        assert!(!break_glass.forces_monitor_mode("other", now));
+    }
+
+    #[test]
+    fn report_status() {
+        let break_glass = BreakGlass::new(DEFAULT_TTL);
+        let since = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        break_glass
+            .activate(activation(&["policy"], None), BreakGlassSource::File, since)
+            .unwrap();
+
+        assert_eq!(
+            BreakGlassStatus {
+                active: true,
+                policies: Some(BTreeSet::from(["policy".to_owned()])),
+                source: Some(BreakGlassSource::File),
+                reason: Some("incident".to_owned()),
+                expires_at: Some("2023-11-14T22:14:20Z".to_owned()),
+            },
+            break_glass.status(since)
+        );
+    }
+
+    #[rstest]
+    #[case::empty_file("", BreakGlassActivation::default())]
+    #[case::selected_policies(
+        "policies: [policy]\nttlSeconds: 120\nreason: incident\n",
+        activation(&["policy"], Some(120))
+    )]
+    fn parse_sentinel_file(#[case] contents: &str, #[case] expected: BreakGlassActivation) {
+        let sentinel_file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(sentinel_file.path(), contents).unwrap();
+
+        assert_eq!(
+            expected,
+            read_sentinel_file(&sentinel_file.path().to_path_buf()).unwrap()
+        );
+    }
+}
@@ -81,6 +81,18 @@ pub(crate) fn build_cli() -> Command {
             .env("KUBEWARDEN_WORKERS")
             .help("Number of worker threads to create"),
 
+        Arg::new("workers-queue-timeout")
+            .long("workers-queue-timeout")
+            .value_name("QUEUE_TIMEOUT_MILLISECONDS")
+            .env("KUBEWARDEN_WORKERS_QUEUE_TIMEOUT")
+            .help("Maximum time a request waits for a free worker, before being rejected because the server is overloaded. By default, requests wait until a worker is free"),
+
+        Arg::new("policy-group-workers")
+            .long("policy-group-workers")
+            .value_name("WORKERS_NUMBER")
+            .env("KUBEWARDEN_POLICY_GROUP_WORKERS")
+            .help("Number of threads evaluating concurrently the members of the policy groups using concurrentEvaluation"),
+
         Arg::new("cert-file")
             .long("cert-file")
             .value_name("CERT_FILE")
@@ -153,7 +165,13 @@ pub(crate) fn build_cli() -> Command {
             .value_name("NAMESPACE")
             .env("KUBEWARDEN_ALWAYS_ACCEPT_ADMISSION_REVIEWS_ON_NAMESPACE")
             .required(false)
-            .help("Always accept AdmissionReviews that target the given namespace"),
+            .help("Always accept AdmissionReviews that target the given namespace. Deprecated, use --exemptions-path instead"),
+
+        Arg::new("exemptions-path")
+            .long("exemptions-path")
+            .value_name("EXEMPTIONS_FILE")
+            .env("KUBEWARDEN_EXEMPTIONS_PATH")
+            .help("YAML file holding the namespaces, users, groups and service accounts whose requests are accepted by all the policies"),
 
         Arg::new("disable-timeout-protection")
             .long("disable-timeout-protection")
@@ -198,12 +216,136 @@ pub(crate) fn build_cli() -> Command {
             .action(ArgAction::SetTrue)
             .help("Do not exit with an error if the Kubernetes connection fails. This will cause context-aware policies to break when there's no connection with Kubernetes."),
 
+        Arg::new("decision-log-path")
+            .long("decision-log-path")
+            .value_name("DECISION_LOG_PATH")
+            .env("KUBEWARDEN_DECISION_LOG_PATH")
+            .required(false)
+            .help("Write a JSON record for each policy evaluation to the given file"),
+
+        Arg::new("decision-log-max-size")
+            .long("decision-log-max-size")
+            .value_name("MAXIMUM_SIZE_MEGABYTES")
+            .env("KUBEWARDEN_DECISION_LOG_MAX_SIZE")
+            .default_value("100")
+            .help("Rotate the decision log file once it exceeds the given size"),
+
+        Arg::new("decision-log-max-age")
+            .long("decision-log-max-age")
+            .value_name("MAXIMUM_AGE_SECONDS")
+            .env("KUBEWARDEN_DECISION_LOG_MAX_AGE")
+            .required(false)
+            .help("Rotate the decision log file once it has been written for the given time"),
+
+        Arg::new("decision-log-max-files")
+            .long("decision-log-max-files")
+            .value_name("MAXIMUM_FILES")
+            .env("KUBEWARDEN_DECISION_LOG_MAX_FILES")
+            .default_value("5")
+            .help("Number of rotated decision log files to keep"),
+
+        Arg::new("decision-log-include-payload")
+            .long("decision-log-include-payload")
+            .env("KUBEWARDEN_DECISION_LOG_INCLUDE_PAYLOAD")
+            .action(ArgAction::SetTrue)
+            .help("Include the evaluated request inside of the decision log records"),
+
+        Arg::new("decision-log-exclude-dry-run")
+            .long("decision-log-exclude-dry-run")
+            .env("KUBEWARDEN_DECISION_LOG_EXCLUDE_DRY_RUN")
+            .action(ArgAction::SetTrue)
+            .help("Do not write the dry-run requests to the decision log"),
+
+        Arg::new("decision-webhook-url")
+            .long("decision-webhook-url")
+            .value_name("DECISION_WEBHOOK_URL")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_URL")
+            .required(false)
+            .help("Send batches of policy evaluation records to the given HTTP endpoint"),
+
+        Arg::new("decision-webhook-batch-size")
+            .long("decision-webhook-batch-size")
+            .value_name("BATCH_SIZE")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_BATCH_SIZE")
+            .default_value("100")
+            .help("Maximum number of records sent to the decision webhook with a single request"),
+
+        Arg::new("decision-webhook-flush-interval")
+            .long("decision-webhook-flush-interval")
+            .value_name("FLUSH_INTERVAL_MILLISECONDS")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_FLUSH_INTERVAL")
+            .default_value("1000")
+            .help("Maximum time a record waits before being sent to the decision webhook"),
+
+        Arg::new("decision-webhook-buffer-size")
+            .long("decision-webhook-buffer-size")
+            .value_name("BUFFER_SIZE")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_BUFFER_SIZE")
+            .default_value("10000")
+            .help("Number of records waiting for delivery to the decision webhook. Further records are dropped"),
+
+        Arg::new("decision-webhook-max-retries")
+            .long("decision-webhook-max-retries")
+            .value_name("MAXIMUM_RETRIES")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_MAX_RETRIES")
+            .default_value("3")
+            .help("Number of times the delivery to the decision webhook is retried before dropping the records"),
+
+        Arg::new("decision-webhook-only-violations")
+            .long("decision-webhook-only-violations")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_ONLY_VIOLATIONS")
+            .action(ArgAction::SetTrue)
+            .help("Send to the decision webhook only the rejected requests and the violations of policies in monitor mode"),
+
+        Arg::new("decision-webhook-exclude-dry-run")
+            .long("decision-webhook-exclude-dry-run")
+            .env("KUBEWARDEN_DECISION_WEBHOOK_EXCLUDE_DRY_RUN")
+            .action(ArgAction::SetTrue)
+            .help("Do not send the dry-run requests to the decision webhook"),
+
+        Arg::new("break-glass-token-file")
+            .long("break-glass-token-file")
+            .value_name("TOKEN_FILE")
+            .env("KUBEWARDEN_BREAK_GLASS_TOKEN_FILE")
+            .help("File holding the bearer token required by the /break-glass endpoint. The endpoint is not exposed when not set"),
+
+        Arg::new("break-glass-file")
+            .long("break-glass-file")
+            .value_name("SENTINEL_FILE")
+            .env("KUBEWARDEN_BREAK_GLASS_FILE")
+            .help("Force the policies into monitor mode while the given file exists"),
+
+        Arg::new("break-glass-default-ttl")
+            .long("break-glass-default-ttl")
+            .value_name("TTL_SECONDS")
+            .env("KUBEWARDEN_BREAK_GLASS_DEFAULT_TTL")
+            .default_value("900")
+            .help("Number of seconds after which the break-glass override expires, when the activation doesn't define it. Cannot be greater than 604800 (7 days)"),
+
         Arg::new("enable-pprof")
             .long("enable-pprof")
             .env("KUBEWARDEN_ENABLE_PPROF")
             .action(ArgAction::SetTrue)
             .help("Enable pprof profiling"),
 
+        Arg::new("enforce-metadata-rules")
+            .long("enforce-metadata-rules")
+            .env("KUBEWARDEN_ENFORCE_METADATA_RULES")
+            .action(ArgAction::SetTrue)
+            .help("Accept without evaluating the policies the requests that do not match the rules declared by their metadata"),
+
+        Arg::new("monitor-mode-warnings")
+            .long("monitor-mode-warnings")
+            .env("KUBEWARDEN_MONITOR_MODE_WARNINGS")
+            .action(ArgAction::SetTrue)
+            .help("Add a warning and an audit annotation to the requests that would have been rejected by the policies running in monitor mode"),
+
+        Arg::new("verify-mutation-idempotency")
+            .long("verify-mutation-idempotency")
+            .env("KUBEWARDEN_VERIFY_MUTATION_IDEMPOTENCY")
+            .action(ArgAction::SetTrue)
+            .help("Evaluate the mutating policies a second time, against the mutated object, to ensure their mutations are idempotent"),
+
         Arg::new("continue-on-errors")
             .long("continue-on-errors")
             .env("KUBEWARDEN_CONTINUE_ON_ERRORS")
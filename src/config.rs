@@ -1,5 +1,6 @@
 use anyhow::{Result, anyhow};
 use clap::ArgMatches;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
 use lazy_static::lazy_static;
 use opentelemetry_otlp::tonic_types::transport::{Certificate, ClientTlsConfig, Identity};
 use policy_evaluator::{
@@ -9,15 +10,30 @@ use policy_evaluator::{
         sources::{Sources, read_sources_file},
         verify::config::{LatestVerificationConfig, VerificationConfigV1, read_verification_file},
     },
-    policy_metadata::ContextAwareResource,
+    policy_metadata::{ContextAwareResource, Rule},
 };
 use serde::Deserialize;
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     env,
     fs::{self, File},
     net::SocketAddr,
     path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    break_glass::{self, BreakGlassConfig},
+    decision_log::DecisionLogConfig,
+    decision_webhook::DecisionWebhookConfig,
+    evaluation::{
+        enforcement_schedule::EnforcementWindows,
+        exemptions::{ExemptionMatcher, ExemptionScope},
+        message_template::MessageTemplate,
+        mutation_deny_paths::MutationDenyPaths,
+        policy_group_expression::validate_mutating_expression,
+        request_matcher::validate_label_selector,
+    },
 };
 
 pub static SERVICE_NAME: &str = "kubewarden-policy-server";
@@ -35,11 +51,16 @@ pub struct Config {
     pub policies: HashMap<String, PolicyOrPolicyGroup>,
     pub policies_download_dir: PathBuf,
     pub ignore_kubernetes_connection_failure: bool,
-    pub always_accept_admission_reviews_on_namespace: Option<String>,
+    /// The requests that are accepted by all the policies without evaluating them
+    pub exemptions: Exemptions,
     // This is the global timeout for each policy evaluation.
     pub policy_evaluation_limit_seconds: Option<u64>,
     pub tls_config: Option<TlsConfig>,
     pub pool_size: usize,
+    /// The maximum time a request waits for a free worker
+    pub pool_queue_timeout: Option<Duration>,
+    /// The number of threads evaluating concurrently the members of the policy groups
+    pub policy_group_pool_size: usize,
     pub metrics_enabled: bool,
     pub sigstore_cache_dir: PathBuf,
     pub verification_config: Option<VerificationConfigV1>,
@@ -52,6 +73,12 @@ pub struct Config {
     pub daemon_stdout_file: Option<String>,
     pub daemon_stderr_file: Option<String>,
     pub continue_on_errors: bool,
+    pub verify_mutation_idempotency: bool,
+    pub enforce_metadata_rules: bool,
+    pub monitor_mode_warnings: bool,
+    pub decision_log: Option<DecisionLogConfig>,
+    pub decision_webhook: Option<DecisionWebhookConfig>,
+    pub break_glass: Option<BreakGlassConfig>,
 }
 
 pub struct TlsConfig {
@@ -91,9 +118,21 @@ impl Config {
                 v.parse::<usize>()
                     .expect("error parsing the number of workers")
             });
-        let always_accept_admission_reviews_on_namespace = matches
-            .get_one::<String>("always-accept-admission-reviews-on-namespace")
-            .map(|s| s.to_owned());
+        let pool_queue_timeout = matches
+            .get_one::<String>("workers-queue-timeout")
+            .map(|v| {
+                v.parse::<u64>()
+                    .map(Duration::from_millis)
+                    .map_err(|e| anyhow!("error parsing workers-queue-timeout: {}", e))
+            })
+            .transpose()?;
+        let policy_group_pool_size = matches
+            .get_one::<String>("policy-group-workers")
+            .map_or_else(num_cpus::get, |v| {
+                v.parse::<usize>()
+                    .expect("error parsing the number of policy group workers")
+            });
+        let exemptions = exemptions(matches)?;
 
         let metrics_enabled = matches
             .get_one::<bool>("enable-metrics")
@@ -145,6 +184,25 @@ impl Config {
             .expect("clap should have assigned a default value")
             .to_owned();
 
+        let verify_mutation_idempotency = matches
+            .get_one::<bool>("verify-mutation-idempotency")
+            .expect("clap should have assigned a default value")
+            .to_owned();
+
+        let enforce_metadata_rules = matches
+            .get_one::<bool>("enforce-metadata-rules")
+            .expect("clap should have assigned a default value")
+            .to_owned();
+
+        let monitor_mode_warnings = matches
+            .get_one::<bool>("monitor-mode-warnings")
+            .expect("clap should have assigned a default value")
+            .to_owned();
+
+        let decision_log = decision_log_config(matches)?;
+        let decision_webhook = decision_webhook_config(matches)?;
+        let break_glass = break_glass_config(matches)?;
+
         Ok(Self {
             addr,
             readiness_probe_addr,
@@ -153,9 +211,11 @@ impl Config {
             policies_download_dir,
             ignore_kubernetes_connection_failure,
             tls_config,
-            always_accept_admission_reviews_on_namespace,
+            exemptions,
             policy_evaluation_limit_seconds,
             pool_size,
+            pool_queue_timeout,
+            policy_group_pool_size,
             metrics_enabled,
             sigstore_cache_dir,
             verification_config,
@@ -168,6 +228,12 @@ impl Config {
             daemon_stderr_file,
             enable_pprof,
             continue_on_errors,
+            verify_mutation_idempotency,
+            enforce_metadata_rules,
+            monitor_mode_warnings,
+            decision_log,
+            decision_webhook,
+            break_glass,
         })
     }
 }
@@ -192,6 +258,131 @@ fn readiness_probe_bind_address(matches: &clap::ArgMatches) -> Result<SocketAddr
     .map_err(|e| anyhow!("error parsing arguments: {}", e))
 }
 
+fn decision_log_config(matches: &clap::ArgMatches) -> Result<Option<DecisionLogConfig>> {
+    let path = match matches.get_one::<String>("decision-log-path") {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(None),
+    };
+    let max_size_megabytes = matches
+        .get_one::<String>("decision-log-max-size")
+        .expect("This should not happen, there's a default value for decision-log-max-size")
+        .parse::<u64>()
+        .map_err(|e| anyhow!("error parsing decision-log-max-size: {}", e))?;
+    let max_age = matches
+        .get_one::<String>("decision-log-max-age")
+        .map(|max_age| max_age.parse::<u64>().map(Duration::from_secs))
+        .transpose()
+        .map_err(|e| anyhow!("error parsing decision-log-max-age: {}", e))?;
+    let max_files = matches
+        .get_one::<String>("decision-log-max-files")
+        .expect("This should not happen, there's a default value for decision-log-max-files")
+        .parse::<usize>()
+        .map_err(|e| anyhow!("error parsing decision-log-max-files: {}", e))?;
+    let include_payload = matches
+        .get_one::<bool>("decision-log-include-payload")
+        .expect("clap should have assigned a default value")
+        .to_owned();
+    let exclude_dry_run = matches
+        .get_one::<bool>("decision-log-exclude-dry-run")
+        .expect("clap should have assigned a default value")
+        .to_owned();
+
+    Ok(Some(DecisionLogConfig {
+        path,
+        max_size_bytes: max_size_megabytes * 1024 * 1024,
+        max_age,
+        max_files,
+        include_payload,
+        exclude_dry_run,
+    }))
+}
+
+fn decision_webhook_config(matches: &clap::ArgMatches) -> Result<Option<DecisionWebhookConfig>> {
+    let url = match matches.get_one::<String>("decision-webhook-url") {
+        Some(url) => url.to_owned(),
+        None => return Ok(None),
+    };
+    let batch_size = matches
+        .get_one::<String>("decision-webhook-batch-size")
+        .expect("This should not happen, there's a default value for decision-webhook-batch-size")
+        .parse::<usize>()
+        .map_err(|e| anyhow!("error parsing decision-webhook-batch-size: {}", e))?;
+    let flush_interval = matches
+        .get_one::<String>("decision-webhook-flush-interval")
+        .expect(
+            "This should not happen, there's a default value for decision-webhook-flush-interval",
+        )
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|e| anyhow!("error parsing decision-webhook-flush-interval: {}", e))?;
+    let buffer_size = matches
+        .get_one::<String>("decision-webhook-buffer-size")
+        .expect("This should not happen, there's a default value for decision-webhook-buffer-size")
+        .parse::<usize>()
+        .map_err(|e| anyhow!("error parsing decision-webhook-buffer-size: {}", e))?;
+    let max_retries = matches
+        .get_one::<String>("decision-webhook-max-retries")
+        .expect("This should not happen, there's a default value for decision-webhook-max-retries")
+        .parse::<u32>()
+        .map_err(|e| anyhow!("error parsing decision-webhook-max-retries: {}", e))?;
+    let only_violations = matches
+        .get_one::<bool>("decision-webhook-only-violations")
+        .expect("clap should have assigned a default value")
+        .to_owned();
+    let exclude_dry_run = matches
+        .get_one::<bool>("decision-webhook-exclude-dry-run")
+        .expect("clap should have assigned a default value")
+        .to_owned();
+
+    Ok(Some(DecisionWebhookConfig {
+        url,
+        batch_size,
+        flush_interval,
+        buffer_size,
+        max_retries,
+        only_violations,
+        exclude_dry_run,
+    }))
+}
+
+fn break_glass_config(matches: &clap::ArgMatches) -> Result<Option<BreakGlassConfig>> {
+    let token = matches
+        .get_one::<String>("break-glass-token-file")
+        .map(|path| {
+            fs::read_to_string(path)
+                .map(|token| token.trim().to_owned())
+                .map_err(|e| anyhow!("error while loading break-glass token from {}: {}", path, e))
+        })
+        .transpose()?;
+    if token.as_deref().is_some_and(str::is_empty) {
+        return Err(anyhow!("the break-glass token cannot be empty"));
+    }
+    let sentinel_file = matches
+        .get_one::<String>("break-glass-file")
+        .map(PathBuf::from);
+    if token.is_none() && sentinel_file.is_none() {
+        return Ok(None);
+    }
+    let default_ttl = matches
+        .get_one::<String>("break-glass-default-ttl")
+        .expect("This should not happen, there's a default value for break-glass-default-ttl")
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .map_err(|e| anyhow!("error parsing break-glass-default-ttl: {}", e))?;
+    if default_ttl > break_glass::MAX_TTL {
+        return Err(anyhow!(
+            "break-glass-default-ttl cannot be greater than {}",
+            break_glass::MAX_TTL.as_secs()
+        ));
+    }
+
+    Ok(Some(BreakGlassConfig {
+        token,
+        sentinel_file,
+        default_ttl,
+    }))
+}
+
 fn build_tls_config(matches: &clap::ArgMatches) -> Result<Option<TlsConfig>> {
     let cert_file = matches.get_one::<PathBuf>("cert-file").cloned();
     let key_file = matches.get_one::<PathBuf>("key-file").cloned();
@@ -229,25 +420,76 @@ fn policies(matches: &clap::ArgMatches) -> Result<HashMap<String, PolicyOrPolicy
         )
     })?;
 
-    validate_policies(&policies)?;
-
-    Ok(policies)
+    validate_policies(&policies)
 }
 
-// Validate the policies and policy groups:
+// Resolve the references made by the members of the policy groups, then validate the
+// policies and policy groups:
 //  - ensure policy names do not contain a '/' character
 //  - ensure names of policy group's policies do not contain a '/' character
-fn validate_policies(policies: &HashMap<String, PolicyOrPolicyGroup>) -> Result<()> {
+//  - ensure the references made by the members of a policy group can be resolved, without cycles
+//  - ensure each policy group, including the nested ones, defines exactly one rule
+//  - ensure the policy groups evaluating their members concurrently do not mutate the requests
+//  - ensure the expression of the policy groups mutating the requests applies only the
+//    mutations of the members that made it true
+//  - ensure the weight of a canary is a percentage
+//  - ensure the mutation deny paths of a policy are valid globs
+//  - ensure the label selectors of a policy use known operators
+//  - ensure the exemptions of a policy are valid regular expressions
+//  - ensure the enforcement schedule of a policy uses valid cron expressions
+//  - ensure the custom rejection message of a policy is a valid template
+//  - ensure the steps of a mutation pipeline have unique names, without a '/' character
+// The resolved policies are returned.
+fn validate_policies(
+    policies: &HashMap<String, PolicyOrPolicyGroup>,
+) -> Result<HashMap<String, PolicyOrPolicyGroup>> {
+    let policies = resolve_policy_group_references(policies)?;
     for (name, policy) in policies.iter() {
         if name.contains('/') {
             return Err(anyhow!("policy name '{}' contains a '/' character", name));
         }
+        if let PolicyOrPolicyGroup::Policy {
+            canary: Some(canary),
+            ..
+        } = policy
+            && canary.weight > 100
+        {
+            return Err(anyhow!(
+                "policy '{}' has a canary weight of {}, the value must be between 0 and 100",
+                name,
+                canary.weight
+            ));
+        }
+        if let PolicyOrPolicyGroup::Policy {
+            mutation_deny_paths,
+            ..
+        } = policy
+        {
+            MutationDenyPaths::new(mutation_deny_paths)
+                .map_err(|e| anyhow!("policy '{}' has {}", name, e))?;
+        }
+        let request_filter = policy.request_filter();
+        for selector in [
+            &request_filter.namespace_selector,
+            &request_filter.object_selector,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            validate_label_selector(selector)
+                .map_err(|e| anyhow!("policy '{}' has {}", name, e))?;
+        }
+        ExemptionMatcher::new(&request_filter.exemptions, ExemptionScope::Policy)
+            .map_err(|e| anyhow!("policy '{}' has {}", name, e))?;
+        if let Some(message) = policy.custom_rejection_message() {
+            MessageTemplate::new(message).map_err(|e| anyhow!("policy '{}' has {}", name, e))?;
+        }
+        if let Some(enforcement_schedule) = policy.enforcement_schedule() {
+            EnforcementWindows::new(enforcement_schedule)
+                .map_err(|e| anyhow!("policy '{}' has {}", name, e))?;
+        }
         if let PolicyOrPolicyGroup::PolicyGroup { policies, .. } = policy {
-            let policies_with_invalid_name: Vec<String> = policies
-                .iter()
-                .filter_map(|(id, _)| if id.contains('/') { Some(id) } else { None })
-                .cloned()
-                .collect();
+            let policies_with_invalid_name = policy_group_members_with_invalid_name(policies);
             if !policies_with_invalid_name.is_empty() {
                 return Err(anyhow!(
                     "policy group '{}' contains policies with invalid names: {:?}",
@@ -255,9 +497,81 @@ fn validate_policies(policies: &HashMap<String, PolicyOrPolicyGroup>) -> Result<
                     policies_with_invalid_name
                 ));
             }
+            policy
+                .settings()
+                .map_err(|e| anyhow!("policy group '{}' has {}", name, e))?;
+            validate_nested_policy_group_rules(policies)
+                .map_err(|e| anyhow!("policy group '{}' contains a {}", name, e))?;
+        }
+        if let PolicyOrPolicyGroup::PolicyGroup {
+            allowed_to_mutate: Some(true),
+            concurrent_evaluation: true,
+            ..
+        } = policy
+        {
+            return Err(anyhow!(
+                "policy group '{}' cannot evaluate its members concurrently, because it is allowed to mutate the requests",
+                name
+            ));
+        }
+        if let PolicyOrPolicyGroup::PolicyGroup {
+            allowed_to_mutate: Some(true),
+            expression: Some(expression),
+            ..
+        } = policy
+        {
+            validate_mutating_expression(expression).map_err(|e| {
+                anyhow!(
+                    "policy group '{}' is allowed to mutate the requests, but its expression {}",
+                    name,
+                    e
+                )
+            })?;
+        }
+        if let PolicyOrPolicyGroup::MutationPipeline { steps, .. } = policy {
+            let mut step_names: HashSet<&str> = HashSet::new();
+            for step in steps {
+                if step.name.contains('/') {
+                    return Err(anyhow!(
+                        "mutation pipeline '{}' contains a step with an invalid name: {}",
+                        name,
+                        step.name
+                    ));
+                }
+                if !step_names.insert(step.name.as_str()) {
+                    return Err(anyhow!(
+                        "mutation pipeline '{}' contains multiple steps named '{}'",
+                        name,
+                        step.name
+                    ));
+                }
+            }
         }
     }
-    Ok(())
+    Ok(policies)
+}
+
+/// Read the global exemptions. The namespace given with the
+/// `--always-accept-admission-reviews-on-namespace` flag is added to the exempted ones
+fn exemptions(matches: &clap::ArgMatches) -> Result<Exemptions> {
+    let mut exemptions = match matches.get_one::<String>("exemptions-path") {
+        Some(path) => {
+            let exemptions_file = File::open(path)
+                .map_err(|e| anyhow!("error while loading exemptions from {}: {}", path, e))?;
+            serde_yaml::from_reader(exemptions_file)
+                .map_err(|e| anyhow!("error while loading exemptions from {}: {}", path, e))?
+        }
+        None => Exemptions::default(),
+    };
+    if let Some(namespace) =
+        matches.get_one::<String>("always-accept-admission-reviews-on-namespace")
+    {
+        exemptions.namespaces.push(regex::escape(namespace));
+    }
+
+    ExemptionMatcher::new(&exemptions, ExemptionScope::Global)?;
+
+    Ok(exemptions)
 }
 
 fn verification_config(matches: &clap::ArgMatches) -> Result<Option<LatestVerificationConfig>> {
@@ -293,16 +607,386 @@ fn remote_server_options(matches: &clap::ArgMatches) -> Result<Option<Sources>>
 pub enum PolicyOrPolicyGroupSettings {
     Policy(PolicySettings),
     PolicyGroup {
-        expression: String,
+        rule: PolicyGroupRule,
         message: String,
         policies: Vec<String>,
+        /// Whether all the members are evaluated concurrently, before the rule of the group
+        concurrent_evaluation: bool,
+    },
+    MutationPipeline {
+        /// The names of the steps, in evaluation order
+        steps: Vec<String>,
+    },
+}
+
+/// The rule deciding whether a policy group accepts the request
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyGroupRule {
+    /// A boolean Rhai expression, calling the members of the group
+    Expression(String),
+    /// The request is accepted when the sum of the weights of the members accepting it
+    /// reaches `min_score`. A `minPassing` rule is a score where each member weighs 1.
+    Score {
+        min_score: u64,
+        /// The weight of each member
+        weights: HashMap<String, u64>,
+    },
+}
+
+/// Build the rule of a policy group. Exactly one of `expression`, `min_passing` and
+/// `min_score` must be set, and the weights of the members can be set only with `min_score`.
+/// The minimum must be reachable, and cannot be 0.
+fn policy_group_rule(
+    expression: &Option<String>,
+    min_passing: Option<u64>,
+    min_score: Option<u64>,
+    policies: &HashMap<String, PolicyGroupMember>,
+) -> Result<PolicyGroupRule> {
+    let weighted_members: Vec<&String> = policies
+        .iter()
+        .filter(|(_, member)| member.weight().is_some())
+        .map(|(name, _)| name)
+        .collect();
+    if min_score.is_none() && !weighted_members.is_empty() {
+        return Err(anyhow!(
+            "members with a weight, which is used only by minScore: {:?}",
+            weighted_members
+        ));
+    }
+
+    match (expression, min_passing, min_score) {
+        (Some(expression), None, None) => Ok(PolicyGroupRule::Expression(expression.clone())),
+        (None, Some(0), None) => Err(anyhow!("a minPassing of 0, which accepts every request")),
+        (None, Some(min_passing), None) => {
+            if min_passing > policies.len() as u64 {
+                return Err(anyhow!(
+                    "a minPassing of {}, but only {} members",
+                    min_passing,
+                    policies.len()
+                ));
+            }
+            Ok(PolicyGroupRule::Score {
+                min_score: min_passing,
+                weights: policies.keys().map(|name| (name.clone(), 1)).collect(),
+            })
+        }
+        (None, None, Some(0)) => Err(anyhow!("a minScore of 0, which accepts every request")),
+        (None, None, Some(min_score)) => {
+            let weights: HashMap<String, u64> = policies
+                .iter()
+                .map(|(name, member)| (name.clone(), member.weight().unwrap_or(1)))
+                .collect();
+            let max_score = weights
+                .values()
+                .try_fold(0u64, |sum, weight| sum.checked_add(*weight))
+                .ok_or_else(|| anyhow!("weights whose sum exceeds {}", u64::MAX))?;
+            if min_score > max_score {
+                return Err(anyhow!(
+                    "a minScore of {}, but the weights of the members sum to {}",
+                    min_score,
+                    max_score
+                ));
+            }
+            Ok(PolicyGroupRule::Score { min_score, weights })
+        }
+        (None, None, None) => Err(anyhow!(
+            "no rule, one of expression, minPassing and minScore must be set"
+        )),
+        _ => Err(anyhow!(
+            "multiple rules, only one of expression, minPassing and minScore can be set"
+        )),
+    }
+}
+
+/// `PolicyGroupMember` represents a member of a policy group: either a single policy, a
+/// nested policy group, or a reference to a policy defined at the top level of the
+/// configuration. The outcome of a nested group is used by the expression of its parent
+/// like the one of a single policy.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum PolicyGroupMember {
+    /// A single policy
+    #[serde(rename_all = "camelCase")]
+    Policy {
+        /// The URL where the policy is located
+        module: String,
+        /// The settings for the policy
+        settings: Option<PolicySettings>,
+        /// The list of Kubernetes resources the policy is allowed to access
+        #[serde(default)]
+        context_aware_resources: BTreeSet<ContextAwareResource>,
+        /// Timeout for the evaluation of the policy
+        timeout_eval_seconds: Option<u64>,
+        /// The weight of the member, used by the `minScore` rule of the group
+        weight: Option<u64>,
     },
+    /// A policy group nested inside of another one
+    #[serde(rename_all = "camelCase")]
+    PolicyGroup {
+        /// The policies that make up for this group
+        /// Key is a unique identifier
+        policies: HashMap<String, PolicyGroupMember>,
+        /// The expression that is used to evaluate the group of policies
+        expression: Option<String>,
+        /// The minimum number of members that must accept the request
+        min_passing: Option<u64>,
+        /// The minimum sum of the weights of the members that must accept the request
+        min_score: Option<u64>,
+        /// The message that is returned when the group of policies evaluates to false
+        message: String,
+        /// The weight of the member, used by the `minScore` rule of the parent group
+        weight: Option<u64>,
+        /// Whether all the members are evaluated concurrently, before the rule of the group
+        #[serde(default)]
+        concurrent_evaluation: bool,
+    },
+    /// A reference to a policy, or a policy group, defined at the top level of the
+    /// configuration. The references are replaced by a copy of the referenced policy once
+    /// the configuration is loaded, see `resolve_policy_group_references`
+    Reference {
+        /// The name of the referenced policy
+        #[serde(rename = "ref")]
+        reference: String,
+        /// The weight of the member, used by the `minScore` rule of the group
+        weight: Option<u64>,
+    },
+}
+
+impl PolicyGroupMember {
+    pub fn settings(&self) -> Result<PolicyOrPolicyGroupSettings> {
+        match self {
+            PolicyGroupMember::Policy { settings, .. } => Ok(PolicyOrPolicyGroupSettings::Policy(
+                settings.clone().unwrap_or_default(),
+            )),
+            PolicyGroupMember::PolicyGroup {
+                expression,
+                min_passing,
+                min_score,
+                message,
+                policies,
+                concurrent_evaluation,
+                ..
+            } => Ok(PolicyOrPolicyGroupSettings::PolicyGroup {
+                rule: policy_group_rule(expression, *min_passing, *min_score, policies)?,
+                message: message.clone(),
+                policies: policies.keys().cloned().collect(),
+                concurrent_evaluation: *concurrent_evaluation,
+            }),
+            PolicyGroupMember::Reference { reference, .. } => Err(anyhow!(
+                "the reference to policy '{}' has not been resolved",
+                reference
+            )),
+        }
+    }
+
+    /// The weight of the member, used by the `minScore` rule of the group
+    pub fn weight(&self) -> Option<u64> {
+        match self {
+            PolicyGroupMember::Policy { weight, .. }
+            | PolicyGroupMember::PolicyGroup { weight, .. }
+            | PolicyGroupMember::Reference { weight, .. } => *weight,
+        }
+    }
+
+    /// Returns true when the member, or one of the members of a nested group, defines a
+    /// timeout for its evaluation
+    pub fn has_timeout(&self) -> bool {
+        match self {
+            PolicyGroupMember::Policy {
+                timeout_eval_seconds,
+                ..
+            } => timeout_eval_seconds.is_some(),
+            PolicyGroupMember::PolicyGroup { policies, .. } => {
+                policies.values().any(PolicyGroupMember::has_timeout)
+            }
+            PolicyGroupMember::Reference { .. } => false,
+        }
+    }
+
+    /// Returns true when the member, or one of the members of a nested group, is a policy
+    /// group evaluating its members concurrently
+    pub fn has_concurrent_evaluation(&self) -> bool {
+        match self {
+            PolicyGroupMember::PolicyGroup {
+                concurrent_evaluation,
+                policies,
+                ..
+            } => {
+                *concurrent_evaluation
+                    || policies
+                        .values()
+                        .any(PolicyGroupMember::has_concurrent_evaluation)
+            }
+            PolicyGroupMember::Policy { .. } | PolicyGroupMember::Reference { .. } => false,
+        }
+    }
+}
+
+/// Replace the members of the policy groups that reference a top level policy with a copy of
+/// the module, the settings, the context aware resources and the timeout of the referenced
+/// policy. A reference to a policy group becomes a nested group.
+///
+/// An error is returned when a reference cannot be resolved, when it targets a mutation
+/// pipeline, or when a group references itself.
+fn resolve_policy_group_references(
+    policies: &HashMap<String, PolicyOrPolicyGroup>,
+) -> Result<HashMap<String, PolicyOrPolicyGroup>> {
+    let mut resolved_policies = policies.clone();
+    for (name, policy) in resolved_policies.iter_mut() {
+        if let PolicyOrPolicyGroup::PolicyGroup {
+            policies: members, ..
+        } = policy
+        {
+            *members = resolve_policy_group_members(members, policies, &mut vec![name.clone()])?;
+        }
+    }
+    Ok(resolved_policies)
+}
+
+/// Resolve the references made by the members of a group. `referencing_groups` holds the
+/// names of the top level groups being resolved, from the outermost one, and is used to
+/// detect the cycles.
+fn resolve_policy_group_members(
+    members: &HashMap<String, PolicyGroupMember>,
+    policies: &HashMap<String, PolicyOrPolicyGroup>,
+    referencing_groups: &mut Vec<String>,
+) -> Result<HashMap<String, PolicyGroupMember>> {
+    let mut resolved_members = HashMap::new();
+    for (member_name, member) in members {
+        let resolved_member = match member {
+            PolicyGroupMember::Policy { .. } => member.clone(),
+            PolicyGroupMember::PolicyGroup {
+                policies: nested_members,
+                expression,
+                min_passing,
+                min_score,
+                message,
+                weight,
+                concurrent_evaluation,
+            } => PolicyGroupMember::PolicyGroup {
+                policies: resolve_policy_group_members(
+                    nested_members,
+                    policies,
+                    referencing_groups,
+                )?,
+                expression: expression.clone(),
+                min_passing: *min_passing,
+                min_score: *min_score,
+                message: message.clone(),
+                weight: *weight,
+                concurrent_evaluation: *concurrent_evaluation,
+            },
+            PolicyGroupMember::Reference { reference, weight } => {
+                if referencing_groups.contains(reference) {
+                    return Err(anyhow!(
+                        "policy group '{}' has a cyclic reference: {} -> {}",
+                        referencing_groups[0],
+                        referencing_groups.join(" -> "),
+                        reference
+                    ));
+                }
+                match policies.get(reference) {
+                    Some(PolicyOrPolicyGroup::Policy {
+                        module,
+                        settings,
+                        context_aware_resources,
+                        timeout_eval_seconds,
+                        ..
+                    }) => PolicyGroupMember::Policy {
+                        module: module.clone(),
+                        settings: settings.clone(),
+                        context_aware_resources: context_aware_resources.clone(),
+                        timeout_eval_seconds: *timeout_eval_seconds,
+                        weight: *weight,
+                    },
+                    Some(PolicyOrPolicyGroup::PolicyGroup {
+                        policies: referenced_members,
+                        expression,
+                        min_passing,
+                        min_score,
+                        message,
+                        concurrent_evaluation,
+                        ..
+                    }) => {
+                        referencing_groups.push(reference.clone());
+                        let referenced_members = resolve_policy_group_members(
+                            referenced_members,
+                            policies,
+                            referencing_groups,
+                        )?;
+                        referencing_groups.pop();
+                        PolicyGroupMember::PolicyGroup {
+                            policies: referenced_members,
+                            expression: expression.clone(),
+                            min_passing: *min_passing,
+                            min_score: *min_score,
+                            message: message.clone(),
+                            weight: *weight,
+                            concurrent_evaluation: *concurrent_evaluation,
+                        }
+                    }
+                    Some(PolicyOrPolicyGroup::MutationPipeline { .. }) => {
+                        return Err(anyhow!(
+                            "policy group '{}' references '{}', which is a mutation pipeline",
+                            referencing_groups[0],
+                            reference
+                        ));
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "policy group '{}' references '{}', which is not defined",
+                            referencing_groups[0],
+                            reference
+                        ));
+                    }
+                }
+            }
+        };
+        resolved_members.insert(member_name.clone(), resolved_member);
+    }
+    Ok(resolved_members)
+}
+
+/// Returns the names of the members of a policy group that contain the '/' character. The
+/// names of the members of nested groups are prefixed by the name of the nested group.
+fn policy_group_members_with_invalid_name(
+    policies: &HashMap<String, PolicyGroupMember>,
+) -> Vec<String> {
+    let mut invalid_names = Vec::new();
+    for (name, member) in policies {
+        if name.contains('/') {
+            invalid_names.push(name.clone());
+        }
+        if let PolicyGroupMember::PolicyGroup { policies, .. } = member {
+            invalid_names.extend(
+                policy_group_members_with_invalid_name(policies)
+                    .into_iter()
+                    .map(|nested_name| format!("{name}/{nested_name}")),
+            );
+        }
+    }
+    invalid_names
+}
+
+/// Ensure each nested policy group defines exactly one rule, see `policy_group_rule`
+fn validate_nested_policy_group_rules(policies: &HashMap<String, PolicyGroupMember>) -> Result<()> {
+    for (name, member) in policies {
+        if let PolicyGroupMember::PolicyGroup { policies, .. } = member {
+            member
+                .settings()
+                .map_err(|e| anyhow!("nested policy group '{}' that has {}", name, e))?;
+            validate_nested_policy_group_rules(policies)?;
+        }
+    }
+    Ok(())
 }
 
-/// `PolicyGroupMember` represents a single policy that is part of a policy group.
+/// `MutationPipelineStep` represents a single policy that is part of a mutation pipeline.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
-pub struct PolicyGroupMember {
+pub struct MutationPipelineStep {
+    /// The name of the step, unique inside of the pipeline
+    pub name: String,
     /// The URL where the policy is located
     pub module: String,
     /// The settings for the policy
@@ -314,7 +998,7 @@ pub struct PolicyGroupMember {
     pub timeout_eval_seconds: Option<u64>,
 }
 
-impl PolicyGroupMember {
+impl MutationPipelineStep {
     pub fn settings(&self) -> Result<PolicyOrPolicyGroupSettings> {
         Ok(PolicyOrPolicyGroupSettings::Policy(
             self.settings.clone().unwrap_or_default(),
@@ -322,7 +1006,102 @@ impl PolicyGroupMember {
     }
 }
 
-/// Describes a policy that can be either an individual policy or a group policy.
+/// `PolicyCanary` describes an alternative version of an individual policy. A stable share of the
+/// incoming requests, defined by `weight`, is evaluated by the canary instead of the main policy.
+///
+/// The canary shares the mode, the mutation permissions, the custom rejection message,
+/// the context aware resources and the timeout of the policy it belongs to.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct PolicyCanary {
+    /// The URL where the canary policy is located
+    pub module: String,
+    /// The settings for the canary policy. When not provided, the settings of the main
+    /// policy are used
+    pub settings: Option<PolicySettings>,
+    /// The percentage of requests, from 0 to 100, that are evaluated by the canary
+    pub weight: u8,
+    /// The request attribute used to decide which variant evaluates a request
+    #[serde(default)]
+    pub routing_key: CanaryRoutingKey,
+}
+
+/// The request attribute hashed to route a request either to the main policy or to its canary.
+/// Requests sharing the same value are always routed to the same variant.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CanaryRoutingKey {
+    /// The UID of the request
+    #[default]
+    RequestUid,
+    /// The namespace of the object. Requests that do not have a namespace fall back to the
+    /// request UID
+    Namespace,
+}
+
+/// `RequestFilter` restricts the requests evaluated by a policy. The requests that do not
+/// satisfy all the conditions are accepted without evaluating the policy.
+///
+/// The conditions have the same meaning of the ones defined inside of a
+/// `ValidatingWebhookConfiguration`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestFilter {
+    /// The rules the request must match. When not provided, the rules declared by the
+    /// metadata of the policy are used if `--enforce-metadata-rules` is set
+    pub rules: Option<Vec<Rule>>,
+    /// The selector the labels of the namespace of the request must match
+    pub namespace_selector: Option<LabelSelector>,
+    /// The selector the labels of the object must match
+    pub object_selector: Option<LabelSelector>,
+    /// The requests accepted without evaluating the policy
+    #[serde(default)]
+    pub exemptions: Exemptions,
+}
+
+/// `Exemptions` describes the requests that are accepted without being evaluated. The values
+/// are regular expressions that must match the whole namespace, username, group or service
+/// account of the request. Service accounts are identified as `<namespace>/<name>`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Exemptions {
+    /// The namespaces of the exempted requests
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+    /// The usernames of the exempted users
+    #[serde(default)]
+    pub usernames: Vec<String>,
+    /// The groups of the exempted users
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// The exempted service accounts
+    #[serde(default)]
+    pub service_accounts: Vec<String>,
+}
+
+/// `EnforcementSchedule` changes the mode of a policy during recurring time windows. Outside
+/// of the windows, the policy runs with its own `policyMode`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct EnforcementSchedule {
+    /// The mode of the policy while one of the windows is open
+    pub policy_mode: PolicyMode,
+    /// The windows during which the policy runs with `policy_mode`
+    pub windows: Vec<EnforcementWindow>,
+}
+
+/// `EnforcementWindow` is a recurring time window of an `EnforcementSchedule`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct EnforcementWindow {
+    /// A cron expression, with 5 fields evaluated in UTC, defining when the window opens
+    pub start: String,
+    /// How long the window stays open
+    pub duration_seconds: u64,
+}
+
+/// Describes a policy that can be either an individual policy, a group policy or a
+/// mutation pipeline.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum PolicyOrPolicyGroup {
@@ -345,24 +1124,132 @@ pub enum PolicyOrPolicyGroup {
         message: Option<String>,
         /// Timeout for the evaluation of the policy
         timeout_eval_seconds: Option<u64>,
+        /// An alternative version of the policy that receives a share of the requests
+        canary: Option<PolicyCanary>,
+        #[serde(default)]
+        /// The fields the policy is not allowed to mutate, expressed as JSON pointer globs
+        mutation_deny_paths: Vec<String>,
+        /// The time windows during which the mode of the policy changes
+        enforcement_schedule: Option<EnforcementSchedule>,
+        /// When set, the context-aware policies accept dry-run requests without evaluating them
+        #[serde(default)]
+        skip_context_aware_resources_on_dry_run: bool,
+        #[serde(flatten)]
+        /// The requests the policy is evaluated against
+        request_filter: RequestFilter,
     },
-    /// A group of policies that are evaluated together using a given expression
+    /// A group of policies that are evaluated together using a given rule: either an
+    /// expression, a minimum number of members accepting the request, or a minimum score
     #[serde(rename_all = "camelCase")]
     PolicyGroup {
         /// The mode of the policy
         #[serde(default)]
         policy_mode: PolicyMode,
+        /// Whether the group is allowed to mutate the request, see
+        /// `EvaluationEnvironment::validate_policy_group`
+        allowed_to_mutate: Option<bool>,
+        /// Whether all the members are evaluated concurrently, before the rule of the group
+        #[serde(default)]
+        concurrent_evaluation: bool,
         /// The policies that make up for this group
         /// Key is a unique identifier
         policies: HashMap<String, PolicyGroupMember>,
         /// The expression that is used to evaluate the group of policies
-        expression: String,
+        expression: Option<String>,
+        /// The minimum number of members that must accept the request
+        min_passing: Option<u64>,
+        /// The minimum sum of the weights of the members that must accept the request
+        min_score: Option<u64>,
         /// The message that is returned when the group of policies evaluates to false
         message: String,
+        /// The time windows during which the mode of the policy changes
+        enforcement_schedule: Option<EnforcementSchedule>,
+        /// When set, the context-aware policies accept dry-run requests without evaluating them
+        #[serde(default)]
+        skip_context_aware_resources_on_dry_run: bool,
+        /// The requests the group is evaluated against
+        #[serde(flatten)]
+        request_filter: RequestFilter,
+    },
+    /// An ordered list of policies. Each policy evaluates the object as mutated by the
+    /// previous ones, the patches of all the steps are returned as a single patch
+    #[serde(rename_all = "camelCase")]
+    MutationPipeline {
+        /// The mode of the pipeline
+        #[serde(default)]
+        policy_mode: PolicyMode,
+        /// Whether the pipeline is allowed to mutate the request
+        allowed_to_mutate: Option<bool>,
+        /// The message that is returned when one of the steps rejects the request
+        message: Option<String>,
+        /// The policies making up the pipeline, in evaluation order
+        steps: Vec<MutationPipelineStep>,
+        /// The time windows during which the mode of the policy changes
+        enforcement_schedule: Option<EnforcementSchedule>,
+        /// When set, the context-aware policies accept dry-run requests without evaluating them
+        #[serde(default)]
+        skip_context_aware_resources_on_dry_run: bool,
+        /// The requests the pipeline is evaluated against
+        #[serde(flatten)]
+        request_filter: RequestFilter,
     },
 }
 
 impl PolicyOrPolicyGroup {
+    /// The message replacing the one of the rejections. Policy groups build their own
+    /// message, defined by the `message` of the group.
+    pub fn custom_rejection_message(&self) -> Option<&str> {
+        match self {
+            PolicyOrPolicyGroup::Policy { message, .. }
+            | PolicyOrPolicyGroup::MutationPipeline { message, .. } => message.as_deref(),
+            PolicyOrPolicyGroup::PolicyGroup { .. } => None,
+        }
+    }
+
+    pub fn enforcement_schedule(&self) -> Option<&EnforcementSchedule> {
+        match self {
+            PolicyOrPolicyGroup::Policy {
+                enforcement_schedule,
+                ..
+            }
+            | PolicyOrPolicyGroup::PolicyGroup {
+                enforcement_schedule,
+                ..
+            }
+            | PolicyOrPolicyGroup::MutationPipeline {
+                enforcement_schedule,
+                ..
+            } => enforcement_schedule.as_ref(),
+        }
+    }
+
+    /// Whether the context-aware policies accept the dry-run requests without evaluating them.
+    /// For policy groups and mutation pipelines, this applies to all the members.
+    pub fn skip_context_aware_resources_on_dry_run(&self) -> bool {
+        match self {
+            PolicyOrPolicyGroup::Policy {
+                skip_context_aware_resources_on_dry_run,
+                ..
+            }
+            | PolicyOrPolicyGroup::PolicyGroup {
+                skip_context_aware_resources_on_dry_run,
+                ..
+            }
+            | PolicyOrPolicyGroup::MutationPipeline {
+                skip_context_aware_resources_on_dry_run,
+                ..
+            } => *skip_context_aware_resources_on_dry_run,
+        }
+    }
+
+    pub fn request_filter(&self) -> &RequestFilter {
+        match self {
+            PolicyOrPolicyGroup::Policy { request_filter, .. }
+            | PolicyOrPolicyGroup::PolicyGroup { request_filter, .. }
+            | PolicyOrPolicyGroup::MutationPipeline { request_filter, .. } => request_filter,
+        }
+    }
+
     pub fn settings(&self) -> Result<PolicyOrPolicyGroupSettings> {
         match self {
             PolicyOrPolicyGroup::Policy { settings, .. } => Ok(
@@ -370,14 +1257,23 @@ impl PolicyOrPolicyGroup {
             ),
             PolicyOrPolicyGroup::PolicyGroup {
                 expression,
+                min_passing,
+                min_score,
                 message,
                 policies,
+                concurrent_evaluation,
                 ..
             } => Ok(PolicyOrPolicyGroupSettings::PolicyGroup {
-                expression: expression.clone(),
+                rule: policy_group_rule(expression, *min_passing, *min_score, policies)?,
                 message: message.clone(),
                 policies: policies.keys().cloned().collect(),
+                concurrent_evaluation: *concurrent_evaluation,
             }),
+            PolicyOrPolicyGroup::MutationPipeline { steps, .. } => {
+                Ok(PolicyOrPolicyGroupSettings::MutationPipeline {
+                    steps: steps.iter().map(|step| step.name.clone()).collect(),
+                })
+            }
         }
     }
 }
@@ -458,6 +1354,7 @@ example:
           kind: Namespace
         - apiVersion: v1
           kind: Pod
+    skipContextAwareResourcesOnDryRun: true
 group_policy:
     policyMode: monitor
     expression: "true"
@@ -469,6 +1366,14 @@ group_policy:
         policy2:
             module: ghcr.io/kubewarden/policies/policy2:0.1.0
             settings: {}
+pipeline:
+    allowedToMutate: true
+    steps:
+        - name: step1
+          module: ghcr.io/kubewarden/policies/policy1:0.1.0
+        - name: step2
+          module: ghcr.io/kubewarden/policies/policy2:0.1.0
+          timeoutEvalSeconds: 2
 "#;
 
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -497,36 +1402,77 @@ group_policy:
                     ]),
                     message: Some("my custom error message".to_owned()),
                     timeout_eval_seconds: None,
+                    mutation_deny_paths: Vec::new(),
+                    enforcement_schedule: None,
+                    skip_context_aware_resources_on_dry_run: true,
+                    request_filter: RequestFilter::default(),
+                    canary: None,
                 },
             ),
             (
                 "group_policy".to_owned(),
                 PolicyOrPolicyGroup::PolicyGroup {
                     policy_mode: PolicyMode::Monitor,
-                    expression: "true".to_owned(),
+                    allowed_to_mutate: None,
+                    concurrent_evaluation: false,
+                    enforcement_schedule: None,
+                    skip_context_aware_resources_on_dry_run: false,
+                    request_filter: RequestFilter::default(),
+                    expression: Some("true".to_owned()),
+                    min_passing: None,
+                    min_score: None,
                     message: "group policy message".to_owned(),
                     policies: HashMap::from([
                         (
                             "policy1".to_owned(),
-                            PolicyGroupMember {
+                            PolicyGroupMember::Policy {
                                 module: "ghcr.io/kubewarden/policies/policy1:0.1.0".to_owned(),
                                 settings: Some(PolicySettings::default()),
                                 context_aware_resources: BTreeSet::new(),
                                 timeout_eval_seconds: None,
+                                weight: None,
                             },
                         ),
                         (
                             "policy2".to_string(),
-                            PolicyGroupMember {
+                            PolicyGroupMember::Policy {
                                 module: "ghcr.io/kubewarden/policies/policy2:0.1.0".to_owned(),
                                 settings: Some(PolicySettings::default()),
                                 context_aware_resources: BTreeSet::new(),
                                 timeout_eval_seconds: None,
+                                weight: None,
                             },
                         ),
                     ]),
                 },
             ),
+            (
+                "pipeline".to_owned(),
+                PolicyOrPolicyGroup::MutationPipeline {
+                    policy_mode: PolicyMode::Protect,
+                    enforcement_schedule: None,
+                    skip_context_aware_resources_on_dry_run: false,
+                    request_filter: RequestFilter::default(),
+                    allowed_to_mutate: Some(true),
+                    message: None,
+                    steps: vec![
+                        MutationPipelineStep {
+                            name: "step1".to_owned(),
+                            module: "ghcr.io/kubewarden/policies/policy1:0.1.0".to_owned(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                        },
+                        MutationPipelineStep {
+                            name: "step2".to_owned(),
+                            module: "ghcr.io/kubewarden/policies/policy2:0.1.0".to_owned(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: Some(2),
+                        },
+                    ],
+                },
+            ),
         ]);
 
         assert_eq!(expected_policies, policies);
@@ -666,11 +1612,814 @@ group_policy:
 "#,
         false
     )]
-    fn policy_validation(#[case] policies_yaml: &str, #[case] is_valid: bool) {
-        let policies: HashMap<String, PolicyOrPolicyGroup> =
-            serde_yaml::from_str(policies_yaml).unwrap();
-
-        let validation_result = validate_policies(&policies);
-        assert_eq!(is_valid, validation_result.is_ok());
+    #[case::nested_policy_group(
+        r#"
+---
+group_policy:
+  expression: "policy1() && image_provenance()"
+  message: "group policy message"
+  policies:
+    policy1:
+      module: file:///tmp/namespace-validate-policy.wasm
+    image_provenance:
+      expression: "signed() || trusted_registry()"
+      message: "the image provenance cannot be verified"
+      policies:
+        signed:
+          module: file:///tmp/verify-image-signatures.wasm
+        trusted_registry:
+          module: file:///tmp/trusted-repos.wasm
+"#,
+        true
+    )]
+    #[case::nested_policy_group_member_with_invalid_name(
+        r#"
+---
+group_policy:
+  expression: "image_provenance()"
+  message: "group policy message"
+  policies:
+    image_provenance:
+      expression: "true"
+      message: "the image provenance cannot be verified"
+      policies:
+        signed/a:
+          module: file:///tmp/verify-image-signatures.wasm
+"#,
+        false
+    )]
+    #[case::min_passing(
+        r#"
+---
+attestations:
+  minPassing: 2
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+    sbom:
+      module: file:///tmp/verify-sbom.wasm
+    signature:
+      module: file:///tmp/verify-image-signatures.wasm
+"#,
+        true
+    )]
+    #[case::min_score(
+        r#"
+---
+attestations:
+  minScore: 3
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+      weight: 2
+    sbom:
+      module: file:///tmp/verify-sbom.wasm
+"#,
+        true
+    )]
+    #[case::policy_group_without_rule(
+        r#"
+---
+attestations:
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+"#,
+        false
+    )]
+    #[case::policy_group_with_multiple_rules(
+        r#"
+---
+attestations:
+  expression: "slsa()"
+  minPassing: 1
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+"#,
+        false
+    )]
+    #[case::weight_without_min_score(
+        r#"
+---
+attestations:
+  minPassing: 1
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+      weight: 2
+"#,
+        false
+    )]
+    #[case::min_passing_greater_than_members(
+        r#"
+---
+attestations:
+  minPassing: 2
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+"#,
+        false
+    )]
+    #[case::min_passing_zero(
+        r#"
+---
+attestations:
+  minPassing: 0
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+"#,
+        false
+    )]
+    #[case::min_score_zero(
+        r#"
+---
+attestations:
+  minScore: 0
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+"#,
+        false
+    )]
+    #[case::min_score_reaching_the_sum_of_the_weights(
+        r#"
+---
+attestations:
+  minScore: 3
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+      weight: 2
+    sbom:
+      module: file:///tmp/verify-sbom.wasm
+"#,
+        true
+    )]
+    #[case::min_score_greater_than_the_sum_of_the_weights(
+        r#"
+---
+attestations:
+  minScore: 4
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+      weight: 2
+    sbom:
+      module: file:///tmp/verify-sbom.wasm
+"#,
+        false
+    )]
+    #[case::min_score_with_weights_overflowing(
+        r#"
+---
+attestations:
+  minScore: 4
+  message: "not enough attestations"
+  policies:
+    slsa:
+      module: file:///tmp/verify-slsa.wasm
+      weight: 18446744073709551615
+    sbom:
+      module: file:///tmp/verify-sbom.wasm
+"#,
+        false
+    )]
+    #[case::nested_policy_group_without_rule(
+        r#"
+---
+group_policy:
+  expression: "attestations()"
+  message: "group policy message"
+  policies:
+    attestations:
+      message: "not enough attestations"
+      policies:
+        slsa:
+          module: file:///tmp/verify-slsa.wasm
+"#,
+        false
+    )]
+    #[case::concurrent_evaluation(
+        r#"
+---
+signatures:
+  concurrentEvaluation: true
+  expression: "sigstore_pgp() || sigstore_gh_action()"
+  message: "the image signatures cannot be verified"
+  policies:
+    sigstore_pgp:
+      module: file:///tmp/verify-image-signatures.wasm
+    sigstore_gh_action:
+      module: file:///tmp/verify-image-signatures.wasm
+"#,
+        true
+    )]
+    #[case::nested_concurrent_evaluation(
+        r#"
+---
+signatures:
+  expression: "happy() && nested()"
+  message: "the image signatures cannot be verified"
+  policies:
+    happy:
+      module: file:///tmp/happy.wasm
+    nested:
+      concurrentEvaluation: true
+      expression: "sigstore_pgp() || sigstore_gh_action()"
+      message: "the nested group rejected the request"
+      policies:
+        sigstore_pgp:
+          module: file:///tmp/verify-image-signatures.wasm
+        sigstore_gh_action:
+          module: file:///tmp/verify-image-signatures.wasm
+"#,
+        true
+    )]
+    #[case::concurrent_evaluation_allowed_to_mutate(
+        r#"
+---
+signatures:
+  concurrentEvaluation: true
+  allowedToMutate: true
+  expression: "sigstore_pgp() || sigstore_gh_action()"
+  message: "the image signatures cannot be verified"
+  policies:
+    sigstore_pgp:
+      module: file:///tmp/verify-image-signatures.wasm
+    sigstore_gh_action:
+      module: file:///tmp/verify-image-signatures.wasm
+"#,
+        false
+    )]
+    #[case::allowed_to_mutate_disjunction_after_conjunction(
+        r#"
+---
+labels:
+  allowedToMutate: true
+  expression: "(add_team_label() && has_owner()) || add_default_labels()"
+  message: "the labels cannot be set"
+  policies:
+    add_team_label:
+      module: file:///tmp/add-labels.wasm
+    has_owner:
+      module: file:///tmp/has-owner.wasm
+    add_default_labels:
+      module: file:///tmp/add-labels.wasm
+"#,
+        false
+    )]
+    #[case::allowed_to_mutate_negation(
+        r#"
+---
+labels:
+  allowedToMutate: true
+  expression: "!add_team_label() || add_default_labels()"
+  message: "the labels cannot be set"
+  policies:
+    add_team_label:
+      module: file:///tmp/add-labels.wasm
+    add_default_labels:
+      module: file:///tmp/add-labels.wasm
+"#,
+        false
+    )]
+    #[case::allowed_to_mutate_conjunction_of_disjunction(
+        r#"
+---
+labels:
+  allowedToMutate: true
+  expression: "has_owner() && (add_team_label() || add_default_labels())"
+  message: "the labels cannot be set"
+  policies:
+    add_team_label:
+      module: file:///tmp/add-labels.wasm
+    has_owner:
+      module: file:///tmp/has-owner.wasm
+    add_default_labels:
+      module: file:///tmp/add-labels.wasm
+"#,
+        true
+    )]
+    #[case::reference_to_policy(
+        r#"
+---
+psp-capabilities:
+  module: file:///tmp/capabilities-psp.wasm
+  settings:
+    allowed_capabilities: ["CHOWN"]
+group_policy:
+  expression: "capabilities()"
+  message: "group policy message"
+  policies:
+    capabilities:
+      ref: psp-capabilities
+"#,
+        true
+    )]
+    #[case::reference_to_policy_group(
+        r#"
+---
+image_provenance:
+  expression: "signed()"
+  message: "the image provenance cannot be verified"
+  policies:
+    signed:
+      module: file:///tmp/verify-image-signatures.wasm
+group_policy:
+  expression: "image_provenance()"
+  message: "group policy message"
+  policies:
+    image_provenance:
+      ref: image_provenance
+"#,
+        true
+    )]
+    #[case::reference_to_unknown_policy(
+        r#"
+---
+group_policy:
+  expression: "capabilities()"
+  message: "group policy message"
+  policies:
+    capabilities:
+      ref: psp-capabilities
+"#,
+        false
+    )]
+    #[case::reference_to_mutation_pipeline(
+        r#"
+---
+pipeline:
+  steps:
+    - name: add_labels
+      module: file:///tmp/add-labels.wasm
+group_policy:
+  expression: "pipeline()"
+  message: "group policy message"
+  policies:
+    pipeline:
+      ref: pipeline
+"#,
+        false
+    )]
+    #[case::self_reference(
+        r#"
+---
+group_policy:
+  expression: "itself()"
+  message: "group policy message"
+  policies:
+    itself:
+      ref: group_policy
+"#,
+        false
+    )]
+    #[case::cyclic_references(
+        r#"
+---
+group_a:
+  expression: "b()"
+  message: "group a message"
+  policies:
+    b:
+      ref: group_b
+group_b:
+  expression: "nested()"
+  message: "group b message"
+  policies:
+    nested:
+      expression: "a()"
+      message: "nested group message"
+      policies:
+        a:
+          ref: group_a
+"#,
+        false
+    )]
+    #[case::canary_with_valid_weight(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  canary:
+    module: file:///tmp/namespace-validate-policy-v2.wasm
+    weight: 10
+    routingKey: namespace
+"#,
+        true
+    )]
+    #[case::canary_with_invalid_weight(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  canary:
+    module: file:///tmp/namespace-validate-policy-v2.wasm
+    weight: 110
+"#,
+        false
+    )]
+    #[case::mutation_deny_paths(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  allowedToMutate: true
+  mutationDenyPaths:
+    - /metadata/ownerReferences
+    - /spec/containers/*/image
+"#,
+        true
+    )]
+    #[case::invalid_mutation_deny_paths(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  allowedToMutate: true
+  mutationDenyPaths:
+    - /metadata/[
+"#,
+        false
+    )]
+    #[case::request_filter(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  rules:
+    - apiGroups: [""]
+      apiVersions: ["v1"]
+      resources: ["pods"]
+      operations: ["CREATE", "UPDATE"]
+  namespaceSelector:
+    matchExpressions:
+      - key: environment
+        operator: In
+        values: ["production"]
+  objectSelector:
+    matchLabels:
+      app: web
+"#,
+        true
+    )]
+    #[case::exemptions(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  exemptions:
+    namespaces: ["kube-system", "ci-.*"]
+    groups: ["system:masters"]
+    serviceAccounts: ["ci/deployer"]
+"#,
+        true
+    )]
+    #[case::invalid_exemptions(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  exemptions:
+    usernames: ["system:("]
+"#,
+        false
+    )]
+    #[case::enforcement_schedule(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  policyMode: protect
+  enforcementSchedule:
+    policyMode: monitor
+    windows:
+      - start: "0 18 * * 5"
+        durationSeconds: 216000
+"#,
+        true
+    )]
+    #[case::invalid_enforcement_schedule(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  enforcementSchedule:
+    policyMode: monitor
+    windows:
+      - start: "every friday"
+        durationSeconds: 3600
+"#,
+        false
+    )]
+    #[case::message_template(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  message: "{{ .kind }} {{ .name }} rejected: {{ .policyMessage }}"
+"#,
+        true
+    )]
+    #[case::invalid_message_template(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  message: "{{ .owner }} rejected"
+"#,
+        false
+    )]
+    #[case::request_filter_with_invalid_selector(
+        r#"
+---
+example:
+  module: file:///tmp/namespace-validate-policy.wasm
+  objectSelector:
+    matchExpressions:
+      - key: app
+        operator: Exists
+        values: ["web"]
+"#,
+        false
+    )]
+    #[case::mutation_pipeline(
+        r#"
+---
+pipeline:
+  allowedToMutate: true
+  steps:
+    - name: add_labels
+      module: file:///tmp/add-labels.wasm
+    - name: set_resources
+      module: file:///tmp/set-resources.wasm
+"#,
+        true
+    )]
+    #[case::mutation_pipeline_with_duplicated_steps(
+        r#"
+---
+pipeline:
+  steps:
+    - name: add_labels
+      module: file:///tmp/add-labels.wasm
+    - name: add_labels
+      module: file:///tmp/set-resources.wasm
+"#,
+        false
+    )]
+    #[case::mutation_pipeline_step_with_invalid_name(
+        r#"
+---
+pipeline:
+  steps:
+    - name: add/labels
+      module: file:///tmp/add-labels.wasm
+"#,
+        false
+    )]
+    fn policy_validation(#[case] policies_yaml: &str, #[case] is_valid: bool) {
+        let policies: HashMap<String, PolicyOrPolicyGroup> =
+            serde_yaml::from_str(policies_yaml).unwrap();
+
+        let validation_result = validate_policies(&policies);
+        assert_eq!(is_valid, validation_result.is_ok());
+    }
+
+    #[test]
+    fn resolve_references() {
+        let policies_yaml = r#"
+---
+psp-capabilities:
+  module: file:///tmp/capabilities-psp.wasm
+  policyMode: monitor
+  settings:
+    allowed_capabilities: ["CHOWN"]
+  timeoutEvalSeconds: 2
+image_provenance:
+  concurrentEvaluation: true
+  expression: "signed()"
+  message: "the image provenance cannot be verified"
+  policies:
+    signed:
+      module: file:///tmp/verify-image-signatures.wasm
+group_policy:
+  expression: "capabilities() && image_provenance()"
+  message: "group policy message"
+  policies:
+    capabilities:
+      ref: psp-capabilities
+    image_provenance:
+      ref: image_provenance
+"#;
+        let policies: HashMap<String, PolicyOrPolicyGroup> =
+            serde_yaml::from_str(policies_yaml).unwrap();
+
+        let resolved_policies = validate_policies(&policies).unwrap();
+
+        let PolicyOrPolicyGroup::PolicyGroup {
+            policies: members, ..
+        } = &resolved_policies["group_policy"]
+        else {
+            panic!("group_policy is not a policy group");
+        };
+        assert_eq!(
+            PolicyGroupMember::Policy {
+                module: "file:///tmp/capabilities-psp.wasm".to_owned(),
+                settings: Some(
+                    serde_json::from_value(json!({"allowed_capabilities": ["CHOWN"]})).unwrap()
+                ),
+                context_aware_resources: BTreeSet::new(),
+                timeout_eval_seconds: Some(2),
+                weight: None,
+            },
+            members["capabilities"]
+        );
+        assert!(matches!(
+            &members["image_provenance"],
+            PolicyGroupMember::PolicyGroup { policies, expression, concurrent_evaluation: true, .. }
+                if expression.as_deref() == Some("signed()") && policies.contains_key("signed")
+        ));
+        // the referenced policies are still defined on their own
+        assert_eq!(3, resolved_policies.len());
+        assert_eq!(
+            policies["psp-capabilities"],
+            resolved_policies["psp-capabilities"]
+        );
+    }
+
+    #[test]
+    fn resolve_references_of_nested_groups() {
+        let policies_yaml = r#"
+---
+psp-capabilities:
+  module: file:///tmp/capabilities-psp.wasm
+  settings:
+    allowed_capabilities: ["CHOWN"]
+image_provenance:
+  expression: "signed() && capabilities()"
+  message: "the image provenance cannot be verified"
+  policies:
+    signed:
+      module: file:///tmp/verify-image-signatures.wasm
+    capabilities:
+      ref: psp-capabilities
+group_policy:
+  expression: "nested()"
+  message: "group policy message"
+  policies:
+    nested:
+      expression: "image_provenance()"
+      message: "nested group message"
+      policies:
+        image_provenance:
+          ref: image_provenance
+"#;
+        let policies: HashMap<String, PolicyOrPolicyGroup> =
+            serde_yaml::from_str(policies_yaml).unwrap();
+
+        let resolved_policies = resolve_policy_group_references(&policies).unwrap();
+
+        // the reference made by a nested group, to a group making a reference, is resolved
+        // down to the policies
+        let PolicyOrPolicyGroup::PolicyGroup {
+            policies: members, ..
+        } = &resolved_policies["group_policy"]
+        else {
+            panic!("group_policy is not a policy group");
+        };
+        let PolicyGroupMember::PolicyGroup {
+            policies: nested_members,
+            ..
+        } = &members["nested"]
+        else {
+            panic!("nested is not a policy group");
+        };
+        let PolicyGroupMember::PolicyGroup {
+            policies: referenced_members,
+            expression,
+            ..
+        } = &nested_members["image_provenance"]
+        else {
+            panic!("image_provenance is not a policy group");
+        };
+        assert_eq!(Some("signed() && capabilities()"), expression.as_deref());
+        assert!(matches!(
+            &referenced_members["capabilities"],
+            PolicyGroupMember::Policy { module, .. } if module == "file:///tmp/capabilities-psp.wasm"
+        ));
+    }
+
+    #[rstest]
+    #[case::missing_policy(
+        r#"
+---
+group_policy:
+  expression: "capabilities()"
+  message: "group policy message"
+  policies:
+    capabilities:
+      ref: psp-capabilities
+"#,
+        "policy group 'group_policy' references 'psp-capabilities', which is not defined"
+    )]
+    #[case::missing_policy_of_nested_group(
+        r#"
+---
+group_policy:
+  expression: "nested()"
+  message: "group policy message"
+  policies:
+    nested:
+      expression: "capabilities()"
+      message: "nested group message"
+      policies:
+        capabilities:
+          ref: psp-capabilities
+"#,
+        "policy group 'group_policy' references 'psp-capabilities', which is not defined"
+    )]
+    #[case::cycle_between_groups(
+        r#"
+---
+group_a:
+  expression: "b()"
+  message: "group a message"
+  policies:
+    b:
+      ref: group_b
+group_b:
+  expression: "a()"
+  message: "group b message"
+  policies:
+    a:
+      ref: group_a
+"#,
+        "has a cyclic reference"
+    )]
+    #[case::cycle_through_nested_group(
+        r#"
+---
+group_a:
+  expression: "nested()"
+  message: "group a message"
+  policies:
+    nested:
+      expression: "b()"
+      message: "nested group message"
+      policies:
+        b:
+          ref: group_b
+group_b:
+  expression: "a()"
+  message: "group b message"
+  policies:
+    a:
+      ref: group_a
+"#,
+        "has a cyclic reference"
+    )]
+    fn resolve_invalid_references(#[case] policies_yaml: &str, #[case] expected_error: &str) {
+        let policies: HashMap<String, PolicyOrPolicyGroup> =
+            serde_yaml::from_str(policies_yaml).unwrap();
+
+        let error = resolve_policy_group_references(&policies)
+            .expect_err("the references should not be resolved")
+            .to_string();
+
+        assert!(error.contains(expected_error), "{error}");
+    }
+
+    #[test]
+    fn policy_group_member_cannot_be_both_a_policy_and_a_group() {
+        let policies_yaml = r#"
+---
+group_policy:
+  expression: "policy1()"
+  message: "group policy message"
+  policies:
+    policy1:
+      module: file:///tmp/namespace-validate-policy.wasm
+      expression: "true"
+      message: "nested group message"
+      policies: {}
+"#;
+
+        let policies: Result<HashMap<String, PolicyOrPolicyGroup>, _> =
+            serde_yaml::from_str(policies_yaml);
+        assert!(policies.is_err());
     }
 }
@@ -0,0 +1,402 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{SecondsFormat, Utc};
+use policy_evaluator::{admission_response::AdmissionResponse, policy_evaluator::ValidateRequest};
+use serde::Serialize;
+use tracing::warn;
+
+static DECISION_LOG: OnceLock<DecisionLog> = OnceLock::new();
+
+/// Configuration of the decision log sink
+#[derive(Clone, Debug)]
+pub struct DecisionLogConfig {
+    /// Path of the file where decisions are written
+    pub path: PathBuf,
+    /// Rotate the file once it grows beyond this size
+    pub max_size_bytes: u64,
+    /// Rotate the file once it has been open for longer than this
+    pub max_age: Option<Duration>,
+    /// Number of rotated files to keep around
+    pub max_files: usize,
+    /// Whether the evaluated request should be part of each record
+    pub include_payload: bool,
+    /// Whether the dry-run requests should be left out of the log
+    pub exclude_dry_run: bool,
+}
+
+/// Initialize the global decision log. All the evaluations performed after this call
+/// are written to the configured file.
+pub fn setup_decision_log(config: DecisionLogConfig) -> Result<()> {
+    let decision_log = DecisionLog::new(config)?;
+    DECISION_LOG
+        .set(decision_log)
+        .map_err(|_| anyhow!("decision log has already been initialized"))
+}
+
+/// Returns true when the evaluated requests have to be added to the records
+pub(crate) fn include_payload() -> bool {
+    DECISION_LOG
+        .get()
+        .is_some_and(|decision_log| decision_log.include_payload)
+}
+
+/// Append the given record to the decision log. This is a no-op when the decision log
+/// has not been initialized.
+pub(crate) fn record_decision(record: &DecisionRecord) {
+    if let Some(decision_log) = DECISION_LOG.get()
+        && let Err(error) = decision_log.write(record)
+    {
+        warn!(?error, "cannot write decision log record");
+    }
+}
+
+/// A single policy evaluation, as written to the decision log
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct DecisionRecord {
+    /// RFC 3339 timestamp of the evaluation
+    pub(crate) time: String,
+    pub(crate) policy_id: String,
+    pub(crate) policy_mode: String,
+    pub(crate) policy_variant: String,
+    pub(crate) request_origin: String,
+    pub(crate) request_uid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) operation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) user: Option<String>,
+    /// The decision returned to the client
+    pub(crate) allowed: bool,
+    /// The decision taken by the policy, before being processed by `AdmissionResponseHandler`
+    pub(crate) raw_allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) code: Option<u16>,
+    pub(crate) mutated: bool,
+    pub(crate) latency_ms: f64,
+    /// The policy has been forced into monitor mode by the break-glass override
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) break_glass: bool,
+    /// The request has been made with `dryRun: true`, its changes are not persisted
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) payload: Option<serde_json::Value>,
+}
+
+impl DecisionRecord {
+    /// Create a record holding the details of the given request and response. The
+    /// details about the policy have to be filled by the caller.
+    pub(crate) fn new(
+        validate_request: &ValidateRequest,
+        admission_response: &AdmissionResponse,
+    ) -> Self {
+        let mut record = DecisionRecord {
+            time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            request_uid: validate_request.uid().to_owned(),
+            allowed: admission_response.allowed,
+            message: admission_response
+                .status
+                .as_ref()
+                .and_then(|status| status.message.clone()),
+            code: admission_response
+                .status
+                .as_ref()
+                .and_then(|status| status.code),
+            mutated: admission_response.patch.is_some(),
+            ..Default::default()
+        };
+
+        if let ValidateRequest::AdmissionRequest(adm_req) = validate_request {
+            record.kind = Some(adm_req.kind.kind.clone());
+            record.namespace = adm_req.namespace.clone();
+            record.name = adm_req.name.clone();
+            record.operation = Some(adm_req.operation.clone());
+            record.user = adm_req.user_info.username.clone();
+            record.dry_run = adm_req.dry_run.unwrap_or(false);
+        }
+
+        if include_payload() {
+            record.payload = match validate_request {
+                ValidateRequest::AdmissionRequest(adm_req) => serde_json::to_value(adm_req).ok(),
+                ValidateRequest::Raw(raw_request) => Some(raw_request.clone()),
+            };
+        }
+
+        record
+    }
+}
+
+struct DecisionLog {
+    include_payload: bool,
+    exclude_dry_run: bool,
+    writer: Mutex<RotatingFile>,
+}
+
+impl DecisionLog {
+    fn new(config: DecisionLogConfig) -> Result<Self> {
+        Ok(DecisionLog {
+            include_payload: config.include_payload,
+            exclude_dry_run: config.exclude_dry_run,
+            writer: Mutex::new(RotatingFile::open(
+                config.path,
+                config.max_size_bytes,
+                config.max_age,
+                config.max_files,
+            )?),
+        })
+    }
+
+    fn write(&self, record: &DecisionRecord) -> Result<()> {
+        if self.exclude_dry_run && record.dry_run {
+            return Ok(());
+        }
+
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow!("decision log lock is poisoned"))?;
+        writer.write_line(&line)
+    }
+}
+
+/// A file that is rotated once it exceeds the given size or age.
+///
+/// Rotated files are renamed by adding a numeric suffix to the original path, the lower
+/// the suffix the more recent the file: `decisions.log.1`, `decisions.log.2`, ...
+struct RotatingFile {
+    path: PathBuf,
+    max_size_bytes: u64,
+    max_age: Option<Duration>,
+    max_files: usize,
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl RotatingFile {
+    fn open(
+        path: PathBuf,
+        max_size_bytes: u64,
+        max_age: Option<Duration>,
+        max_files: usize,
+    ) -> Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)
+                .map_err(|e| anyhow!("cannot create decision log directory: {}", e))?;
+        }
+        let (file, size) = open_append(&path)?;
+
+        Ok(RotatingFile {
+            path,
+            max_size_bytes,
+            max_age,
+            max_files,
+            file,
+            size,
+            opened_at: SystemTime::now(),
+        })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.should_rotate(line.len() as u64) {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn should_rotate(&self, incoming_bytes: u64) -> bool {
+        // never rotate an empty file, otherwise a record bigger than the size
+        // limit would cause a rotation on every write
+        if self.size == 0 {
+            return false;
+        }
+
+        let too_big = self.size + incoming_bytes > self.max_size_bytes;
+        let too_old = self.max_age.is_some_and(|max_age| {
+            self.opened_at
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= max_age)
+        });
+
+        too_big || too_old
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = rotated_path(&self.path, self.max_files);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        let (file, size) = open_append(&self.path)?;
+        self.file = file;
+        self.size = size;
+        self.opened_at = SystemTime::now();
+
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<(File, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow!("cannot open decision log file {}: {}", path.display(), e))?;
+    let size = file.metadata()?.len();
+
+    Ok((file, size))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    PathBuf::from(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn read_lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn record_is_written_as_json_line() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("decisions.log");
+        let decision_log = DecisionLog::new(DecisionLogConfig {
+            path: path.clone(),
+            max_size_bytes: 1024 * 1024,
+            max_age: None,
+            max_files: 1,
+            include_payload: false,
+            exclude_dry_run: false,
+        })
+        .unwrap();
+
+        let record = DecisionRecord {
+            policy_id: "privileged-pods".to_string(),
+            request_uid: "uid".to_string(),
+            allowed: true,
+            raw_allowed: false,
+            ..Default::default()
+        };
+        decision_log.write(&record).unwrap();
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 1);
+        let written: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(written["policy_id"], "privileged-pods");
+        assert_eq!(written["allowed"], true);
+        assert_eq!(written["raw_allowed"], false);
+        assert!(written.get("payload").is_none());
+    }
+
+    #[test]
+    fn dry_run_records_are_excluded() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("decisions.log");
+        let decision_log = DecisionLog::new(DecisionLogConfig {
+            path: path.clone(),
+            max_size_bytes: 1024 * 1024,
+            max_age: None,
+            max_files: 1,
+            include_payload: false,
+            exclude_dry_run: true,
+        })
+        .unwrap();
+
+        for (request_uid, dry_run) in [("dry-run", true), ("uid", false)] {
+            let record = DecisionRecord {
+                request_uid: request_uid.to_string(),
+                dry_run,
+                ..Default::default()
+            };
+            decision_log.write(&record).unwrap();
+        }
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 1);
+        let written: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(written["request_uid"], "uid");
+        assert!(written.get("dry_run").is_none());
+    }
+
+    #[test]
+    fn rotate_when_size_is_exceeded() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("decisions.log");
+        let mut rotating_file = RotatingFile::open(path.clone(), 10, None, 2).unwrap();
+
+        for line in [
+            "first-line\n",
+            "second-line\n",
+            "third-line\n",
+            "fourth-line\n",
+        ] {
+            rotating_file.write_line(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(read_lines(&path), vec!["fourth-line"]);
+        assert_eq!(read_lines(&rotated_path(&path, 1)), vec!["third-line"]);
+        assert_eq!(read_lines(&rotated_path(&path, 2)), vec!["second-line"]);
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn rotate_when_age_is_exceeded() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("decisions.log");
+        let mut rotating_file =
+            RotatingFile::open(path.clone(), 1024, Some(Duration::from_secs(60)), 1).unwrap();
+
+        rotating_file.write_line(b"first-line\n").unwrap();
+        rotating_file.opened_at = SystemTime::now() - Duration::from_secs(120);
+        rotating_file.write_line(b"second-line\n").unwrap();
+
+        assert_eq!(read_lines(&path), vec!["second-line"]);
+        assert_eq!(read_lines(&rotated_path(&path, 1)), vec!["first-line"]);
+    }
+}
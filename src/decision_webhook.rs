@@ -0,0 +1,383 @@
+use std::{
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    time::{self, Instant},
+};
+use tracing::{debug, warn};
+
+use crate::{decision_log::DecisionRecord, metrics};
+
+static DECISION_WEBHOOK: OnceLock<DecisionWebhook> = OnceLock::new();
+
+/// Delay before the first delivery retry, doubled on each attempt
+const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(200);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configuration of the webhook receiving the decision events
+#[derive(Clone, Debug)]
+pub struct DecisionWebhookConfig {
+    /// The endpoint receiving the events, as a JSON array
+    pub url: String,
+    /// Maximum number of events sent with a single request
+    pub batch_size: usize,
+    /// Maximum time an event waits for its batch to be filled
+    pub flush_interval: Duration,
+    /// Number of events that can be buffered while waiting for delivery
+    pub buffer_size: usize,
+    /// Number of times the delivery of a batch is retried before dropping it
+    pub max_retries: u32,
+    /// Notify only the rejected requests and the violations of policies in monitor mode
+    pub only_violations: bool,
+    /// Do not notify the dry-run requests
+    pub exclude_dry_run: bool,
+}
+
+/// Initialize the global decision webhook and start the task delivering the events.
+///
+/// This must be invoked from within a tokio runtime.
+pub fn setup_decision_webhook(config: DecisionWebhookConfig) -> Result<()> {
+    let (decision_webhook, worker) = DecisionWebhook::new(config)?;
+    DECISION_WEBHOOK
+        .set(decision_webhook)
+        .map_err(|_| anyhow!("decision webhook has already been initialized"))?;
+    tokio::spawn(worker.run());
+
+    Ok(())
+}
+
+/// Queue the given decision for delivery. This is a no-op when the decision webhook
+/// has not been initialized.
+///
+/// The event is dropped when the buffer is full, the evaluation of the request is
+/// never slowed down by the webhook.
+pub(crate) fn notify_decision(record: &DecisionRecord) {
+    if let Some(decision_webhook) = DECISION_WEBHOOK.get() {
+        decision_webhook.notify(record);
+    }
+}
+
+/// Returns true when the decision rejected the request, or when the request would have
+/// been rejected by a policy running in monitor mode
+fn is_violation(record: &DecisionRecord) -> bool {
+    !record.allowed || !record.raw_allowed
+}
+
+struct DecisionWebhook {
+    sender: mpsc::Sender<DecisionRecord>,
+    only_violations: bool,
+    exclude_dry_run: bool,
+    dropped_events: Arc<AtomicU64>,
+}
+
+impl DecisionWebhook {
+    fn new(config: DecisionWebhookConfig) -> Result<(Self, DecisionWebhookWorker)> {
+        if config.batch_size == 0 || config.buffer_size == 0 {
+            return Err(anyhow!(
+                "decision webhook batch size and buffer size must be greater than zero"
+            ));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| anyhow!("cannot create decision webhook client: {}", e))?;
+        let (sender, receiver) = mpsc::channel(config.buffer_size);
+        let dropped_events = Arc::new(AtomicU64::new(0));
+
+        let decision_webhook = DecisionWebhook {
+            sender,
+            only_violations: config.only_violations,
+            exclude_dry_run: config.exclude_dry_run,
+            dropped_events: dropped_events.clone(),
+        };
+        let worker = DecisionWebhookWorker {
+            receiver,
+            client,
+            url: config.url,
+            batch_size: config.batch_size,
+            flush_interval: config.flush_interval,
+            max_retries: config.max_retries,
+            dropped_events,
+        };
+
+        Ok((decision_webhook, worker))
+    }
+
+    fn notify(&self, record: &DecisionRecord) {
+        if (self.only_violations && !is_violation(record))
+            || (self.exclude_dry_run && record.dry_run)
+        {
+            return;
+        }
+
+        match self.sender.try_send(record.clone()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => drop_events(&self.dropped_events, 1, "buffer_full"),
+            Err(TrySendError::Closed(_)) => drop_events(&self.dropped_events, 1, "closed"),
+        }
+    }
+}
+
+/// Delivers the buffered events to the webhook, in batches
+struct DecisionWebhookWorker {
+    receiver: mpsc::Receiver<DecisionRecord>,
+    client: reqwest::Client,
+    url: String,
+    batch_size: usize,
+    flush_interval: Duration,
+    max_retries: u32,
+    dropped_events: Arc<AtomicU64>,
+}
+
+impl DecisionWebhookWorker {
+    async fn run(mut self) {
+        while let Some(batch) = self.next_batch().await {
+            self.deliver(batch).await;
+        }
+    }
+
+    /// Wait for the next batch of events. The batch is returned once it is full, or once
+    /// the flush interval is elapsed since its first event was received.
+    ///
+    /// Returns `None` when all the senders are gone and the buffer is empty.
+    async fn next_batch(&mut self) -> Option<Vec<DecisionRecord>> {
+        let first_event = self.receiver.recv().await?;
+        let mut batch = Vec::with_capacity(self.batch_size);
+        batch.push(first_event);
+
+        let deadline = Instant::now() + self.flush_interval;
+        while batch.len() < self.batch_size {
+            match time::timeout_at(deadline, self.receiver.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        Some(batch)
+    }
+
+    async fn deliver(&self, batch: Vec<DecisionRecord>) {
+        let mut delay = RETRY_INITIAL_DELAY;
+
+        for attempt in 0..=self.max_retries {
+            if attempt > 0 {
+                time::sleep(delay).await;
+                delay *= 2;
+            }
+
+            let result = self
+                .client
+                .post(&self.url)
+                .json(&batch)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match result {
+                Ok(_) => {
+                    debug!(events = batch.len(), "decision events delivered");
+                    return;
+                }
+                Err(error) => {
+                    warn!(
+                        ?error,
+                        attempt,
+                        events = batch.len(),
+                        "cannot deliver decision events"
+                    );
+                }
+            }
+        }
+
+        drop_events(&self.dropped_events, batch.len() as u64, "delivery_failed");
+    }
+}
+
+fn drop_events(dropped_events: &AtomicU64, count: u64, reason: &str) {
+    let dropped_events_total = dropped_events.fetch_add(count, Ordering::Relaxed) + count;
+    metrics::add_dropped_decision_events(count, reason);
+    warn!(
+        reason,
+        events = count,
+        dropped_events_total,
+        "decision events dropped"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+    use rstest::rstest;
+    use serde_json::Value;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Clone)]
+    struct Receiver {
+        batches: mpsc::Sender<Vec<Value>>,
+        failures_left: Arc<AtomicUsize>,
+    }
+
+    async fn receive_batch(
+        State(receiver): State<Receiver>,
+        Json(batch): Json<Vec<Value>>,
+    ) -> StatusCode {
+        if receiver
+            .failures_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+        {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        receiver.batches.send(batch).await.unwrap();
+
+        StatusCode::OK
+    }
+
+    /// Start a local server standing in for the webhook receiver. The server fails
+    /// the first `failures` requests.
+    async fn start_receiver(failures: usize) -> (String, mpsc::Receiver<Vec<Value>>) {
+        let (batches, batches_rx) = mpsc::channel(10);
+        let router = Router::new()
+            .route("/events", post(receive_batch))
+            .with_state(Receiver {
+                batches,
+                failures_left: Arc::new(AtomicUsize::new(failures)),
+            });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        (format!("http://{addr}/events"), batches_rx)
+    }
+
+    fn webhook_config(url: String) -> DecisionWebhookConfig {
+        DecisionWebhookConfig {
+            url,
+            batch_size: 2,
+            flush_interval: Duration::from_millis(100),
+            buffer_size: 10,
+            max_retries: 2,
+            only_violations: false,
+            exclude_dry_run: false,
+        }
+    }
+
+    fn decision(request_uid: &str, allowed: bool, raw_allowed: bool) -> DecisionRecord {
+        DecisionRecord {
+            request_uid: request_uid.to_owned(),
+            allowed,
+            raw_allowed,
+            ..Default::default()
+        }
+    }
+
+    fn request_uids(batch: &[Value]) -> Vec<&str> {
+        batch
+            .iter()
+            .map(|event| event["request_uid"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn deliver_batches() {
+        let (url, mut batches) = start_receiver(0).await;
+        let (decision_webhook, worker) = DecisionWebhook::new(webhook_config(url)).unwrap();
+        tokio::spawn(worker.run());
+
+        for request_uid in ["uid-1", "uid-2", "uid-3"] {
+            decision_webhook.notify(&decision(request_uid, true, true));
+        }
+
+        let batch = batches.recv().await.unwrap();
+        assert_eq!(request_uids(&batch), vec!["uid-1", "uid-2"]);
+        // the last batch is not full, it's sent once the flush interval is elapsed
+        let batch = batches.recv().await.unwrap();
+        assert_eq!(request_uids(&batch), vec!["uid-3"]);
+    }
+
+    #[tokio::test]
+    async fn deliver_only_violations() {
+        let (url, mut batches) = start_receiver(0).await;
+        let (decision_webhook, worker) = DecisionWebhook::new(DecisionWebhookConfig {
+            only_violations: true,
+            ..webhook_config(url)
+        })
+        .unwrap();
+        tokio::spawn(worker.run());
+
+        decision_webhook.notify(&decision("accepted", true, true));
+        decision_webhook.notify(&decision("rejected", false, false));
+        decision_webhook.notify(&decision("monitor-violation", true, false));
+
+        let batch = batches.recv().await.unwrap();
+        assert_eq!(request_uids(&batch), vec!["rejected", "monitor-violation"]);
+    }
+
+    #[tokio::test]
+    async fn skip_dry_run_requests() {
+        let (url, mut batches) = start_receiver(0).await;
+        let (decision_webhook, worker) = DecisionWebhook::new(DecisionWebhookConfig {
+            exclude_dry_run: true,
+            ..webhook_config(url)
+        })
+        .unwrap();
+        tokio::spawn(worker.run());
+
+        decision_webhook.notify(&DecisionRecord {
+            dry_run: true,
+            ..decision("dry-run", false, false)
+        });
+        decision_webhook.notify(&decision("uid-1", true, true));
+        decision_webhook.notify(&decision("uid-2", false, false));
+
+        let batch = batches.recv().await.unwrap();
+        assert_eq!(request_uids(&batch), vec!["uid-1", "uid-2"]);
+    }
+
+    #[rstest]
+    #[case::delivered_after_retry(2, 0)]
+    #[case::dropped_after_retries(3, 1)]
+    #[tokio::test]
+    async fn retry_delivery(#[case] failures: usize, #[case] expected_dropped_events: u64) {
+        let (url, mut batches) = start_receiver(failures).await;
+        let (decision_webhook, worker) = DecisionWebhook::new(DecisionWebhookConfig {
+            batch_size: 1,
+            ..webhook_config(url)
+        })
+        .unwrap();
+        let dropped_events = decision_webhook.dropped_events.clone();
+
+        decision_webhook.notify(&decision("uid", false, false));
+        drop(decision_webhook);
+        worker.run().await;
+
+        assert_eq!(
+            expected_dropped_events,
+            dropped_events.load(Ordering::Relaxed)
+        );
+        assert_eq!(expected_dropped_events == 0, batches.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn drop_events_when_buffer_is_full() {
+        let (decision_webhook, _worker) = DecisionWebhook::new(DecisionWebhookConfig {
+            buffer_size: 2,
+            ..webhook_config("http://127.0.0.1:1/events".to_owned())
+        })
+        .unwrap();
+
+        for request_uid in ["uid-1", "uid-2", "uid-3", "uid-4"] {
+            decision_webhook.notify(&decision(request_uid, false, false));
+        }
+
+        assert_eq!(2, decision_webhook.dropped_events.load(Ordering::Relaxed));
+    }
+}
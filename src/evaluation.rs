@@ -1,9 +1,22 @@
+pub(crate) mod enforcement_schedule;
 mod evaluation_environment;
+pub(crate) mod exemptions;
+pub(crate) mod message_template;
+pub(crate) mod mutation_deny_paths;
+pub(crate) mod patch;
 mod policy_evaluation_settings;
+pub(crate) mod policy_failure;
+pub(crate) mod policy_group_expression;
+mod policy_rules;
+pub(crate) mod policy_variant;
 pub(crate) mod precompiled_policy;
+pub(crate) mod request_matcher;
 
 // This is required to mock the `EvaluationEnvironment` inside of our tests
 #[mockall_double::double]
 pub(crate) use evaluation_environment::EvaluationEnvironment;
 
 pub(crate) use evaluation_environment::EvaluationEnvironmentBuilder;
+pub(crate) use evaluation_environment::{
+    POLICY_GROUP_MEMBER_ALLOWED, POLICY_GROUP_MEMBER_CAUSE_PREFIX,
+};
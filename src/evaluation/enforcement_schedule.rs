@@ -0,0 +1,124 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use croner::Cron;
+use policy_evaluator::admission_response_handler::policy_mode::PolicyMode;
+
+use crate::config::EnforcementSchedule;
+
+/// The longest a window can stay open, in seconds
+const MAX_WINDOW_DURATION_SECONDS: u64 = 366 * 24 * 60 * 60;
+
+/// The compiled version of the `EnforcementSchedule` of a policy. Each window opens at the
+/// times matched by a cron expression, evaluated in UTC, and stays open for a fixed duration.
+#[derive(Clone, Debug)]
+pub(crate) struct EnforcementWindows {
+    /// The mode of the policy while one of the windows is open
+    policy_mode: PolicyMode,
+    windows: Vec<(Cron, Duration)>,
+}
+
+impl EnforcementWindows {
+    pub(crate) fn new(schedule: &EnforcementSchedule) -> Result<Self> {
+        if schedule.windows.is_empty() {
+            return Err(anyhow!("an enforcement schedule without windows"));
+        }
+
+        let windows = schedule
+            .windows
+            .iter()
+            .map(|window| {
+                let cron = Cron::new(&window.start).parse().map_err(|e| {
+                    anyhow!("invalid enforcement window start '{}': {}", window.start, e)
+                })?;
+                let duration = Some(window.duration_seconds)
+                    .filter(|seconds| (1..=MAX_WINDOW_DURATION_SECONDS).contains(seconds))
+                    .and_then(|seconds| Duration::try_seconds(seconds as i64))
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "invalid duration of the enforcement window '{}': {} seconds",
+                            window.start,
+                            window.duration_seconds
+                        )
+                    })?;
+                Ok((cron, duration))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(EnforcementWindows {
+            policy_mode: schedule.policy_mode.clone(),
+            windows,
+        })
+    }
+
+    /// Returns the mode of the policy when one of the windows is open at the given time,
+    /// `None` otherwise.
+    pub(crate) fn policy_mode(&self, now: DateTime<Utc>) -> Option<PolicyMode> {
+        self.windows
+            .iter()
+            .any(|(cron, duration)| {
+                // the window is open when it started during the last `duration`
+                now.checked_sub_signed(*duration).is_some_and(|since| {
+                    cron.find_next_occurrence(&since, false)
+                        .is_ok_and(|start| start <= now)
+                })
+            })
+            .then(|| self.policy_mode.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnforcementWindow;
+    use rstest::*;
+
+    fn schedule(windows: &[(&str, u64)]) -> EnforcementSchedule {
+        EnforcementSchedule {
+            policy_mode: PolicyMode::Monitor,
+            windows: windows
+                .iter()
+                .map(|(start, duration_seconds)| EnforcementWindow {
+                    start: start.to_string(),
+                    duration_seconds: *duration_seconds,
+                })
+                .collect(),
+        }
+    }
+
+    fn time(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    // 2025-06-02 is a Monday
+    #[rstest]
+    #[case::window_opening(&[("0 9 * * 1-5", 3600)], "2025-06-02T09:00:00Z", true)]
+    #[case::window_open(&[("0 9 * * 1-5", 3600)], "2025-06-02T09:59:59Z", true)]
+    #[case::window_closed(&[("0 9 * * 1-5", 3600)], "2025-06-02T10:00:00Z", false)]
+    #[case::window_not_open_yet(&[("0 9 * * 1-5", 3600)], "2025-06-02T08:59:59Z", false)]
+    #[case::wrong_day(&[("0 9 * * 1-5", 3600)], "2025-06-01T09:30:00Z", false)]
+    #[case::window_open_across_days(&[("0 22 * * 5", 3 * 24 * 3600)], "2025-06-02T08:00:00Z", true)]
+    #[case::second_window(&[("0 9 * * 1", 60), ("0 12 * * 1", 3600)], "2025-06-02T12:30:00Z", true)]
+    fn policy_mode_inside_windows(
+        #[case] windows: &[(&str, u64)],
+        #[case] now: &str,
+        #[case] inside: bool,
+    ) {
+        let enforcement_windows = EnforcementWindows::new(&schedule(windows)).unwrap();
+
+        assert_eq!(
+            inside.then_some(PolicyMode::Monitor),
+            enforcement_windows.policy_mode(time(now))
+        );
+    }
+
+    #[rstest]
+    #[case::no_windows(&[])]
+    #[case::invalid_cron(&[("0 25 * * *", 60)])]
+    #[case::cron_with_seconds(&[("0 0 9 * * *", 60)])]
+    #[case::zero_duration(&[("0 9 * * *", 0)])]
+    #[case::duration_longer_than_a_year(&[("0 9 * * *", 367 * 24 * 3600)])]
+    #[case::duration_out_of_range(&[("0 9 * * *", i64::MAX as u64 / 1000)])]
+    fn invalid_schedule(#[case] windows: &[(&str, u64)]) {
+        assert!(EnforcementWindows::new(&schedule(windows)).is_err());
+    }
+}
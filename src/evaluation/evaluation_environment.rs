@@ -1,32 +1,48 @@
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     sync::Arc,
 };
 
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::Namespace;
 use policy_evaluator::{
-    admission_response::AdmissionResponse,
+    admission_request::AdmissionRequest,
+    admission_response::{AdmissionResponse, AdmissionResponseStatus, PatchType, StatusCause},
     admission_response_handler::{
         errors::{EvaluationError, Result},
         policy_id::PolicyID,
         policy_mode::PolicyMode,
     },
-    callback_requests::CallbackRequest,
+    callback_requests::{CallbackRequest, CallbackRequestType},
     evaluation_context::EvaluationContext,
     kubewarden_policy_sdk::settings::SettingsValidationResponse,
     policy_evaluator::{PolicyEvaluator, PolicyEvaluatorPre, PolicyExecutionMode, ValidateRequest},
     policy_evaluator_builder::PolicyEvaluatorBuilder,
-    policy_group_evaluator::{PolicyGroupMemberSettings, evaluator::PolicyGroupEvaluator},
-    policy_metadata::ContextAwareResource,
+    policy_metadata::{ContextAwareResource, Rule},
     wasmtime,
 };
-use tokio::sync::mpsc;
-use tracing::debug;
+use rayon::prelude::*;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{Span, debug, field, info_span, warn};
 
 use crate::{
-    config::{PolicyOrPolicyGroup, PolicyOrPolicyGroupSettings},
+    config::{
+        Exemptions, PolicyGroupMember, PolicyGroupRule, PolicyOrPolicyGroup,
+        PolicyOrPolicyGroupSettings, RequestFilter,
+    },
     evaluation::{
+        enforcement_schedule::EnforcementWindows,
+        exemptions::{Exemption, ExemptionMatcher, ExemptionScope},
+        message_template::MessageTemplate,
+        mutation_deny_paths::MutationDenyPaths,
+        patch,
         policy_evaluation_settings::PolicyEvaluationSettings,
+        policy_failure::{PolicyFailure, PolicyResponse},
+        policy_group_expression::{ExpressionError, PolicyGroupExpression},
+        policy_rules::rules_match_request,
+        policy_variant::{PolicyCanaryRouting, PolicyVariant},
         precompiled_policy::{PrecompiledPolicies, PrecompiledPolicy},
+        request_matcher::RequestMatcher,
     },
 };
 
@@ -36,6 +52,16 @@ use mockall::automock;
 /// The digest of a WebAssembly module
 type ModuleDigest = String;
 
+/// The audit annotation reporting the score of the policy groups using a score
+const POLICY_GROUP_SCORE_AUDIT_ANNOTATION: &str = "policy-group-score";
+
+/// The prefix of the field of the causes describing the members of a policy group
+pub(crate) const POLICY_GROUP_MEMBER_CAUSE_PREFIX: &str = "spec.policies.";
+/// The reason of the causes describing the members of a policy group that accepted the request
+pub(crate) const POLICY_GROUP_MEMBER_ALLOWED: &str = "Allowed";
+/// The reason of the causes describing the members of a policy group that rejected the request
+pub(crate) const POLICY_GROUP_MEMBER_DENIED: &str = "Denied";
+
 /// This structure contains all the policies defined by the user inside of the `policies.yml`.
 /// It also provides helper methods to perform the validation of a request and the validation
 /// of the settings provided by the user.
@@ -54,11 +80,14 @@ type ModuleDigest = String;
 /// only once, during the bootstrap phase.
 #[derive(Default)]
 pub(crate) struct EvaluationEnvironment {
-    /// The name of the Namespace where Policy Server doesn't operate. All the requests
-    /// involving this Namespace are going to be accepted. This is usually done to prevent user
-    /// policies from messing with the components of the Kubewarden stack (which are all
-    /// deployed inside of the same Namespace).
-    always_accept_admission_reviews_on_namespace: Option<String>,
+    /// The requests that are accepted by all the policies without evaluating them. This is
+    /// usually done to prevent user policies from messing with the components of the
+    /// Kubewarden stack, or with the system namespaces and service accounts.
+    exemptions: Option<ExemptionMatcher>,
+
+    /// A map with the ID of the policy as key, and the requests the policy accepts without
+    /// evaluating them as value.
+    policy_id_to_exemptions: HashMap<PolicyID, ExemptionMatcher>,
 
     /// A map with the module digest as key, and the associated `PolicyEvaluatorPre`
     /// as value
@@ -89,6 +118,22 @@ pub(crate) struct EvaluationEnvironment {
     /// A Set containing the IDs of the policy groups.
     policy_groups: HashSet<PolicyID>,
 
+    /// A map with the ID of a policy group using an expression as key, and the compiled
+    /// expression as value. The groups whose expression cannot be compiled have an
+    /// initialization error instead.
+    policy_id_to_group_expression: HashMap<PolicyID, PolicyGroupExpression>,
+
+    /// A Set containing the IDs of the mutation pipelines.
+    mutation_pipelines: HashSet<PolicyID>,
+
+    /// A map with the ID of the policy as key, and the details about how the requests are
+    /// split between the policy and its canary as value.
+    policy_id_to_canary: HashMap<PolicyID, PolicyCanaryRouting>,
+
+    /// A map with the ID of the policy as key, and the rules declared by its metadata as value.
+    /// The rules of a policy group are the union of the rules of its members.
+    policy_id_to_rules: HashMap<PolicyID, Vec<Rule>>,
+
     /// Channel used by the synchronous world (like the `host_callback` waPC function,
     /// but also Burrego for k8s context aware data),
     /// to request the computation of code that can only be run inside of an
@@ -97,6 +142,28 @@ pub(crate) struct EvaluationEnvironment {
 
     /// When set, defines after how many seconds a policy evaluation is interrupted.
     global_policy_evaluation_limit_seconds: Option<u64>,
+
+    /// When set, the mutating policies are evaluated a second time against the object they
+    /// mutated, to ensure the mutation is idempotent.
+    verify_mutation_idempotency: bool,
+
+    /// When set, the requests that would have been rejected by a policy running in monitor
+    /// mode receive a warning describing the violation.
+    monitor_mode_warnings: bool,
+
+    /// A map with the ID of the policy as key, and the conditions a request must satisfy to
+    /// be evaluated by the policy as value. Policies without conditions evaluate all the
+    /// requests.
+    policy_id_to_request_matcher: HashMap<PolicyID, RequestMatcher>,
+
+    /// A map with the ID of the policy as key, and the time windows during which the mode of
+    /// the policy changes as value.
+    policy_id_to_enforcement_windows: HashMap<PolicyID, EnforcementWindows>,
+
+    /// The threads evaluating concurrently the members of the policy groups using
+    /// `concurrentEvaluation`. Created only when at least one policy group, nested ones
+    /// included, uses it.
+    policy_group_pool: Option<rayon::ThreadPool>,
 }
 
 /// This structure is used to build the `EvaluationEnvironment` instance.
@@ -106,7 +173,11 @@ pub(crate) struct EvaluationEnvironmentBuilder<'engine, 'precompiled_policies> {
     callback_handler_tx: mpsc::Sender<CallbackRequest>,
     continue_on_errors: bool,
     global_policy_evaluation_limit_seconds: Option<u64>,
-    always_accept_admission_reviews_on_namespace: Option<String>,
+    exemptions: Exemptions,
+    verify_mutation_idempotency: bool,
+    enforce_metadata_rules: bool,
+    monitor_mode_warnings: bool,
+    policy_group_workers: usize,
 }
 
 impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'precompiled_policies> {
@@ -122,7 +193,11 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
             callback_handler_tx,
             continue_on_errors: false,
             global_policy_evaluation_limit_seconds: None,
-            always_accept_admission_reviews_on_namespace: None,
+            exemptions: Exemptions::default(),
+            verify_mutation_idempotency: false,
+            enforce_metadata_rules: false,
+            monitor_mode_warnings: false,
+            policy_group_workers: num_cpus::get(),
         }
     }
 
@@ -141,9 +216,35 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
         self
     }
 
-    /// Set the namespace where all the requests are going to be accepted
-    pub fn with_always_accept_admission_reviews_on_namespace(mut self, namespace: String) -> Self {
-        self.always_accept_admission_reviews_on_namespace = Some(namespace);
+    /// Evaluate the mutating policies a second time, against the object they mutated
+    pub fn with_verify_mutation_idempotency(mut self, verify_mutation_idempotency: bool) -> Self {
+        self.verify_mutation_idempotency = verify_mutation_idempotency;
+        self
+    }
+
+    /// Skip the evaluation of the requests that do not match the rules declared by the
+    /// metadata of the policies. The rules defined inside of `policies.yml` are always enforced
+    pub fn with_enforce_metadata_rules(mut self, enforce_metadata_rules: bool) -> Self {
+        self.enforce_metadata_rules = enforce_metadata_rules;
+        self
+    }
+
+    /// Warn about the requests that would have been rejected by the policies running in
+    /// monitor mode
+    pub fn with_monitor_mode_warnings(mut self, monitor_mode_warnings: bool) -> Self {
+        self.monitor_mode_warnings = monitor_mode_warnings;
+        self
+    }
+
+    /// Set the number of threads evaluating concurrently the members of the policy groups
+    pub fn with_policy_group_workers(mut self, policy_group_workers: usize) -> Self {
+        self.policy_group_workers = policy_group_workers;
+        self
+    }
+
+    /// Set the requests that are going to be accepted by all the policies
+    pub fn with_exemptions(mut self, exemptions: Exemptions) -> Self {
+        self.exemptions = exemptions;
         self
     }
 
@@ -172,15 +273,43 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
         &self,
         policies: &HashMap<String, PolicyOrPolicyGroup>,
     ) -> Result<EvaluationEnvironment> {
+        let exemptions = ExemptionMatcher::new(&self.exemptions, ExemptionScope::Global)
+            .map_err(|e| EvaluationError::BootstrapFailure(e.to_string()))?;
+
         let mut eval_env = EvaluationEnvironment {
-            always_accept_admission_reviews_on_namespace: self
-                .always_accept_admission_reviews_on_namespace
-                .clone(),
+            exemptions: (!exemptions.is_empty()).then_some(exemptions),
             callback_handler_tx: Some(self.callback_handler_tx.clone()),
             global_policy_evaluation_limit_seconds: self.global_policy_evaluation_limit_seconds,
+            verify_mutation_idempotency: self.verify_mutation_idempotency,
+            monitor_mode_warnings: self.monitor_mode_warnings,
             ..Default::default()
         };
 
+        if policies.values().any(|policy| match policy {
+            PolicyOrPolicyGroup::PolicyGroup {
+                concurrent_evaluation,
+                policies,
+                ..
+            } => {
+                *concurrent_evaluation
+                    || policies
+                        .values()
+                        .any(PolicyGroupMember::has_concurrent_evaluation)
+            }
+            _ => false,
+        }) {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.policy_group_workers)
+                .thread_name(|index| format!("policy-group-worker-{index}"))
+                .build()
+                .map_err(|e| {
+                    EvaluationError::BootstrapFailure(format!(
+                        "cannot create the policy group workers: {e}"
+                    ))
+                })?;
+            eval_env.policy_group_pool = Some(pool);
+        }
+
         for (policy_name, policy) in policies {
             // there's no way to recover from a parse error, so we just return it
             let id: PolicyID = policy_name.parse()?;
@@ -200,23 +329,71 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                 }
             };
 
+            let custom_rejection_message = match policy
+                .custom_rejection_message()
+                .map(MessageTemplate::new)
+                .transpose()
+            {
+                Ok(custom_rejection_message) => custom_rejection_message,
+                Err(e) => {
+                    if !self.continue_on_errors {
+                        return Err(EvaluationError::BootstrapFailure(format!("{id}: {e}")));
+                    }
+                    eval_env
+                        .policy_initialization_errors
+                        .insert(id.to_owned(), e.to_string());
+                    continue;
+                }
+            };
+
+            let skip_context_aware_resources_on_dry_run =
+                policy.skip_context_aware_resources_on_dry_run();
+
             match policy {
                 PolicyOrPolicyGroup::Policy {
                     module: url,
                     policy_mode,
-                    message,
                     allowed_to_mutate,
                     context_aware_resources,
                     timeout_eval_seconds,
+                    canary,
+                    mutation_deny_paths,
                     ..
                 } => {
+                    let mutation_deny_paths = if mutation_deny_paths.is_empty() {
+                        None
+                    } else {
+                        match MutationDenyPaths::new(mutation_deny_paths) {
+                            Ok(mutation_deny_paths) => Some(mutation_deny_paths),
+                            Err(e) => {
+                                if !self.continue_on_errors {
+                                    return Err(EvaluationError::BootstrapFailure(e.to_string()));
+                                }
+                                eval_env
+                                    .policy_initialization_errors
+                                    .insert(id.to_owned(), e.to_string());
+                                continue;
+                            }
+                        }
+                    };
+
                     let policy_evaluation_settings = PolicyEvaluationSettings {
                         policy_mode: policy_mode.to_owned(),
                         allowed_to_mutate: allowed_to_mutate.unwrap_or(false),
                         settings,
-                        custom_rejection_message: message.clone(),
+                        custom_rejection_message: custom_rejection_message.clone(),
                         timeout_eval_seconds: timeout_eval_seconds.to_owned(),
+                        mutation_deny_paths,
+                        skip_context_aware_resources_on_dry_run,
                     };
+                    let canary_evaluation_settings = canary.as_ref().map(|canary| {
+                        let mut canary_evaluation_settings = policy_evaluation_settings.clone();
+                        if let Some(settings) = &canary.settings {
+                            canary_evaluation_settings.settings =
+                                PolicyOrPolicyGroupSettings::Policy(settings.clone());
+                        }
+                        canary_evaluation_settings
+                    });
 
                     let epoch_deadline =
                         timeout_eval_seconds.or(self.global_policy_evaluation_limit_seconds);
@@ -228,42 +405,120 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                         epoch_deadline,
                     };
 
-                    if let Err(e) = self.bootstrap_policy(
+                    let bootstrapped = match self.bootstrap_policy(
                         &mut eval_env,
                         id.clone(),
                         url,
-                        policy_evaluation_settings,
+                        policy_evaluation_settings.clone(),
                         eval_ctx,
                     ) {
-                        if !self.continue_on_errors {
-                            return Err(e);
+                        Ok(()) => true,
+                        Err(e) => {
+                            if !self.continue_on_errors {
+                                return Err(e);
+                            }
+                            // The policy stays known: the requests it has to evaluate are
+                            // rejected with the initialization error
+                            eval_env
+                                .policy_id_to_settings
+                                .entry(id.clone())
+                                .or_insert(policy_evaluation_settings);
+                            eval_env
+                                .policy_initialization_errors
+                                .insert(id.to_owned(), e.to_string());
+                            false
+                        }
+                    };
+
+                    if bootstrapped
+                        && let (Some(canary), Some(canary_evaluation_settings)) =
+                            (canary, canary_evaluation_settings)
+                    {
+                        // The ID cannot clash with the one of a user defined policy, because
+                        // policy names cannot contain the '/' character
+                        let canary_policy_id = PolicyID::Policy(format!("{id}/canary"));
+
+                        let eval_ctx = EvaluationContext {
+                            policy_id: canary_policy_id.to_string(),
+                            callback_channel: Some(self.callback_handler_tx.clone()),
+                            ctx_aware_resources_allow_list: context_aware_resources.to_owned(),
+                            epoch_deadline,
+                        };
+
+                        eval_env.policy_id_to_canary.insert(
+                            id.clone(),
+                            PolicyCanaryRouting {
+                                canary_policy_id: canary_policy_id.clone(),
+                                weight: canary.weight,
+                                routing_key: canary.routing_key,
+                            },
+                        );
+
+                        if let Err(e) = self.bootstrap_policy(
+                            &mut eval_env,
+                            canary_policy_id.clone(),
+                            &canary.module,
+                            canary_evaluation_settings,
+                            eval_ctx,
+                        ) {
+                            if !self.continue_on_errors {
+                                return Err(e);
+                            }
+                            // The stable policy is bootstrapped: its request filter and its
+                            // enforcement schedule must still be registered
+                            eval_env
+                                .policy_initialization_errors
+                                .insert(canary_policy_id, e.to_string());
                         }
-                        eval_env
-                            .policy_initialization_errors
-                            .insert(id.to_owned(), e.to_string());
-                        continue;
                     }
                 }
                 PolicyOrPolicyGroup::PolicyGroup {
                     policy_mode,
+                    allowed_to_mutate,
                     policies,
                     ..
                 } => {
                     let policy_evaluation_settings = PolicyEvaluationSettings {
                         policy_mode: policy_mode.to_owned(),
-                        allowed_to_mutate: false, // Group policies are not allowed to mutate
+                        allowed_to_mutate: allowed_to_mutate.unwrap_or(false),
                         custom_rejection_message: None,
                         settings,
                         timeout_eval_seconds: None,
+                        mutation_deny_paths: None,
+                        skip_context_aware_resources_on_dry_run,
                     };
                     eval_env.register_policy_group(&id, policy_evaluation_settings);
 
-                    for (policy_name, policy) in policies {
+                    self.bootstrap_policy_group_members(
+                        &mut eval_env,
+                        &id,
+                        policies,
+                        skip_context_aware_resources_on_dry_run,
+                    )?;
+                }
+                PolicyOrPolicyGroup::MutationPipeline {
+                    policy_mode,
+                    allowed_to_mutate,
+                    steps,
+                    ..
+                } => {
+                    let policy_evaluation_settings = PolicyEvaluationSettings {
+                        policy_mode: policy_mode.to_owned(),
+                        allowed_to_mutate: allowed_to_mutate.unwrap_or(false),
+                        custom_rejection_message: custom_rejection_message.clone(),
+                        settings,
+                        timeout_eval_seconds: None,
+                        mutation_deny_paths: None,
+                        skip_context_aware_resources_on_dry_run,
+                    };
+                    eval_env.register_mutation_pipeline(&id, policy_evaluation_settings);
+
+                    for step in steps {
                         let policy_id = PolicyID::PolicyGroupPolicy {
                             group: id.to_string(),
-                            name: policy_name.clone(),
+                            name: step.name.clone(),
                         };
-                        let settings = match policy.settings() {
+                        let settings = match step.settings() {
                             Ok(s) => s,
                             Err(e) => {
                                 if !self.continue_on_errors {
@@ -278,31 +533,33 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                             }
                         };
 
+                        // The mutations of the steps are checked against the settings
+                        // of the pipeline
                         let policy_evaluation_settings = PolicyEvaluationSettings {
                             policy_mode: PolicyMode::Protect,
-                            allowed_to_mutate: false,
+                            allowed_to_mutate: true,
                             settings,
                             custom_rejection_message: None,
-                            timeout_eval_seconds: policy.timeout_eval_seconds,
+                            timeout_eval_seconds: step.timeout_eval_seconds,
+                            mutation_deny_paths: None,
+                            skip_context_aware_resources_on_dry_run,
                         };
 
-                        let epoch_deadline = policy
+                        let epoch_deadline = step
                             .timeout_eval_seconds
                             .or(self.global_policy_evaluation_limit_seconds);
 
                         let eval_ctx = EvaluationContext {
                             policy_id: policy_id.to_string(),
                             callback_channel: Some(self.callback_handler_tx.clone()),
-                            ctx_aware_resources_allow_list: policy
-                                .context_aware_resources
-                                .to_owned(),
+                            ctx_aware_resources_allow_list: step.context_aware_resources.to_owned(),
                             epoch_deadline,
                         };
 
                         if let Err(e) = self.bootstrap_policy(
                             &mut eval_env,
                             policy_id.clone(),
-                            &policy.module,
+                            &step.module,
                             policy_evaluation_settings,
                             eval_ctx,
                         ) {
@@ -317,11 +574,151 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
                     }
                 }
             }
+
+            if let Err(e) = eval_env.register_request_filter(
+                &id,
+                policy.request_filter(),
+                self.enforce_metadata_rules,
+            ) {
+                if !self.continue_on_errors {
+                    return Err(e);
+                }
+                eval_env
+                    .policy_initialization_errors
+                    .insert(id.to_owned(), e.to_string());
+            }
+
+            if let Some(enforcement_schedule) = policy.enforcement_schedule() {
+                match EnforcementWindows::new(enforcement_schedule) {
+                    Ok(enforcement_windows) => {
+                        eval_env
+                            .policy_id_to_enforcement_windows
+                            .insert(id.to_owned(), enforcement_windows);
+                    }
+                    Err(e) => {
+                        if !self.continue_on_errors {
+                            return Err(EvaluationError::BootstrapFailure(format!("{id}: {e}")));
+                        }
+                        eval_env
+                            .policy_initialization_errors
+                            .insert(id.to_owned(), e.to_string());
+                    }
+                }
+            }
+
+            if eval_env
+                .policy_id_to_rules
+                .get(&id)
+                .is_none_or(|rules| rules.is_empty())
+                && !eval_env.failed_policy_ids().contains(&id)
+            {
+                warn!(
+                    policy_id = id.to_string(),
+                    "the policy doesn't declare any rule, inside of its metadata or of its configuration: it is never evaluated by the validate endpoint running all the matching policies"
+                );
+            }
         }
 
         Ok(eval_env)
     }
 
+    /// Internal method used to bootstrap the members of a policy group. The members that are
+    /// nested policy groups are registered as groups, and their own members are bootstrapped
+    /// too.
+    fn bootstrap_policy_group_members(
+        &self,
+        eval_env: &mut EvaluationEnvironment,
+        group_id: &PolicyID,
+        policies: &HashMap<String, PolicyGroupMember>,
+        skip_context_aware_resources_on_dry_run: bool,
+    ) -> Result<()> {
+        for (policy_name, policy) in policies {
+            let policy_id = policy_group_member_id(group_id, policy_name);
+            let settings = match policy.settings() {
+                Ok(s) => s,
+                Err(e) => {
+                    if !self.continue_on_errors {
+                        return Err(EvaluationError::BootstrapFailure(format!(
+                            "cannot extract settings from policy: {e}"
+                        )));
+                    }
+                    eval_env
+                        .policy_initialization_errors
+                        .insert(policy_id, e.to_string());
+                    continue;
+                }
+            };
+
+            let policy_evaluation_settings = PolicyEvaluationSettings {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: false,
+                settings,
+                custom_rejection_message: None,
+                timeout_eval_seconds: None,
+                mutation_deny_paths: None,
+                skip_context_aware_resources_on_dry_run,
+            };
+
+            match policy {
+                PolicyGroupMember::Policy {
+                    module,
+                    context_aware_resources,
+                    timeout_eval_seconds,
+                    ..
+                } => {
+                    let policy_evaluation_settings = PolicyEvaluationSettings {
+                        timeout_eval_seconds: *timeout_eval_seconds,
+                        ..policy_evaluation_settings
+                    };
+
+                    let epoch_deadline =
+                        timeout_eval_seconds.or(self.global_policy_evaluation_limit_seconds);
+
+                    let eval_ctx = EvaluationContext {
+                        policy_id: policy_id.to_string(),
+                        callback_channel: Some(self.callback_handler_tx.clone()),
+                        ctx_aware_resources_allow_list: context_aware_resources.to_owned(),
+                        epoch_deadline,
+                    };
+
+                    if let Err(e) = self.bootstrap_policy(
+                        eval_env,
+                        policy_id.clone(),
+                        module,
+                        policy_evaluation_settings,
+                        eval_ctx,
+                    ) {
+                        if !self.continue_on_errors {
+                            return Err(e);
+                        }
+                        eval_env
+                            .policy_initialization_errors
+                            .insert(policy_id, e.to_string());
+                        continue;
+                    }
+                }
+                PolicyGroupMember::PolicyGroup { policies, .. } => {
+                    eval_env.register_policy_group(&policy_id, policy_evaluation_settings);
+
+                    self.bootstrap_policy_group_members(
+                        eval_env,
+                        &policy_id,
+                        policies,
+                        skip_context_aware_resources_on_dry_run,
+                    )?;
+                }
+                // The references are resolved when the configuration is loaded
+                PolicyGroupMember::Reference { reference, .. } => {
+                    return Err(EvaluationError::BootstrapFailure(format!(
+                        "{policy_id}: the reference to policy '{reference}' has not been resolved"
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Internal method used to bootstrap a policy. The policy is either a single policy or a
     /// children of a policy group.
     fn bootstrap_policy(
@@ -358,13 +755,28 @@ impl<'engine, 'precompiled_policies> EvaluationEnvironmentBuilder<'engine, 'prec
 #[cfg_attr(test, automock)]
 #[cfg_attr(test, allow(dead_code))]
 impl EvaluationEnvironment {
-    /// Returns `true` if the given `namespace` is the special Namespace that is ignored by all
-    /// the policies
-    pub(crate) fn should_always_accept_requests_made_inside_of_namespace(
+    /// Return the reason why the request has to be accepted without evaluating the policy,
+    /// if any. The global exemptions are checked before the ones of the policy.
+    pub(crate) fn get_request_exemption(
         &self,
-        namespace: &str,
-    ) -> bool {
-        self.always_accept_admission_reviews_on_namespace.as_deref() == Some(namespace)
+        policy_id: &PolicyID,
+        adm_req: &AdmissionRequest,
+    ) -> Option<Exemption> {
+        self.exemptions
+            .iter()
+            .chain(self.policy_id_to_exemptions.get(policy_id))
+            .find_map(|exemption_matcher| exemption_matcher.exemption(adm_req))
+    }
+
+    /// Returns `true` if the mutations have to be checked for idempotency
+    pub(crate) fn should_verify_mutation_idempotency(&self) -> bool {
+        self.verify_mutation_idempotency
+    }
+
+    /// Returns `true` if the violations of the policies running in monitor mode have to be
+    /// reported to the client
+    pub(crate) fn should_warn_on_monitor_mode_violations(&self) -> bool {
+        self.monitor_mode_warnings
     }
 
     /// Register a new policy. It takes care of creating a new `PolicyEvaluator` (when needed).
@@ -420,55 +832,318 @@ impl EvaluationEnvironment {
             eval_ctx.ctx_aware_resources_allow_list,
         );
 
+        let rules_owner = match policy_id {
+            PolicyID::PolicyGroupPolicy { group, .. } => PolicyID::Policy(group.to_owned()),
+            PolicyID::Policy(_) => policy_id.to_owned(),
+        };
+        self.policy_id_to_rules
+            .entry(rules_owner)
+            .or_default()
+            .extend(precompiled_policy.rules.iter().cloned());
+
         Ok(())
     }
 
-    /// Register a policy group
+    /// Register a policy group. The expression of the group is compiled once, here.
     fn register_policy_group(
         &mut self,
         policy_id: &PolicyID,
         policy_evaluation_settings: PolicyEvaluationSettings,
     ) {
+        if let PolicyOrPolicyGroupSettings::PolicyGroup {
+            rule: PolicyGroupRule::Expression(expression),
+            policies,
+            ..
+        } = &policy_evaluation_settings.settings
+        {
+            match PolicyGroupExpression::new(expression, policies) {
+                Ok(policy_group_expression) => {
+                    self.policy_id_to_group_expression
+                        .insert(policy_id.to_owned(), policy_group_expression);
+                }
+                Err(e) => {
+                    self.policy_initialization_errors.insert(
+                        policy_id.to_owned(),
+                        format!("invalid expression of policy group {policy_id}: {e}"),
+                    );
+                }
+            }
+        }
+
         self.policy_id_to_settings
             .insert(policy_id.to_owned(), policy_evaluation_settings);
         self.policy_groups.insert(policy_id.to_owned());
     }
 
-    /// Given a policy ID, return how the policy operates
-    pub(crate) fn get_policy_mode(&self, policy_id: &PolicyID) -> Result<PolicyMode> {
+    /// Register a mutation pipeline
+    fn register_mutation_pipeline(
+        &mut self,
+        policy_id: &PolicyID,
+        policy_evaluation_settings: PolicyEvaluationSettings,
+    ) {
         self.policy_id_to_settings
-            .get(policy_id)
-            .map(|settings| settings.policy_mode.clone())
-            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
+            .insert(policy_id.to_owned(), policy_evaluation_settings);
+        self.mutation_pipelines.insert(policy_id.to_owned());
     }
 
-    /// Given a policy ID, return how the policy custom reject message
-    pub(crate) fn get_policy_custom_rejection_message(
+    /// Register the conditions a request must satisfy to be evaluated by the policy, and the
+    /// requests the policy accepts without evaluating them. This must
+    /// be invoked once the policy, or all the members of the group, have been registered.
+    ///
+    /// The rules defined by the user replace the ones declared by the metadata of the policy,
+    /// also when evaluating all the matching policies.
+    fn register_request_filter(
+        &mut self,
+        policy_id: &PolicyID,
+        request_filter: &RequestFilter,
+        enforce_metadata_rules: bool,
+    ) -> Result<()> {
+        let exemptions = ExemptionMatcher::new(&request_filter.exemptions, ExemptionScope::Policy)
+            .map_err(|e| EvaluationError::BootstrapFailure(format!("{policy_id}: {e}")))?;
+        if !exemptions.is_empty() {
+            self.policy_id_to_exemptions
+                .insert(policy_id.to_owned(), exemptions);
+        }
+
+        if let Some(rules) = &request_filter.rules {
+            self.policy_id_to_rules
+                .insert(policy_id.to_owned(), rules.to_owned());
+        }
+
+        // Policies that do not declare any rule are never filtered by the metadata rules
+        let rules = request_filter.rules.clone().or_else(|| {
+            self.policy_id_to_rules
+                .get(policy_id)
+                .filter(|rules| enforce_metadata_rules && !rules.is_empty())
+                .cloned()
+        });
+        let request_matcher = RequestMatcher {
+            rules,
+            namespace_selector: request_filter.namespace_selector.clone(),
+            object_selector: request_filter.object_selector.clone(),
+        };
+
+        if !request_matcher.is_empty() {
+            self.policy_id_to_request_matcher
+                .insert(policy_id.to_owned(), request_matcher);
+        }
+
+        Ok(())
+    }
+
+    /// Return the IDs of the policies whose rules match the given request, sorted by name.
+    /// Canaries and the members of policy groups are not part of the result, they are
+    /// evaluated through the policy they belong to.
+    ///
+    /// The policies that failed to initialize, or whose canary or members did, match all the
+    /// requests: their rules might be unknown, and they must reject the requests instead of
+    /// being skipped.
+    pub(crate) fn get_policies_matching_request(
+        &self,
+        adm_req: &AdmissionRequest,
+    ) -> Vec<PolicyID> {
+        let canary_policy_ids: HashSet<&PolicyID> = self
+            .policy_id_to_canary
+            .values()
+            .map(|canary_routing| &canary_routing.canary_policy_id)
+            .collect();
+        let failed_policy_ids = self.failed_policy_ids();
+
+        let mut policy_ids: Vec<PolicyID> = self
+            .policy_id_to_settings
+            .keys()
+            .filter(|policy_id| {
+                matches!(policy_id, PolicyID::Policy(_)) && !canary_policy_ids.contains(policy_id)
+            })
+            .filter(|policy_id| {
+                failed_policy_ids.contains(*policy_id)
+                    || self
+                        .policy_id_to_rules
+                        .get(*policy_id)
+                        .is_some_and(|rules| rules_match_request(rules, adm_req))
+            })
+            .map(|policy_id| policy_id.to_owned())
+            .collect();
+        policy_ids.sort_by_key(|policy_id| policy_id.to_string());
+
+        policy_ids
+    }
+
+    /// Returns the IDs of the policies that failed to initialize, including the ones whose
+    /// canary or members failed to initialize
+    fn failed_policy_ids(&self) -> HashSet<PolicyID> {
+        self.policy_initialization_errors
+            .keys()
+            .map(|policy_id| match policy_id {
+                PolicyID::PolicyGroupPolicy { group, .. } => PolicyID::Policy(group.to_owned()),
+                PolicyID::Policy(_) => self
+                    .policy_id_to_canary
+                    .iter()
+                    .find(|(_, canary_routing)| &canary_routing.canary_policy_id == policy_id)
+                    .map_or_else(
+                        || policy_id.to_owned(),
+                        |(stable_id, _)| stable_id.to_owned(),
+                    ),
+            })
+            .collect()
+    }
+
+    /// Returns `true` when the request satisfies the conditions of the policy, and has to be
+    /// evaluated. The labels of the namespace of the request are looked up only when the
+    /// policy has a namespace selector.
+    pub(crate) fn should_evaluate_request(
         &self,
         policy_id: &PolicyID,
-    ) -> Result<Option<String>> {
-        self.policy_id_to_settings
+        adm_req: &AdmissionRequest,
+    ) -> bool {
+        self.policy_id_to_request_matcher
             .get(policy_id)
-            .map(|settings| settings.custom_rejection_message.clone())
-            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
+            .is_none_or(|request_matcher| {
+                request_matcher.matches(adm_req, |namespace| self.get_namespace_labels(namespace))
+            })
     }
 
-    /// Given a policy ID, returns true if the policy is allowed to mutate
-    pub(crate) fn get_policy_allowed_to_mutate(&self, policy_id: &PolicyID) -> Result<bool> {
+    /// Fetch the labels of the given namespace, using the cache of the callback handler.
+    /// Returns `None` when the namespace cannot be obtained.
+    fn get_namespace_labels(&self, namespace: &str) -> Option<BTreeMap<String, String>> {
+        let callback_handler_tx = self.callback_handler_tx.as_ref()?;
+        let (tx, rx) = oneshot::channel();
+        let request = CallbackRequest {
+            request: CallbackRequestType::KubernetesGetResource {
+                api_version: "v1".to_owned(),
+                kind: "Namespace".to_owned(),
+                name: namespace.to_owned(),
+                namespace: None,
+                disable_cache: false,
+            },
+            response_channel: tx,
+        };
+
+        let response = callback_handler_tx
+            .blocking_send(request)
+            .map_err(|e| anyhow::anyhow!("cannot send the request: {e}"))
+            .and_then(|_| {
+                rx.blocking_recv()
+                    .map_err(|e| anyhow::anyhow!("cannot receive the response: {e}"))?
+            })
+            .and_then(|response| {
+                serde_json::from_slice::<Namespace>(&response.payload)
+                    .map_err(|e| anyhow::anyhow!("cannot parse the namespace: {e}"))
+            });
+
+        match response {
+            Ok(namespace) => Some(namespace.metadata.labels.unwrap_or_default()),
+            Err(error) => {
+                warn!(
+                    namespace,
+                    ?error,
+                    "cannot fetch the labels of the namespace, the namespace selector is ignored"
+                );
+                None
+            }
+        }
+    }
+
+    /// Given a policy ID, return how the policy operates
+    pub(crate) fn get_policy_mode(&self, policy_id: &PolicyID) -> Result<PolicyMode> {
         self.policy_id_to_settings
             .get(policy_id)
-            .map(|settings| settings.allowed_to_mutate)
+            .map(|settings| settings.policy_mode.clone())
             .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
     }
 
-    /// Given a policy ID, returns the settings provided by the user inside of `policies.yml`
-    fn get_policy_settings(&self, policy_id: &PolicyID) -> Result<PolicyEvaluationSettings> {
-        let settings = self
-            .policy_id_to_settings
-            .get(policy_id)
-            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?
-            .clone();
-        Ok(settings)
+    /// Given a policy ID, return the error that prevented the policy from being registered,
+    /// for example because its settings are invalid. The registered policies report their
+    /// initialization error when they are asked to evaluate a request.
+    pub(crate) fn get_policy_initialization_error(&self, policy_id: &PolicyID) -> Option<String> {
+        if self.policy_id_to_settings.contains_key(policy_id) {
+            return None;
+        }
+        self.policy_initialization_errors.get(policy_id).cloned()
+    }
+
+    /// Given a policy ID, return the digest of its WebAssembly module. Policy groups and
+    /// mutation pipelines are made of multiple modules, they do not have a digest.
+    pub(crate) fn get_policy_module_digest(&self, policy_id: &PolicyID) -> Option<String> {
+        self.policy_id_to_module_digest.get(policy_id).cloned()
+    }
+
+    /// Given a policy ID, return the mode imposed by its enforcement schedule at the given
+    /// time. Returns `None` when none of the windows of the schedule is open, or when the
+    /// policy doesn't have a schedule.
+    pub(crate) fn get_scheduled_policy_mode(
+        &self,
+        policy_id: &PolicyID,
+        now: DateTime<Utc>,
+    ) -> Option<PolicyMode> {
+        self.policy_id_to_enforcement_windows
+            .get(policy_id)
+            .and_then(|enforcement_windows| enforcement_windows.policy_mode(now))
+    }
+
+    /// Given a policy ID and a request, return the variant of the policy that has to evaluate
+    /// the request. Policies without a canary always use the stable variant.
+    pub(crate) fn select_policy_variant(
+        &self,
+        policy_id: &PolicyID,
+        req: &ValidateRequest,
+    ) -> Result<PolicyVariant> {
+        if !self.policy_id_to_settings.contains_key(policy_id) {
+            return Err(EvaluationError::PolicyNotFound(policy_id.to_string()));
+        }
+
+        let variant = match self.policy_id_to_canary.get(policy_id) {
+            Some(canary_routing) => {
+                let namespace = match req {
+                    ValidateRequest::AdmissionRequest(adm_req) => adm_req.namespace.as_deref(),
+                    ValidateRequest::Raw(_) => None,
+                };
+                canary_routing.select(req.uid(), namespace)
+            }
+            None => PolicyVariant::Stable,
+        };
+
+        Ok(variant)
+    }
+
+    /// Given a policy ID, return how the policy custom reject message
+    pub(crate) fn get_policy_custom_rejection_message(
+        &self,
+        policy_id: &PolicyID,
+    ) -> Result<Option<MessageTemplate>> {
+        self.policy_id_to_settings
+            .get(policy_id)
+            .map(|settings| settings.custom_rejection_message.clone())
+            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
+    }
+
+    /// Given a policy ID, returns true if the policy is allowed to mutate
+    pub(crate) fn get_policy_allowed_to_mutate(&self, policy_id: &PolicyID) -> Result<bool> {
+        self.policy_id_to_settings
+            .get(policy_id)
+            .map(|settings| settings.allowed_to_mutate)
+            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
+    }
+
+    /// Given a policy ID, returns the fields the policy is not allowed to mutate
+    pub(crate) fn get_policy_mutation_deny_paths(
+        &self,
+        policy_id: &PolicyID,
+    ) -> Result<Option<MutationDenyPaths>> {
+        self.policy_id_to_settings
+            .get(policy_id)
+            .map(|settings| settings.mutation_deny_paths.clone())
+            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))
+    }
+
+    /// Given a policy ID, returns the settings provided by the user inside of `policies.yml`
+    fn get_policy_settings(&self, policy_id: &PolicyID) -> Result<PolicyEvaluationSettings> {
+        let settings = self
+            .policy_id_to_settings
+            .get(policy_id)
+            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?
+            .clone();
+        Ok(settings)
     }
 
     /// Validate the settings the user provided for the given policy
@@ -496,23 +1171,33 @@ impl EvaluationEnvironment {
                     }
                 };
             }
-            PolicyOrPolicyGroupSettings::PolicyGroup { .. } => {
-                let group_evaluator = self.build_policy_group_evaluator(policy_id)?;
-                let validation_result = group_evaluator.validate_settings();
-                if !validation_result.valid {
-                    return Err(EvaluationError::PolicyInitialization(
-                        validation_result
-                            .message
-                            .unwrap_or(format!("{} settings are not valid", policy_id)),
-                    ));
+            PolicyOrPolicyGroupSettings::PolicyGroup { rule, .. } => {
+                for name in self.nested_policy_groups(policy_id)? {
+                    self.validate_settings(&policy_group_member_id(policy_id, &name))?;
+                }
+                // The groups using a score have no expression to validate. The expression
+                // is evaluated as if all the members accepted the request.
+                if let PolicyGroupRule::Expression(_) = rule {
+                    self.policy_id_to_group_expression
+                        .get(policy_id)
+                        .ok_or_else(|| self.policy_group_expression_error(policy_id))?
+                        .evaluate(policy_id, |_| Ok(true))
+                        .map_err(|e| match e {
+                            ExpressionError::Member(error) => error,
+                            ExpressionError::Expression(message) => {
+                                EvaluationError::PolicyInitialization(message)
+                            }
+                        })?;
                 }
             }
+            // The settings of the steps are validated when the steps are registered
+            PolicyOrPolicyGroupSettings::MutationPipeline { .. } => {}
         }
 
         Ok(())
     }
 
-    /// Internal method, create a `PolicyEvaluator` by using a pre-initialized instance
+    /// Internal method, create a `PolicyEvaluator` by using a pre-initialized instance.
     fn rehydrate(&self, policy_id: &PolicyID) -> Result<PolicyEvaluator> {
         if self.policy_groups.contains(policy_id) {
             return Err(EvaluationError::CannotRehydratePolicyGroup(
@@ -539,12 +1224,13 @@ impl EvaluationEnvironment {
         let ctx_aware_resources_allow_list = self
             .policy_id_to_ctx_aware_allowed_resources
             .get(policy_id)
-            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
+            .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?
+            .clone();
 
         let eval_ctx = EvaluationContext {
             policy_id: policy_id.to_string(),
             callback_channel: self.callback_handler_tx.clone(),
-            ctx_aware_resources_allow_list: ctx_aware_resources_allow_list.clone(),
+            ctx_aware_resources_allow_list,
             epoch_deadline,
         };
 
@@ -553,20 +1239,24 @@ impl EvaluationEnvironment {
         })
     }
 
-    /// Perform a request validation
-    pub fn validate(
-        &self,
-        policy_id: &PolicyID,
-        req: &ValidateRequest,
-    ) -> Result<AdmissionResponse> {
+    /// Perform a request validation. The failures of the policy that rejected the request are
+    /// returned alongside the response, see `PolicyFailure`.
+    pub fn validate(&self, policy_id: &PolicyID, req: &ValidateRequest) -> Result<PolicyResponse> {
         if self.policy_groups.contains(policy_id) {
             self.validate_policy_group(policy_id, req)
+        } else if self.mutation_pipelines.contains(policy_id) {
+            self.validate_mutation_pipeline(policy_id, req)
         } else {
             self.validate_policy(policy_id, req)
         }
     }
 
-    /// Validate a policy.
+    /// Validate a policy. The timeouts and the traps of the WebAssembly module are classified
+    /// on the response of policy-evaluator, see `PolicyResponse::of_module`.
+    ///
+    /// The context-aware policies configured to skip the Kubernetes resources on dry-run
+    /// requests accept these requests without being evaluated, see
+    /// `skips_context_aware_resources`.
     ///
     /// Note, `self` is wrapped inside of `Arc` because this method is called from within a Rhai engine closure that
     /// requires `+send` and `+sync`.
@@ -574,96 +1264,716 @@ impl EvaluationEnvironment {
         &self,
         policy_id: &PolicyID,
         req: &ValidateRequest,
-    ) -> Result<AdmissionResponse> {
+    ) -> Result<PolicyResponse> {
         debug!(?policy_id, "validate individual policy");
 
         if let Some(error) = self.policy_initialization_errors.get(policy_id) {
             return Err(EvaluationError::PolicyInitialization(error.to_string()));
         }
 
+        if is_dry_run(req) && self.skips_context_aware_resources(policy_id) {
+            debug!(
+                ?policy_id,
+                "dry-run request accepted without evaluating the policy"
+            );
+            return Ok(AdmissionResponse {
+                uid: req.uid().to_owned(),
+                allowed: true,
+                ..Default::default()
+            }
+            .into());
+        }
+
         let settings = match self.get_policy_settings(policy_id)?.settings {
             PolicyOrPolicyGroupSettings::Policy(settings) => settings,
             _ => unreachable!(),
         };
         let mut evaluator = self.rehydrate(policy_id)?;
 
-        Ok(evaluator.validate(req.clone(), &settings))
+        Ok(PolicyResponse::of_module(
+            evaluator.validate(req.clone(), &settings),
+        ))
+    }
+
+    /// Returns true when the given policy accesses Kubernetes resources and is configured to
+    /// skip them on dry-run requests
+    fn skips_context_aware_resources(&self, policy_id: &PolicyID) -> bool {
+        self.policy_id_to_settings
+            .get(policy_id)
+            .is_some_and(|settings| settings.skip_context_aware_resources_on_dry_run)
+            && self
+                .policy_id_to_ctx_aware_allowed_resources
+                .get(policy_id)
+                .is_some_and(|allow_list| !allow_list.is_empty())
     }
 
     /// Validate a policy group
     ///
-    /// Note, `self` is wrapped inside of `Arc` because the Rhai engine closure requires
-    /// `+send` and `+sync`.
+    /// The expression of a group is evaluated by `validate_policy_group_expression`. When the
+    /// group rejects the request, the outcome of the members is added to the causes of the
+    /// rejection, see `policy_group_member_causes`. The plain members are reported only when
+    /// they rejected the request.
+    ///
+    /// The members of a group allowed to mutate an admission request receive the object as
+    /// mutated by the previous members, see `validate_mutating_policy_group_member`. When the
+    /// group accepts the request, the returned patch describes the changes made by all the
+    /// members that accepted it. The configuration ensures these are the members whose outcome
+    /// made the expression true, in the order of evaluation, see
+    /// `policy_group_expression::validate_mutating_expression`.
+    ///
+    /// The groups using a score evaluate all their members, see `score_policy_group`.
+    ///
+    /// The groups using `concurrentEvaluation` evaluate all their members before the rule, see
+    /// `validate_policy_group_members_concurrently`. The rule is then resolved against the
+    /// outcome of the members.
+    ///
+    /// The failures of the members are reported by the causes of the rejection, only the
+    /// failure of the expression is returned alongside the response.
     fn validate_policy_group(
         &self,
         policy_id: &PolicyID,
         req: &ValidateRequest,
-    ) -> Result<AdmissionResponse> {
-        let group_evaluator = Arc::new(self.build_policy_group_evaluator(policy_id)?);
-        Ok(group_evaluator.validate(req))
-    }
-
-    fn build_policy_group_evaluator(&self, policy_id: &PolicyID) -> Result<PolicyGroupEvaluator> {
-        let (expression, message, policies) = match self.get_policy_settings(policy_id)?.settings {
+    ) -> Result<PolicyResponse> {
+        let settings = self.get_policy_settings(policy_id)?;
+        let (rule, message, policies, concurrent_evaluation) = match settings.settings {
             PolicyOrPolicyGroupSettings::PolicyGroup {
-                expression,
+                rule,
                 message,
                 policies,
-            } => (expression, message, policies),
+                concurrent_evaluation,
+            } => (rule, message, policies, concurrent_evaluation),
+            _ => unreachable!(),
+        };
+
+        let mut member_responses: Vec<(String, AdmissionResponse)> = if concurrent_evaluation {
+            self.validate_policy_group_members_concurrently(policy_id, req)?
+        } else {
+            Vec::new()
+        };
+
+        let mut mutated_request = match req {
+            ValidateRequest::AdmissionRequest(adm_req) if settings.allowed_to_mutate => {
+                Some(adm_req.as_ref().clone())
+            }
+            _ => None,
+        };
+
+        let mut response = match rule {
+            PolicyGroupRule::Expression(_) => match self.validate_policy_group_expression(
+                policy_id,
+                req,
+                &mut member_responses,
+                &mut mutated_request,
+            ) {
+                Ok(allowed) => expression_policy_group_response(req.uid(), &message, allowed),
+                Err(ExpressionError::Member(error)) => return Err(error),
+                // Like the policies that time out, the expression rejects the request
+                Err(ExpressionError::Expression(message)) => {
+                    return Ok(PolicyResponse {
+                        response: AdmissionResponse::reject(req.uid().to_owned(), message, 500),
+                        failure: Some(PolicyFailure::PolicyGroupExpression),
+                    });
+                }
+            },
+            PolicyGroupRule::Score { min_score, weights } => {
+                let mut members: Vec<String> = policies
+                    .into_iter()
+                    .filter(|name| {
+                        !member_responses
+                            .iter()
+                            .any(|(evaluated_member, _)| evaluated_member == name)
+                    })
+                    .collect();
+                members.sort();
+                for name in members {
+                    let response = match mutated_request.as_mut() {
+                        Some(member_req) => self.validate_mutating_policy_group_member(
+                            policy_id, &name, req, member_req,
+                        )?,
+                        None => {
+                            debug!(?policy_id, name, "validate policy group member");
+                            validate_policy_group_member(policy_id, &name, req, |member_id| {
+                                self.validate_non_mutating_policy_group_member(member_id, req)
+                            })?
+                        }
+                    };
+                    member_responses.push((name, response));
+                }
+
+                score_policy_group(req.uid(), &message, min_score, &weights, &member_responses)
+            }
+        };
+
+        if response.allowed {
+            if let (ValidateRequest::AdmissionRequest(adm_req), Some(mutated_request)) =
+                (req, mutated_request)
+                && let (Some(original), Some(mutated)) = (&adm_req.object, &mutated_request.object)
+                && original != mutated
+            {
+                match patch::encode_diff(original, mutated) {
+                    Ok(composed_patch) => {
+                        response.patch_type = Some(PatchType::JSONPatch);
+                        response.patch = Some(composed_patch);
+                    }
+                    Err(e) => {
+                        return Ok(AdmissionResponse::reject(
+                            adm_req.uid.clone(),
+                            format!("cannot compose the mutations of {policy_id}: {e}"),
+                            500,
+                        )
+                        .into());
+                    }
+                }
+            }
+        } else {
+            let causes = &mut response
+                .status
+                .get_or_insert_with(Default::default)
+                .details
+                .get_or_insert_with(Default::default)
+                .causes;
+            for (name, member_response) in member_responses {
+                causes.extend(policy_group_member_causes(&name, member_response));
+            }
+        }
+
+        Ok(response.into())
+    }
+
+    /// Evaluate the expression of a policy group, compiled when the group has been
+    /// registered. The members are evaluated the first time the expression calls them, the
+    /// members skipped by the short-circuit of the boolean operators are never evaluated. The
+    /// outcome of the members evaluated concurrently is taken from `member_responses`.
+    ///
+    /// When `mutated_request` is set, the members receive the object as mutated by the
+    /// members called before them, and the patches of the members accepting the request are
+    /// applied to it. The expressions of the groups allowed to mutate cannot reach a member
+    /// after an accepting member whose outcome has been discarded.
+    ///
+    /// The responses of the nested groups, of the members of a mutating group and of the
+    /// members rejecting the request are added to `member_responses`.
+    fn validate_policy_group_expression(
+        &self,
+        policy_id: &PolicyID,
+        req: &ValidateRequest,
+        member_responses: &mut Vec<(String, AdmissionResponse)>,
+        mutated_request: &mut Option<AdmissionRequest>,
+    ) -> std::result::Result<bool, ExpressionError> {
+        let policy_group_expression = self
+            .policy_id_to_group_expression
+            .get(policy_id)
+            .ok_or_else(|| self.policy_group_expression_error(policy_id))?;
+        let (allowed, evaluated_members) = resolve_policy_group_expression(
+            policy_group_expression,
+            policy_id,
+            member_responses,
+            |name| match mutated_request.as_mut() {
+                Some(member_req) => {
+                    self.validate_mutating_policy_group_member(policy_id, name, req, member_req)
+                }
+                None => {
+                    debug!(?policy_id, name, "validate policy group member");
+                    validate_policy_group_member(policy_id, name, req, |member_id| {
+                        self.validate_non_mutating_policy_group_member(member_id, req)
+                    })
+                }
+            },
+        )?;
+        member_responses.extend(evaluated_members.into_iter().filter(|(name, response)| {
+            mutated_request.is_some()
+                || !response.allowed
+                || self
+                    .policy_groups
+                    .contains(&policy_group_member_id(policy_id, name))
+        }));
+
+        Ok(allowed)
+    }
+
+    /// Evaluate a member of a policy group that is not allowed to mutate the request. The
+    /// members returning a patch reject the request.
+    fn validate_non_mutating_policy_group_member(
+        &self,
+        member_id: &PolicyID,
+        req: &ValidateRequest,
+    ) -> Result<AdmissionResponse> {
+        if self.policy_groups.contains(member_id) {
+            return Ok(self.validate_policy_group(member_id, req)?.response);
+        }
+        let response = self.validate_policy(member_id, req)?.response;
+        if response.patch.is_some() {
+            return Ok(AdmissionResponse::reject(
+                req.uid().to_owned(),
+                "mutation is not allowed inside of policy group".to_owned(),
+                500,
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Evaluate a member of a policy group that is allowed to mutate the request. The member
+    /// receives `member_req`, the object as mutated by the members evaluated before it. When
+    /// the member accepts the request, its patch is applied to `member_req`.
+    ///
+    /// The nested groups are evaluated against the original request, and never mutate it.
+    fn validate_mutating_policy_group_member(
+        &self,
+        policy_id: &PolicyID,
+        name: &str,
+        req: &ValidateRequest,
+        member_req: &mut AdmissionRequest,
+    ) -> Result<AdmissionResponse> {
+        if self
+            .policy_groups
+            .contains(&policy_group_member_id(policy_id, name))
+        {
+            debug!(?policy_id, name, "validate nested policy group");
+            return validate_policy_group_member(policy_id, name, req, |member_id| {
+                Ok(self.validate_policy_group(member_id, req)?.response)
+            });
+        }
+
+        debug!(?policy_id, name, "validate mutating policy group member");
+        let member_validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(member_req.clone()));
+        validate_policy_group_member(policy_id, name, &member_validate_request, |member_id| {
+            let response = self
+                .validate_policy(member_id, &member_validate_request)?
+                .response;
+            if response.allowed
+                && let Some(member_patch) = &response.patch
+            {
+                let applied = match member_req.object.as_mut() {
+                    Some(object) => patch::apply_patch(object, member_patch),
+                    None => Err(anyhow::anyhow!(
+                        "the request doesn't have an object to patch"
+                    )),
+                };
+                if let Err(e) = applied {
+                    return Ok(AdmissionResponse::reject(
+                        member_req.uid.clone(),
+                        format!("cannot apply the mutation of {name}: {e}"),
+                        500,
+                    ));
+                }
+            }
+            Ok(response)
+        })
+    }
+
+    /// Evaluate all the members of a policy group concurrently, on the policy group workers.
+    /// The nested groups evaluate their own members concurrently only when they use
+    /// `concurrentEvaluation` too, on the same workers. The members returning a patch reject
+    /// the request.
+    ///
+    /// Returns the responses of the members, sorted by name.
+    fn validate_policy_group_members_concurrently(
+        &self,
+        policy_id: &PolicyID,
+        req: &ValidateRequest,
+    ) -> Result<Vec<(String, AdmissionResponse)>> {
+        let policies = match self.get_policy_settings(policy_id)?.settings {
+            PolicyOrPolicyGroupSettings::PolicyGroup { policies, .. } => policies,
+            _ => unreachable!(),
+        };
+        let pool = self.policy_group_pool.as_ref().expect(
+            "the policy group workers are created when a group evaluates its members concurrently",
+        );
+
+        validate_policy_group_members_on(pool, policy_id, policies, req, |member_id| {
+            self.validate_non_mutating_policy_group_member(member_id, req)
+        })
+    }
+
+    /// Returns the names of the members of the policy group that are nested policy groups,
+    /// sorted by name
+    fn nested_policy_groups(&self, policy_id: &PolicyID) -> Result<Vec<String>> {
+        let policies = match self.get_policy_settings(policy_id)?.settings {
+            PolicyOrPolicyGroupSettings::PolicyGroup { policies, .. } => policies,
             _ => unreachable!(),
         };
+        let mut nested_groups: Vec<String> = policies
+            .into_iter()
+            .filter(|name| {
+                self.policy_groups
+                    .contains(&policy_group_member_id(policy_id, name))
+            })
+            .collect();
+        nested_groups.sort();
+
+        Ok(nested_groups)
+    }
+
+    /// Validate a mutation pipeline.
+    ///
+    /// The steps are evaluated in order, each one of them receives the object as mutated
+    /// by the previous steps. The evaluation stops at the first rejection. The returned
+    /// patch describes the changes made by all the steps. The failure of the step that
+    /// rejected the request is returned alongside the response.
+    fn validate_mutation_pipeline(
+        &self,
+        policy_id: &PolicyID,
+        req: &ValidateRequest,
+    ) -> Result<PolicyResponse> {
+        let steps = match self.get_policy_settings(policy_id)?.settings {
+            PolicyOrPolicyGroupSettings::MutationPipeline { steps } => steps,
+            _ => unreachable!(),
+        };
+        let mut step_failure = None;
+        let response =
+            validate_mutation_pipeline_steps(policy_id, &steps, req, |step, step_req| {
+                let step_id = PolicyID::PolicyGroupPolicy {
+                    group: policy_id.to_string(),
+                    name: step.to_owned(),
+                };
+                let step_response = self.validate_policy(&step_id, step_req)?;
+                step_failure = step_response.failure;
+                Ok(step_response.response)
+            })?;
+
+        Ok(PolicyResponse {
+            failure: step_failure.filter(|_| !response.allowed),
+            response,
+        })
+    }
+
+    /// Returns the error describing why the expression of a policy group cannot be
+    /// evaluated
+    fn policy_group_expression_error(&self, policy_id: &PolicyID) -> EvaluationError {
+        match self.policy_initialization_errors.get(policy_id) {
+            Some(error) => EvaluationError::PolicyInitialization(error.to_owned()),
+            None => EvaluationError::PolicyNotFound(policy_id.to_string()),
+        }
+    }
+}
+
+/// Evaluate the steps of a mutation pipeline with `validate_step`, see
+/// `EvaluationEnvironment::validate_mutation_pipeline`.
+fn validate_mutation_pipeline_steps<F>(
+    policy_id: &PolicyID,
+    steps: &[String],
+    req: &ValidateRequest,
+    mut validate_step: F,
+) -> Result<AdmissionResponse>
+where
+    F: FnMut(&str, &ValidateRequest) -> Result<AdmissionResponse>,
+{
+    let ValidateRequest::AdmissionRequest(adm_req) = req else {
+        return Ok(AdmissionResponse::reject(
+            req.uid().to_owned(),
+            format!("mutation pipeline {policy_id} can only evaluate admission requests"),
+            400,
+        ));
+    };
+
+    let mut step_req = adm_req.clone();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut audit_annotations: HashMap<String, String> = HashMap::new();
+
+    for step in steps {
+        debug!(?policy_id, step, "validate mutation pipeline step");
+        let response = validate_step(step, &ValidateRequest::AdmissionRequest(step_req.clone()))?;
 
-        let mut evaluator = PolicyGroupEvaluator::new(
-            &policy_id.to_string(),
-            &message,
-            &expression,
-            self.callback_handler_tx.clone(),
+        warnings.extend(response.warnings.unwrap_or_default());
+        audit_annotations.extend(
+            response
+                .audit_annotations
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, value)| (format!("{step}.{key}"), value)),
         );
 
-        for sub_policy_name in policies {
-            let policy_id = PolicyID::PolicyGroupPolicy {
-                group: policy_id.to_string(),
-                name: sub_policy_name.clone(),
+        if !response.allowed {
+            let mut status = response.status.unwrap_or_default();
+            status.message = Some(format!(
+                "{step}: {}",
+                status.message.as_deref().unwrap_or("rejected")
+            ));
+
+            return Ok(AdmissionResponse {
+                uid: adm_req.uid.clone(),
+                allowed: false,
+                status: Some(status),
+                warnings: (!warnings.is_empty()).then_some(warnings),
+                audit_annotations: (!audit_annotations.is_empty()).then_some(audit_annotations),
+                ..Default::default()
+            });
+        }
+
+        if let Some(step_patch) = response.patch {
+            let applied = match step_req.object.as_mut() {
+                Some(object) => patch::apply_patch(object, &step_patch),
+                None => Err(anyhow::anyhow!(
+                    "the request doesn't have an object to patch"
+                )),
             };
-            let module_digest = self
-                .policy_id_to_module_digest
-                .get(&policy_id)
-                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
+            if let Err(e) = applied {
+                return Ok(AdmissionResponse::reject(
+                    adm_req.uid.clone(),
+                    format!("cannot apply the mutation of step {step}: {e}"),
+                    500,
+                ));
+            }
+        }
+    }
 
-            let policy_evaluator_pre = self
-                .module_digest_to_policy_evaluator_pre
-                .get(module_digest)
-                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
+    let mut admission_response = AdmissionResponse {
+        uid: adm_req.uid.clone(),
+        allowed: true,
+        warnings: (!warnings.is_empty()).then_some(warnings),
+        audit_annotations: (!audit_annotations.is_empty()).then_some(audit_annotations),
+        ..Default::default()
+    };
+    if let (Some(original), Some(mutated)) = (&adm_req.object, &step_req.object)
+        && original != mutated
+    {
+        match patch::encode_diff(original, mutated) {
+            Ok(composed_patch) => {
+                admission_response.patch_type = Some(PatchType::JSONPatch);
+                admission_response.patch = Some(composed_patch);
+            }
+            Err(e) => {
+                return Ok(AdmissionResponse::reject(
+                    adm_req.uid.clone(),
+                    format!("cannot compose the mutations of {policy_id}: {e}"),
+                    500,
+                ));
+            }
+        }
+    }
 
-            let ctx_aware_resources_allow_list = self
-                .policy_id_to_ctx_aware_allowed_resources
-                .get(&policy_id)
-                .ok_or(EvaluationError::PolicyNotFound(policy_id.to_string()))?;
+    Ok(admission_response)
+}
 
-            let policy_settings = self.get_policy_settings(&policy_id)?;
-            let settings = match policy_settings.settings {
-                PolicyOrPolicyGroupSettings::Policy(settings) => settings,
-                _ => unreachable!(),
-            };
+/// Build the response of a policy group using a score, given the responses of its members. The
+/// score is the sum of the weights of the members accepting the request, the request is
+/// accepted when the score reaches `min_score`. The score is reported by an audit annotation,
+/// and by the message of the rejections.
+fn score_policy_group(
+    uid: &str,
+    message: &str,
+    min_score: u64,
+    weights: &HashMap<String, u64>,
+    member_responses: &[(String, AdmissionResponse)],
+) -> AdmissionResponse {
+    // The configuration rejects the weights whose sum overflows
+    let score = member_responses
+        .iter()
+        .filter(|(_, response)| response.allowed)
+        .map(|(name, _)| weights.get(name).copied().unwrap_or(1))
+        .fold(0u64, u64::saturating_add);
+    let max_score = weights.values().copied().fold(0u64, u64::saturating_add);
+    let audit_annotations = HashMap::from([(
+        POLICY_GROUP_SCORE_AUDIT_ANNOTATION.to_owned(),
+        score.to_string(),
+    )]);
+
+    if score >= min_score {
+        AdmissionResponse {
+            uid: uid.to_owned(),
+            allowed: true,
+            audit_annotations: Some(audit_annotations),
+            ..Default::default()
+        }
+    } else {
+        AdmissionResponse {
+            uid: uid.to_owned(),
+            allowed: false,
+            status: Some(AdmissionResponseStatus {
+                message: Some(format!(
+                    "{message} (score {score} out of {max_score}, {min_score} required)"
+                )),
+                ..Default::default()
+            }),
+            audit_annotations: Some(audit_annotations),
+            ..Default::default()
+        }
+    }
+}
 
-            let epoch_deadline = policy_settings
-                .timeout_eval_seconds
-                .or(self.global_policy_evaluation_limit_seconds);
+/// Evaluate a member of a policy group with `validate`, inside of a child span recording
+/// the outcome of the member
+fn validate_policy_group_member<F>(
+    policy_id: &PolicyID,
+    name: &str,
+    req: &ValidateRequest,
+    validate: F,
+) -> Result<AdmissionResponse>
+where
+    F: FnOnce(&PolicyID) -> Result<AdmissionResponse>,
+{
+    let span = info_span!(
+        "policy_group_member",
+        policy_id = policy_id.to_string(),
+        member = name,
+        request_uid = req.uid(),
+        allowed = field::Empty,
+        code = field::Empty,
+    );
+    let _enter = span.enter();
+
+    let response = validate(&policy_group_member_id(policy_id, name))?;
+    span.record("allowed", response.allowed);
+    if let Some(code) = response.status.as_ref().and_then(|status| status.code) {
+        span.record("code", code);
+    }
 
-            let policy_group_member_settings = PolicyGroupMemberSettings {
-                settings,
-                ctx_aware_resources_allow_list: ctx_aware_resources_allow_list.clone(),
-                epoch_deadline,
-            };
+    Ok(response)
+}
 
-            evaluator.add_policy_member(
-                &sub_policy_name,
-                policy_evaluator_pre.clone(),
-                policy_group_member_settings,
-            );
+/// Returns the causes describing the outcome of a member of a policy group that has been
+/// evaluated. The causes of a nested policy group are reported too, below the path of the
+/// nested group.
+fn policy_group_member_causes(name: &str, response: AdmissionResponse) -> Vec<StatusCause> {
+    let field = format!("{POLICY_GROUP_MEMBER_CAUSE_PREFIX}{name}");
+    let reason = if response.allowed {
+        POLICY_GROUP_MEMBER_ALLOWED
+    } else {
+        POLICY_GROUP_MEMBER_DENIED
+    };
+    let (message, nested_causes) = match response.status {
+        Some(status) => (
+            status.message,
+            status
+                .details
+                .map(|details| details.causes)
+                .unwrap_or_default(),
+        ),
+        None => (None, Vec::new()),
+    };
+
+    let mut causes = vec![StatusCause {
+        field: Some(field.clone()),
+        message,
+        reason: Some(reason.to_owned()),
+    }];
+    causes.extend(nested_causes.into_iter().map(|mut cause| {
+        if let Some(nested_field) = cause
+            .field
+            .as_deref()
+            .and_then(|nested_field| nested_field.strip_prefix(POLICY_GROUP_MEMBER_CAUSE_PREFIX))
+        {
+            cause.field = Some(format!("{field}.policies.{nested_field}"));
+        }
+        cause
+    }));
+    causes
+}
+
+/// Resolve the expression of a policy group, see `PolicyGroupExpression::evaluate`. The
+/// outcome of the members that have already been evaluated is taken from `member_responses`,
+/// the other members are evaluated with `validate_member` when the expression calls them.
+///
+/// Returns the outcome of the expression, and the responses of the members evaluated here,
+/// in the order of their first call.
+fn resolve_policy_group_expression<F>(
+    policy_group_expression: &PolicyGroupExpression,
+    policy_id: &PolicyID,
+    member_responses: &[(String, AdmissionResponse)],
+    mut validate_member: F,
+) -> std::result::Result<(bool, Vec<(String, AdmissionResponse)>), ExpressionError>
+where
+    F: FnMut(&str) -> Result<AdmissionResponse>,
+{
+    let mut evaluated_members: Vec<(String, AdmissionResponse)> = Vec::new();
+    let allowed = policy_group_expression.evaluate(policy_id, |name| {
+        if let Some((_, response)) = member_responses
+            .iter()
+            .find(|(evaluated_member, _)| evaluated_member == name)
+        {
+            return Ok(response.allowed);
         }
+        let response = validate_member(name)?;
+        let allowed = response.allowed;
+        evaluated_members.push((name.to_owned(), response));
+        Ok(allowed)
+    })?;
+
+    Ok((allowed, evaluated_members))
+}
+
+/// Evaluate all the members of a policy group concurrently with `validate_member`, on the
+/// given pool, see `EvaluationEnvironment::validate_policy_group_members_concurrently`.
+///
+/// Returns the responses of the members, sorted by name.
+fn validate_policy_group_members_on<F>(
+    pool: &rayon::ThreadPool,
+    policy_id: &PolicyID,
+    policies: Vec<String>,
+    req: &ValidateRequest,
+    validate_member: F,
+) -> Result<Vec<(String, AdmissionResponse)>>
+where
+    F: Fn(&PolicyID) -> Result<AdmissionResponse> + Sync,
+{
+    // The workers do not inherit the span of the evaluation
+    let span = Span::current();
+    let mut member_responses = pool.install(|| {
+        policies
+            .into_par_iter()
+            .map(|name| {
+                let _enter = span.enter();
+                debug!(
+                    ?policy_id,
+                    name, "validate policy group member concurrently"
+                );
+                let response =
+                    validate_policy_group_member(policy_id, &name, req, &validate_member)?;
+                Ok((name, response))
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+    member_responses.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(member_responses)
+}
+
+/// Build the response of a policy group using an expression, given the outcome of the
+/// expression
+fn expression_policy_group_response(uid: &str, message: &str, allowed: bool) -> AdmissionResponse {
+    if allowed {
+        return AdmissionResponse {
+            uid: uid.to_owned(),
+            allowed: true,
+            ..Default::default()
+        };
+    }
+    AdmissionResponse {
+        uid: uid.to_owned(),
+        allowed: false,
+        status: Some(AdmissionResponseStatus {
+            message: Some(message.to_owned()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
 
-        Ok(evaluator)
+/// Returns the ID of a member of a policy group. The members of the nested groups share the
+/// group of the top level policy group, their name is the path to them, like
+/// `nested_group/member`.
+fn policy_group_member_id(group_id: &PolicyID, name: &str) -> PolicyID {
+    match group_id {
+        PolicyID::Policy(group) => PolicyID::PolicyGroupPolicy {
+            group: group.to_owned(),
+            name: name.to_owned(),
+        },
+        PolicyID::PolicyGroupPolicy {
+            group,
+            name: nested_group,
+        } => PolicyID::PolicyGroupPolicy {
+            group: group.to_owned(),
+            name: format!("{nested_group}/{name}"),
+        },
+    }
+}
+
+/// Returns true when the request is a dry-run admission request, like the ones made by
+/// `kubectl --dry-run=server`
+fn is_dry_run(req: &ValidateRequest) -> bool {
+    match req {
+        ValidateRequest::AdmissionRequest(adm_req) => adm_req.dry_run.unwrap_or(false),
+        ValidateRequest::Raw(_) => false,
     }
 }
 
@@ -710,18 +2020,24 @@ fn create_policy_evaluator_pre(
 mod tests {
     use std::collections::BTreeSet;
 
-    use policy_evaluator::{admission_response, policy_evaluator::ValidateRequest};
+    use policy_evaluator::{
+        admission_response, policy_evaluator::ValidateRequest, policy_metadata::Operation,
+    };
     use rstest::*;
     use sha2::{Digest, Sha256};
 
     use super::*;
-    use crate::config::{PolicyGroupMember, PolicyOrPolicyGroup};
+    use crate::config::{
+        CanaryRoutingKey, EnforcementSchedule, EnforcementWindow, MutationPipelineStep,
+        PolicyCanary, PolicyGroupMember, PolicyOrPolicyGroup, RequestFilter,
+    };
     use crate::test_utils::build_admission_review_request;
 
     /// build a precompiled policy of the given wasm module. Assumes this is a OPA Gatekeeper policy
     fn build_precompiled_policy(
         engine: &wasmtime::Engine,
         module_bytes: &[u8],
+        rules: Vec<Rule>,
     ) -> PrecompiledPolicy {
         let module = wasmtime::Module::new(engine, module_bytes)
             .expect("should be able to build the smallest wasm module ever");
@@ -735,6 +2051,17 @@ mod tests {
             precompiled_module: module.serialize().unwrap(),
             execution_mode: policy_evaluator::policy_evaluator::PolicyExecutionMode::OpaGatekeeper,
             digest: format!("{digest:x}"),
+            rules,
+        }
+    }
+
+    fn mutation_pipeline_step(name: &str, module: &str) -> MutationPipelineStep {
+        MutationPipelineStep {
+            name: name.to_string(),
+            module: module.to_string(),
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            timeout_eval_seconds: None,
         }
     }
 
@@ -747,9 +2074,27 @@ mod tests {
 
         let (callback_handler_tx, _) = mpsc::channel(10);
 
-        let precompiled_policy_happy = build_precompiled_policy(&engine, module_bytes_always_happy);
-        let precompiled_policy_unhappy =
-            build_precompiled_policy(&engine, module_bytes_always_unhappy);
+        // the happy policy is interested in the test request, the unhappy one only in pods
+        let precompiled_policy_happy = build_precompiled_policy(
+            &engine,
+            module_bytes_always_happy,
+            vec![Rule {
+                api_groups: vec!["apps".to_string()],
+                api_versions: vec!["v1".to_string()],
+                resources: vec!["deployments/scale".to_string()],
+                operations: vec![Operation::Update],
+            }],
+        );
+        let precompiled_policy_unhappy = build_precompiled_policy(
+            &engine,
+            module_bytes_always_unhappy,
+            vec![Rule {
+                api_groups: vec!["".to_string()],
+                api_versions: vec!["v1".to_string()],
+                resources: vec!["pods".to_string()],
+                operations: vec![Operation::Create],
+            }],
+        );
 
         let test_policies: HashMap<String, PrecompiledPolicy> = vec![
             (
@@ -783,6 +2128,11 @@ mod tests {
                     context_aware_resources: BTreeSet::new(),
                     message: None,
                     timeout_eval_seconds: None,
+                    mutation_deny_paths: Vec::new(),
+                    enforcement_schedule: None,
+                    skip_context_aware_resources_on_dry_run: false,
+                    request_filter: RequestFilter::default(),
+                    canary: None,
                 },
             );
             precompiled_policies.insert(policy_url, Ok(precompiled_policy.clone()));
@@ -799,34 +2149,100 @@ mod tests {
                 context_aware_resources: BTreeSet::new(),
                 message: None,
                 timeout_eval_seconds: Some(5),
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: None,
             },
         );
 
-        // add policy group policies
+        // add context-aware policy skipping the Kubernetes resources on dry-run requests
         policies.insert(
-            "group_policy_valid_expression_with_single_member".to_string(),
-            PolicyOrPolicyGroup::PolicyGroup {
+            "context_aware_policy_skipped_on_dry_run".to_string(),
+            PolicyOrPolicyGroup::Policy {
+                module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
                 policy_mode: PolicyMode::Protect,
-                policies: vec![(
-                    "happy_policy_1".to_string(),
-                    PolicyGroupMember {
-                        module: "file:///tmp/happy_policy_1.wasm".to_string(),
-                        settings: None,
-                        context_aware_resources: BTreeSet::new(),
-                        timeout_eval_seconds: None,
-                    },
-                )]
-                .into_iter()
-                .collect(),
-                expression: "true || happy_policy_1()".to_string(),
-                message: "something went wrong".to_string(),
-            },
+                allowed_to_mutate: None,
+                settings: None,
+                context_aware_resources: BTreeSet::from([ContextAwareResource {
+                    api_version: "v1".to_string(),
+                    kind: "Pod".to_string(),
+                }]),
+                message: None,
+                timeout_eval_seconds: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: true,
+                request_filter: RequestFilter::default(),
+                canary: None,
+            },
+        );
+
+        // add policy with a canary that receives all the requests
+        policies.insert(
+            "policy_with_canary".to_string(),
+            PolicyOrPolicyGroup::Policy {
+                module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                settings: None,
+                context_aware_resources: BTreeSet::new(),
+                message: None,
+                timeout_eval_seconds: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: Some(PolicyCanary {
+                    module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                    settings: None,
+                    weight: 100,
+                    routing_key: CanaryRoutingKey::RequestUid,
+                }),
+            },
+        );
+
+        // add policy group policies
+        policies.insert(
+            "group_policy_valid_expression_with_single_member".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![(
+                    "happy_policy_1".to_string(),
+                    PolicyGroupMember::Policy {
+                        module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                        settings: None,
+                        context_aware_resources: BTreeSet::new(),
+                        timeout_eval_seconds: None,
+                        weight: None,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                expression: Some("true || happy_policy_1()".to_string()),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
         );
         policies.insert(
             "group_policy_valid_expression_just_rhai".to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
-                expression: "2 > 1".to_string(),
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                expression: Some("2 > 1".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
                 policies: HashMap::new(),
             },
@@ -835,18 +2251,26 @@ mod tests {
             "group_policy_not_valid_expression_because_of_unregistered_function".to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
                 policies: vec![(
                     "happy_policy_1".to_string(),
-                    PolicyGroupMember {
+                    PolicyGroupMember::Policy {
                         module: "file:///tmp/happy_policy_1.wasm".to_string(),
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        weight: None,
                     },
                 )]
                 .into_iter()
                 .collect(),
-                expression: "unknown_policy() || happy_policy_1()".to_string(),
+                expression: Some("unknown_policy() || happy_policy_1()".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
             },
         );
@@ -854,7 +2278,14 @@ mod tests {
             "group_policy_not_valid_expression_because_of_typos".to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
-                expression: "something that doesn't make sense".to_string(),
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                expression: Some("something that doesn't make sense".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
                 policies: HashMap::new(),
             },
@@ -863,7 +2294,14 @@ mod tests {
             "group_policy_not_valid_expression_because_of_does_not_return_boolean".to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
-                expression: "1 + 1".to_string(),
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                expression: Some("1 + 1".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
                 policies: HashMap::new(),
             },
@@ -873,18 +2311,26 @@ mod tests {
                 .to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
                 policies: vec![(
                     "happy_policy_1".to_string(),
-                    PolicyGroupMember {
+                    PolicyGroupMember::Policy {
                         module: "file:///tmp/happy_policy_1.wasm".to_string(),
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        weight: None,
                     },
                 )]
                 .into_iter()
                 .collect(),
-                expression: "happy_policy_1() + 1".to_string(),
+                expression: Some("happy_policy_1() + 1".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
             },
         );
@@ -892,39 +2338,298 @@ mod tests {
             "group_policy_with_unhappy_or_bracket_happy_and_unhappy_bracket".to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
                 policies: vec![
                     (
                         "happy_policy_1".to_string(),
-                        PolicyGroupMember {
+                        PolicyGroupMember::Policy {
                             module: "file:///tmp/happy_policy_1.wasm".to_string(),
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            weight: None,
                         },
                     ),
                     (
                         "unhappy_policy_1".to_string(),
-                        PolicyGroupMember {
+                        PolicyGroupMember::Policy {
                             module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            weight: None,
                         },
                     ),
                     (
                         "unhappy_policy_2".to_string(),
-                        PolicyGroupMember {
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: Some(
+                    "unhappy_policy_1() || (happy_policy_1() && unhappy_policy_2())".to_string(),
+                ),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
+        );
+
+        policies.insert(
+            "group_policy_allowed_to_mutate_happy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: Some(true),
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: Some("unhappy_policy_1() || happy_policy_1()".to_string()),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
+        );
+        policies.insert(
+            "group_policy_allowed_to_mutate_unhappy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: Some(true),
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: Some("happy_policy_1() && unhappy_policy_1()".to_string()),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
+        );
+        policies.insert(
+            "group_policy_concurrent_evaluation".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: true,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: Some("unhappy_policy_1() && happy_policy_1()".to_string()),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
+        );
+        policies.insert(
+            "group_policy_min_passing_happy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: None,
+                min_passing: Some(1),
+                min_score: None,
+                message: "not enough policies accepted the request".to_string(),
+            },
+        );
+        policies.insert(
+            "group_policy_min_passing_unhappy".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                    (
+                        "unhappy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
                             module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                expression: None,
+                min_passing: Some(2),
+                min_score: None,
+                message: "not enough policies accepted the request".to_string(),
+            },
+        );
+        policies.insert(
+            "group_policy_with_nested_group".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: vec![
+                    (
+                        "happy_policy_1".to_string(),
+                        PolicyGroupMember::Policy {
+                            module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                            settings: None,
+                            context_aware_resources: BTreeSet::new(),
+                            timeout_eval_seconds: None,
+                            weight: None,
+                        },
+                    ),
+                    (
+                        "unhappy_group".to_string(),
+                        PolicyGroupMember::PolicyGroup {
+                            policies: vec![(
+                                "unhappy_policy_1".to_string(),
+                                PolicyGroupMember::Policy {
+                                    module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
+                                    settings: None,
+                                    context_aware_resources: BTreeSet::new(),
+                                    timeout_eval_seconds: None,
+                                    weight: None,
+                                },
+                            )]
+                            .into_iter()
+                            .collect(),
+                            expression: Some("unhappy_policy_1()".to_string()),
+                            min_passing: None,
+                            min_score: None,
+                            message: "the nested group rejected the request".to_string(),
+                            weight: None,
+                            concurrent_evaluation: false,
                         },
                     ),
                 ]
                 .into_iter()
                 .collect(),
-                expression: "unhappy_policy_1() || (happy_policy_1() && unhappy_policy_2())"
-                    .to_string(),
+                expression: Some("happy_policy_1() && unhappy_group()".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
             },
         );
@@ -933,43 +2638,87 @@ mod tests {
             "group_policy_with_unhappy_or_happy_or_unhappy".to_string(),
             PolicyOrPolicyGroup::PolicyGroup {
                 policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
                 policies: vec![
                     (
                         "happy_policy_1".to_string(),
-                        PolicyGroupMember {
+                        PolicyGroupMember::Policy {
                             module: "file:///tmp/happy_policy_1.wasm".to_string(),
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            weight: None,
                         },
                     ),
                     (
                         "unhappy_policy_1".to_string(),
-                        PolicyGroupMember {
+                        PolicyGroupMember::Policy {
                             module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            weight: None,
                         },
                     ),
                     (
                         "unhappy_policy_2".to_string(),
-                        PolicyGroupMember {
+                        PolicyGroupMember::Policy {
                             module: "file:///tmp/unhappy_policy_1.wasm".to_string(),
                             settings: None,
                             context_aware_resources: BTreeSet::new(),
                             timeout_eval_seconds: None,
+                            weight: None,
                         },
                     ),
                 ]
                 .into_iter()
                 .collect(),
-                expression: "unhappy_policy_1() || happy_policy_1() || unhappy_policy_2()"
-                    .to_string(),
+                expression: Some(
+                    "unhappy_policy_1() || happy_policy_1() || unhappy_policy_2()".to_string(),
+                ),
+                min_passing: None,
+                min_score: None,
                 message: "something went wrong".to_string(),
             },
         );
 
+        // add mutation pipelines
+        policies.insert(
+            "mutation_pipeline_happy".to_string(),
+            PolicyOrPolicyGroup::MutationPipeline {
+                policy_mode: PolicyMode::Protect,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                allowed_to_mutate: Some(true),
+                message: None,
+                steps: vec![
+                    mutation_pipeline_step("first_step", "file:///tmp/happy_policy_1.wasm"),
+                    mutation_pipeline_step("second_step", "file:///tmp/happy_policy_2.wasm"),
+                ],
+            },
+        );
+        policies.insert(
+            "mutation_pipeline_unhappy".to_string(),
+            PolicyOrPolicyGroup::MutationPipeline {
+                policy_mode: PolicyMode::Protect,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                allowed_to_mutate: Some(true),
+                message: None,
+                steps: vec![
+                    mutation_pipeline_step("first_step", "file:///tmp/happy_policy_1.wasm"),
+                    mutation_pipeline_step("unhappy_step", "file:///tmp/unhappy_policy_1.wasm"),
+                    mutation_pipeline_step("last_step", "file:///tmp/happy_policy_2.wasm"),
+                ],
+            },
+        );
+
         let eval_env_builder =
             EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx);
         eval_env_builder
@@ -1031,12 +2780,12 @@ mod tests {
             admission_response::StatusCause {
                 field: Some("spec.policies.unhappy_policy_1".to_string()),
                 message: Some("failing as expected".to_string()),
-                ..Default::default()
+                reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
             },
             admission_response::StatusCause {
                 field: Some("spec.policies.unhappy_policy_2".to_string()),
                 message: Some("failing as expected".to_string()),
-                ..Default::default()
+                reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
             },
         ]
     )]
@@ -1045,6 +2794,22 @@ mod tests {
         true,
         Vec::new(), // no expected causes, since the request is accepted
     )]
+    #[case::nested_group_rejects(
+        "group_policy_with_nested_group",
+        false,
+        vec![
+            admission_response::StatusCause {
+                field: Some("spec.policies.unhappy_group".to_string()),
+                message: Some("the nested group rejected the request".to_string()),
+                reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+            },
+            admission_response::StatusCause {
+                field: Some("spec.policies.unhappy_group.policies.unhappy_policy_1".to_string()),
+                message: Some("failing as expected".to_string()),
+                reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+            },
+        ]
+    )]
     fn group_policy_warning_assignments(
         #[case] policy_id: &str,
         #[case] admission_accepted: bool,
@@ -1069,7 +2834,8 @@ mod tests {
 
         let response = evaluation_environment
             .validate(&policy_id, &validate_request)
-            .expect("should not have errored");
+            .expect("should not have errored")
+            .response;
         assert_eq!(response.allowed, admission_accepted);
         assert_eq!(response.warnings, None);
 
@@ -1108,36 +2874,295 @@ mod tests {
         );
     }
 
-    #[test]
-    fn validate_policy_with_initialization_error() {
-        let mut evaluation_environment = build_evaluation_environment();
-        let policy_id = PolicyID::Policy("policy_3".to_string());
-        evaluation_environment
-            .policy_initialization_errors
-            .insert(policy_id.clone(), "error".to_string());
-        let evaluation_environment = Arc::new(evaluation_environment);
-
+    #[rstest]
+    #[case::policy_without_canary("happy_policy_1", PolicyVariant::Stable, true)]
+    #[case::policy_with_canary(
+        "policy_with_canary",
+        PolicyVariant::Canary(PolicyID::Policy("policy_with_canary/canary".to_string())),
+        false
+    )]
+    fn route_requests_to_canary(
+        #[case] policy_id: &str,
+        #[case] expected_variant: PolicyVariant,
+        #[case] expected_allowed: bool,
+    ) {
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let evaluation_environment = Arc::new(build_evaluation_environment());
         let validate_request =
             ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
-        assert!(matches!(
-            evaluation_environment.validate(&policy_id, &validate_request).unwrap_err(),
-            EvaluationError::PolicyInitialization(error) if error == "error"
-        ));
+
+        let variant = evaluation_environment
+            .select_policy_variant(&policy_id, &validate_request)
+            .expect("should select a variant");
+        assert_eq!(expected_variant, variant);
+
+        let response = evaluation_environment
+            .validate(variant.policy_id(&policy_id), &validate_request)
+            .expect("should not have errored")
+            .response;
+        assert_eq!(expected_allowed, response.allowed);
     }
 
-    #[rstest]
-    #[case::valid_expression_with_single_policy(
-        "group_policy_valid_expression_with_single_member",
-        true
-    )]
-    #[case::valid_expression_with_just_rhai("group_policy_valid_expression_just_rhai", true)]
-    #[case::not_valid_expression_because_of_unregistered_function(
-        "group_policy_not_valid_expression_because_of_unregistered_function",
-        false
-    )]
-    #[case::not_valid_expression_because_of_typos(
-        "group_policy_not_valid_expression_because_of_typos",
-        false
+    #[test]
+    fn unresolved_policy_group_reference_is_a_bootstrap_failure() {
+        let engine = wasmtime::Engine::default();
+        let precompiled_policies = PrecompiledPolicies::new();
+        let policies: HashMap<String, PolicyOrPolicyGroup> = [(
+            "group".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: [(
+                    "referenced".to_string(),
+                    PolicyGroupMember::Reference {
+                        reference: "policy".to_string(),
+                        weight: None,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                expression: Some("referenced()".to_string()),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
+        )]
+        .into_iter()
+        .collect();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+
+        let result =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .build_evaluation_environment(&policies);
+
+        assert!(matches!(
+            result,
+            Err(EvaluationError::BootstrapFailure(message)) if message.contains("has not been resolved")
+        ));
+    }
+
+    #[test]
+    fn nested_policy_group_concurrent_evaluation_creates_the_workers() {
+        let engine = wasmtime::Engine::default();
+        let precompiled_policies = PrecompiledPolicies::new();
+        let policies: HashMap<String, PolicyOrPolicyGroup> = [(
+            "group".to_string(),
+            PolicyOrPolicyGroup::PolicyGroup {
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                policies: [(
+                    "nested".to_string(),
+                    PolicyGroupMember::PolicyGroup {
+                        policies: HashMap::new(),
+                        expression: Some("true".to_string()),
+                        min_passing: None,
+                        min_score: None,
+                        message: "the nested group rejected the request".to_string(),
+                        weight: None,
+                        concurrent_evaluation: true,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+                expression: Some("nested()".to_string()),
+                min_passing: None,
+                min_score: None,
+                message: "something went wrong".to_string(),
+            },
+        )]
+        .into_iter()
+        .collect();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+
+        let evaluation_environment =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .build_evaluation_environment(&policies)
+                .expect("cannot build the evaluation environment");
+
+        assert!(evaluation_environment.policy_group_pool.is_some());
+        let nested_policy_id = PolicyID::PolicyGroupPolicy {
+            group: "group".to_string(),
+            name: "nested".to_string(),
+        };
+        assert!(matches!(
+            evaluation_environment
+                .get_policy_settings(&nested_policy_id)
+                .unwrap()
+                .settings,
+            PolicyOrPolicyGroupSettings::PolicyGroup {
+                concurrent_evaluation: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn canary_initialization_error_keeps_the_stable_policy_settings() {
+        let engine = wasmtime::Engine::default();
+        let precompiled_policy = build_precompiled_policy(
+            &engine,
+            include_bytes!("../../tests/data/gatekeeper_always_happy_policy.wasm"),
+            vec![],
+        );
+        let precompiled_policies: PrecompiledPolicies = [(
+            "file:///tmp/happy_policy_1.wasm".to_string(),
+            Ok(precompiled_policy),
+        )]
+        .into_iter()
+        .collect();
+        let policies: HashMap<String, PolicyOrPolicyGroup> = [(
+            "policy".to_string(),
+            PolicyOrPolicyGroup::Policy {
+                module: "file:///tmp/happy_policy_1.wasm".to_string(),
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                settings: None,
+                context_aware_resources: BTreeSet::new(),
+                message: None,
+                timeout_eval_seconds: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: Some(EnforcementSchedule {
+                    policy_mode: PolicyMode::Monitor,
+                    windows: vec![EnforcementWindow {
+                        start: "0 22 * * *".to_string(),
+                        duration_seconds: 3600,
+                    }],
+                }),
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter {
+                    rules: Some(vec![rule("", "pods", Operation::Create)]),
+                    ..Default::default()
+                },
+                canary: Some(PolicyCanary {
+                    // the module has not been fetched, the canary cannot be bootstrapped
+                    module: "file:///tmp/missing.wasm".to_string(),
+                    settings: None,
+                    weight: 10,
+                    routing_key: CanaryRoutingKey::RequestUid,
+                }),
+            },
+        )]
+        .into_iter()
+        .collect();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+
+        let evaluation_environment =
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .with_continue_on_errors(true)
+                .build_evaluation_environment(&policies)
+                .unwrap();
+
+        let policy_id = PolicyID::Policy("policy".to_string());
+        assert!(
+            evaluation_environment
+                .policy_initialization_errors
+                .contains_key(&PolicyID::Policy("policy/canary".to_string()))
+        );
+        assert!(
+            !evaluation_environment
+                .policy_initialization_errors
+                .contains_key(&policy_id)
+        );
+        assert!(
+            evaluation_environment
+                .policy_id_to_request_matcher
+                .contains_key(&policy_id)
+        );
+        assert!(
+            evaluation_environment
+                .policy_id_to_enforcement_windows
+                .contains_key(&policy_id)
+        );
+        // the test request updates a deployment, it doesn't match the rules of the policy
+        assert!(
+            !evaluation_environment
+                .should_evaluate_request(&policy_id, &build_admission_review_request().request)
+        );
+    }
+
+    #[test]
+    fn validate_policy_with_initialization_error() {
+        let mut evaluation_environment = build_evaluation_environment();
+        let policy_id = PolicyID::Policy("policy_3".to_string());
+        evaluation_environment
+            .policy_initialization_errors
+            .insert(policy_id.clone(), "error".to_string());
+        let evaluation_environment = Arc::new(evaluation_environment);
+
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+        assert!(matches!(
+            evaluation_environment.validate(&policy_id, &validate_request).unwrap_err(),
+            EvaluationError::PolicyInitialization(error) if error == "error"
+        ));
+    }
+
+    #[rstest]
+    #[case::dry_run_skipped("context_aware_policy_skipped_on_dry_run", true, true)]
+    #[case::not_dry_run("context_aware_policy_skipped_on_dry_run", false, false)]
+    #[case::not_skipped("unhappy_policy_1", true, false)]
+    fn validate_dry_run_request_of_context_aware_policy(
+        #[case] policy_id: &str,
+        #[case] dry_run: bool,
+        #[case] expected_allowed: bool,
+    ) {
+        let evaluation_environment = build_evaluation_environment();
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let mut adm_req = build_admission_review_request().request;
+        adm_req.dry_run = Some(dry_run);
+        let validate_request = ValidateRequest::AdmissionRequest(Box::new(adm_req));
+
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .unwrap()
+            .response;
+
+        assert_eq!(expected_allowed, response.allowed);
+        assert_eq!("hello", response.uid);
+    }
+
+    #[test]
+    fn get_policy_initialization_error_of_unregistered_policy() {
+        let mut evaluation_environment = build_evaluation_environment();
+        let registered_policy_id = PolicyID::Policy("policy_3".to_string());
+        let unregistered_policy_id = PolicyID::Policy("invalid_settings".to_string());
+        for policy_id in [&registered_policy_id, &unregistered_policy_id] {
+            evaluation_environment
+                .policy_initialization_errors
+                .insert(policy_id.clone(), "error".to_string());
+        }
+
+        assert_eq!(
+            None,
+            evaluation_environment.get_policy_initialization_error(&registered_policy_id)
+        );
+        assert_eq!(
+            Some("error".to_string()),
+            evaluation_environment.get_policy_initialization_error(&unregistered_policy_id)
+        );
+    }
+
+    #[rstest]
+    #[case::valid_expression_with_single_policy(
+        "group_policy_valid_expression_with_single_member",
+        true
+    )]
+    #[case::valid_expression_with_just_rhai("group_policy_valid_expression_just_rhai", true)]
+    #[case::not_valid_expression_because_of_unregistered_function(
+        "group_policy_not_valid_expression_because_of_unregistered_function",
+        false
+    )]
+    #[case::not_valid_expression_because_of_typos(
+        "group_policy_not_valid_expression_because_of_typos",
+        false
     )]
     #[case::not_valid_expression_because_doing_operations_with_booleans_is_wrong(
         "group_policy_not_valid_expression_because_doing_operations_with_booleans_is_wrong",
@@ -1163,4 +3188,823 @@ mod tests {
 
         assert_eq!(expression_is_valid, validation_result.is_ok());
     }
+
+    #[rstest]
+    #[case::policy_rejection("unhappy_policy_1", None)]
+    #[case::policy_group_expression_failure(
+        "group_policy_not_valid_expression_because_doing_operations_with_booleans_is_wrong",
+        Some(PolicyFailure::PolicyGroupExpression)
+    )]
+    fn validate_reports_the_failure_alongside_the_rejection(
+        #[case] policy_id: &str,
+        #[case] expected_failure: Option<PolicyFailure>,
+    ) {
+        let evaluation_environment = build_evaluation_environment();
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let policy_response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .expect("should not have errored");
+
+        assert!(!policy_response.response.allowed);
+        assert_eq!(expected_failure, policy_response.failure);
+    }
+
+    #[test]
+    fn policies_matching_request() {
+        let evaluation_environment = build_evaluation_environment();
+        let adm_req = build_admission_review_request().request;
+
+        let policy_ids = evaluation_environment.get_policies_matching_request(&adm_req);
+
+        for expected in [
+            "happy_policy_1",
+            "happy_policy_2",
+            "policy_with_canary",
+            "group_policy_valid_expression_with_single_member",
+        ] {
+            assert!(
+                policy_ids.contains(&PolicyID::Policy(expected.to_string())),
+                "{expected} should match the request"
+            );
+        }
+        for not_expected in [
+            "unhappy_policy_1",
+            "policy_with_canary/canary",
+            "group_policy_valid_expression_just_rhai",
+        ] {
+            assert!(
+                !policy_ids.contains(&PolicyID::Policy(not_expected.to_string())),
+                "{not_expected} should not match the request"
+            );
+        }
+        assert!(
+            policy_ids
+                .iter()
+                .all(|policy_id| matches!(policy_id, PolicyID::Policy(_)))
+        );
+        assert!(policy_ids.is_sorted_by_key(|policy_id| policy_id.to_string()));
+    }
+
+    #[test]
+    fn policies_failing_initialization_match_all_requests() {
+        let engine = wasmtime::Engine::default();
+        let precompiled_policies = PrecompiledPolicies::new();
+        let policies: HashMap<String, PolicyOrPolicyGroup> = [(
+            "broken".to_string(),
+            PolicyOrPolicyGroup::Policy {
+                // the module has not been fetched, the policy cannot be bootstrapped
+                module: "file:///tmp/missing.wasm".to_string(),
+                policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                settings: None,
+                context_aware_resources: BTreeSet::new(),
+                message: None,
+                timeout_eval_seconds: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: None,
+            },
+        )]
+        .into_iter()
+        .collect();
+        let (callback_handler_tx, _) = mpsc::channel(10);
+
+        let evaluation_environment = Arc::new(
+            EvaluationEnvironmentBuilder::new(&engine, &precompiled_policies, callback_handler_tx)
+                .with_continue_on_errors(true)
+                .build_evaluation_environment(&policies)
+                .unwrap(),
+        );
+
+        let policy_id = PolicyID::Policy("broken".to_string());
+        let adm_req = build_admission_review_request().request;
+        assert_eq!(
+            vec![policy_id.clone()],
+            evaluation_environment.get_policies_matching_request(&adm_req)
+        );
+        assert_eq!(
+            PolicyMode::Protect,
+            evaluation_environment.get_policy_mode(&policy_id).unwrap()
+        );
+
+        let validate_request = ValidateRequest::AdmissionRequest(Box::new(adm_req));
+        assert_eq!(
+            PolicyVariant::Stable,
+            evaluation_environment
+                .select_policy_variant(&policy_id, &validate_request)
+                .unwrap()
+        );
+        assert!(matches!(
+            evaluation_environment
+                .validate(&policy_id, &validate_request)
+                .unwrap_err(),
+            EvaluationError::PolicyInitialization(_)
+        ));
+    }
+
+    fn rule(api_group: &str, resource: &str, operation: Operation) -> Rule {
+        Rule {
+            api_groups: vec![api_group.to_string()],
+            api_versions: vec!["v1".to_string()],
+            resources: vec![resource.to_string()],
+            operations: vec![operation],
+        }
+    }
+
+    // the test request targets the `scale` subresource of a `apps/v1` deployment
+    #[rstest]
+    #[case::metadata_rules_not_enforced(
+        vec![rule("", "pods", Operation::Create)],
+        RequestFilter::default(),
+        false,
+        true
+    )]
+    #[case::metadata_rules_enforced(
+        vec![rule("", "pods", Operation::Create)],
+        RequestFilter::default(),
+        true,
+        false
+    )]
+    #[case::no_metadata_rules(vec![], RequestFilter::default(), true, true)]
+    #[case::user_rules_replace_metadata_rules(
+        vec![rule("", "pods", Operation::Create)],
+        RequestFilter {
+            rules: Some(vec![rule("apps", "deployments/scale", Operation::Update)]),
+            ..Default::default()
+        },
+        true,
+        true
+    )]
+    #[case::user_rules_always_enforced(
+        vec![rule("apps", "deployments/scale", Operation::Update)],
+        RequestFilter {
+            rules: Some(vec![rule("", "pods", Operation::Create)]),
+            ..Default::default()
+        },
+        false,
+        false
+    )]
+    #[case::object_selector(
+        vec![],
+        RequestFilter {
+            object_selector: Some(
+                serde_json::from_value(serde_json::json!({"matchLabels": {"app": "web"}}))
+                    .unwrap(),
+            ),
+            ..Default::default()
+        },
+        false,
+        false
+    )]
+    fn evaluate_request_matching_filter(
+        #[case] metadata_rules: Vec<Rule>,
+        #[case] request_filter: RequestFilter,
+        #[case] enforce_metadata_rules: bool,
+        #[case] expected: bool,
+    ) {
+        let policy_id = PolicyID::Policy("policy".to_string());
+        let mut evaluation_environment = EvaluationEnvironment::default();
+        evaluation_environment
+            .policy_id_to_rules
+            .insert(policy_id.clone(), metadata_rules);
+
+        evaluation_environment
+            .register_request_filter(&policy_id, &request_filter, enforce_metadata_rules)
+            .unwrap();
+
+        let adm_req = build_admission_review_request().request;
+        assert_eq!(
+            expected,
+            evaluation_environment.should_evaluate_request(&policy_id, &adm_req)
+        );
+    }
+
+    #[rstest]
+    #[case::not_exempted("other", None)]
+    #[case::globally_exempted("kube-system", Some(ExemptionScope::Global))]
+    #[case::exempted_by_the_policy("ci-1234", Some(ExemptionScope::Policy))]
+    fn request_exemptions(#[case] namespace: &str, #[case] expected: Option<ExemptionScope>) {
+        let policy_id = PolicyID::Policy("policy".to_string());
+        let mut evaluation_environment = EvaluationEnvironment {
+            exemptions: Some(
+                ExemptionMatcher::new(
+                    &Exemptions {
+                        namespaces: vec!["kube-system".to_string()],
+                        ..Default::default()
+                    },
+                    ExemptionScope::Global,
+                )
+                .unwrap(),
+            ),
+            ..Default::default()
+        };
+        evaluation_environment
+            .register_request_filter(
+                &policy_id,
+                &RequestFilter {
+                    exemptions: Exemptions {
+                        namespaces: vec!["ci-.*".to_string()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+
+        let mut adm_req = build_admission_review_request().request;
+        adm_req.namespace = Some(namespace.to_string());
+        let exemption = evaluation_environment.get_request_exemption(&policy_id, &adm_req);
+
+        assert_eq!(expected, exemption.map(|exemption| exemption.scope));
+        // the exemptions of a policy do not apply to the other ones
+        assert!(
+            evaluation_environment
+                .get_request_exemption(&PolicyID::Policy("other".to_string()), &adm_req)
+                .is_none_or(|exemption| exemption.scope == ExemptionScope::Global)
+        );
+    }
+
+    #[rstest]
+    #[case::all_steps_accept("mutation_pipeline_happy", true)]
+    #[case::one_step_rejects("mutation_pipeline_unhappy", false)]
+    fn validate_mutation_pipeline(#[case] policy_id: &str, #[case] expected_allowed: bool) {
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let evaluation_environment = Arc::new(build_evaluation_environment());
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .expect("should not have errored")
+            .response;
+
+        assert_eq!(expected_allowed, response.allowed);
+        // the gatekeeper policies used by the tests never mutate the request
+        assert!(response.patch.is_none());
+        if !expected_allowed {
+            let message = response
+                .status
+                .expect("should have status")
+                .message
+                .expect("should have message");
+            assert!(message.starts_with("unhappy_step: "), "{message}");
+        }
+    }
+
+    fn encode_patch(patch: serde_json::Value) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(&patch).unwrap())
+    }
+
+    fn mutating_step_response(patch: serde_json::Value, warning: &str) -> AdmissionResponse {
+        AdmissionResponse {
+            allowed: true,
+            patch_type: Some(PatchType::JSONPatch),
+            patch: Some(encode_patch(patch)),
+            warnings: Some(vec![warning.to_string()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_mutation_pipeline_steps_chain_the_mutations() {
+        let policy_id = PolicyID::Policy("pipeline".to_string());
+        let steps = vec!["add-metadata".to_string(), "add-labels".to_string()];
+        let request = build_admission_review_request().request;
+        let original_object = request.object.clone().unwrap();
+        let mut evaluated_objects = Vec::new();
+
+        let response = validate_mutation_pipeline_steps(
+            &policy_id,
+            &steps,
+            &ValidateRequest::AdmissionRequest(Box::new(request)),
+            |step, step_req| {
+                let ValidateRequest::AdmissionRequest(step_req) = step_req else {
+                    panic!("steps should receive admission requests");
+                };
+                evaluated_objects.push(step_req.object.clone().unwrap());
+                let response = match step {
+                    "add-metadata" => mutating_step_response(
+                        serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}]),
+                        "metadata added",
+                    ),
+                    _ => mutating_step_response(
+                        serde_json::json!([{"op": "add", "path": "/metadata/labels", "value": {"app": "web"}}]),
+                        "labels added",
+                    ),
+                };
+                Ok(AdmissionResponse {
+                    audit_annotations: Some(HashMap::from([(
+                        "mutated-by".to_string(),
+                        step.to_string(),
+                    )])),
+                    ..response
+                })
+            },
+        )
+        .expect("should not have errored");
+
+        // the second step receives the object mutated by the first one
+        assert_eq!(
+            vec![
+                original_object.clone(),
+                serde_json::json!({"apiVersion": "autoscaling/v1", "kind": "Scale", "metadata": {}}),
+            ],
+            evaluated_objects
+        );
+        assert!(response.allowed);
+        assert_eq!(
+            Some(vec![
+                "metadata added".to_string(),
+                "labels added".to_string()
+            ]),
+            response.warnings
+        );
+        // the steps report the same audit annotation, it is kept for each one of them
+        assert_eq!(
+            Some(HashMap::from([
+                (
+                    "add-metadata.mutated-by".to_string(),
+                    "add-metadata".to_string()
+                ),
+                (
+                    "add-labels.mutated-by".to_string(),
+                    "add-labels".to_string()
+                ),
+            ])),
+            response.audit_annotations
+        );
+        assert_eq!(Some(PatchType::JSONPatch), response.patch_type);
+        let mut patched_object = original_object;
+        patch::apply_patch(&mut patched_object, &response.patch.unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "apiVersion": "autoscaling/v1",
+                "kind": "Scale",
+                "metadata": {"labels": {"app": "web"}}
+            }),
+            patched_object
+        );
+    }
+
+    #[rstest]
+    #[case::step_rejects(
+        AdmissionResponse::reject("".to_string(), "boom".to_string(), 400),
+        true,
+        400,
+        "first: boom"
+    )]
+    #[case::patch_cannot_be_applied(
+        mutating_step_response(serde_json::json!([{"op": "remove", "path": "/spec"}]), "mutated"),
+        true,
+        500,
+        "cannot apply the mutation of step first"
+    )]
+    #[case::request_without_object(
+        mutating_step_response(serde_json::json!([{"op": "add", "path": "/metadata", "value": {}}]), "mutated"),
+        false,
+        500,
+        "the request doesn't have an object to patch"
+    )]
+    fn validate_mutation_pipeline_steps_stops_at_the_first_failure(
+        #[case] first_step_response: AdmissionResponse,
+        #[case] with_object: bool,
+        #[case] expected_code: u16,
+        #[case] expected_message: &str,
+    ) {
+        let policy_id = PolicyID::Policy("pipeline".to_string());
+        let steps = vec!["first".to_string(), "second".to_string()];
+        let mut request = build_admission_review_request().request;
+        if !with_object {
+            request.object = None;
+        }
+        let mut evaluated_steps = Vec::new();
+
+        let response = validate_mutation_pipeline_steps(
+            &policy_id,
+            &steps,
+            &ValidateRequest::AdmissionRequest(Box::new(request)),
+            |step, _step_req| {
+                evaluated_steps.push(step.to_string());
+                Ok(first_step_response.clone())
+            },
+        )
+        .expect("should not have errored");
+
+        assert_eq!(vec!["first".to_string()], evaluated_steps);
+        assert!(!response.allowed);
+        assert!(response.patch.is_none());
+        let status = response.status.expect("should have status");
+        assert_eq!(Some(expected_code), status.code);
+        let message = status.message.expect("should have message");
+        assert!(message.contains(expected_message), "{message}");
+    }
+
+    #[test]
+    fn validate_mutation_pipeline_steps_rejects_raw_requests() {
+        let policy_id = PolicyID::Policy("pipeline".to_string());
+        let steps = vec!["first".to_string()];
+
+        let response = validate_mutation_pipeline_steps(
+            &policy_id,
+            &steps,
+            &ValidateRequest::Raw(serde_json::json!({"uid": "raw"})),
+            |_step, _step_req| panic!("the steps should not be evaluated"),
+        )
+        .expect("should not have errored");
+
+        assert!(!response.allowed);
+        assert_eq!("raw", response.uid);
+        let status = response.status.expect("should have status");
+        assert_eq!(Some(400), status.code);
+        assert_eq!(
+            Some("mutation pipeline pipeline can only evaluate admission requests".to_string()),
+            status.message
+        );
+    }
+
+    #[test]
+    fn validate_mutation_pipeline_steps_propagates_the_evaluation_errors() {
+        let policy_id = PolicyID::Policy("pipeline".to_string());
+        let steps = vec!["first".to_string()];
+
+        let result = validate_mutation_pipeline_steps(
+            &policy_id,
+            &steps,
+            &ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request)),
+            |step, _step_req| Err(EvaluationError::PolicyNotFound(step.to_string())),
+        );
+
+        assert!(matches!(result, Err(EvaluationError::PolicyNotFound(_))));
+    }
+
+    #[rstest]
+    #[case::members_accept("group_policy_allowed_to_mutate_happy", true)]
+    #[case::member_rejects("group_policy_allowed_to_mutate_unhappy", false)]
+    fn validate_policy_group_allowed_to_mutate(
+        #[case] policy_id: &str,
+        #[case] expected_allowed: bool,
+    ) {
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let evaluation_environment = Arc::new(build_evaluation_environment());
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        assert!(
+            evaluation_environment
+                .get_policy_allowed_to_mutate(&policy_id)
+                .unwrap()
+        );
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .expect("should not have errored")
+            .response;
+
+        assert_eq!(expected_allowed, response.allowed);
+        // the gatekeeper policies used by the tests never mutate the request
+        assert!(response.patch.is_none());
+        if !expected_allowed {
+            let causes = response
+                .status
+                .expect("should have status")
+                .details
+                .expect("should have details")
+                .causes;
+            assert_eq!(
+                vec![
+                    admission_response::StatusCause {
+                        field: Some("spec.policies.happy_policy_1".to_string()),
+                        reason: Some(POLICY_GROUP_MEMBER_ALLOWED.to_string()),
+                        ..Default::default()
+                    },
+                    admission_response::StatusCause {
+                        field: Some("spec.policies.unhappy_policy_1".to_string()),
+                        message: Some("failing as expected".to_string()),
+                        reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+                    },
+                ],
+                causes
+            );
+        }
+    }
+
+    #[test]
+    fn validate_policy_group_concurrently() {
+        let policy_id = PolicyID::Policy("group_policy_concurrent_evaluation".to_string());
+        let evaluation_environment = Arc::new(build_evaluation_environment());
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .expect("should not have errored")
+            .response;
+
+        assert!(!response.allowed);
+        // the expression does not need the outcome of happy_policy_1, which is evaluated anyway
+        assert_eq!(
+            vec![
+                admission_response::StatusCause {
+                    field: Some("spec.policies.happy_policy_1".to_string()),
+                    reason: Some(POLICY_GROUP_MEMBER_ALLOWED.to_string()),
+                    ..Default::default()
+                },
+                admission_response::StatusCause {
+                    field: Some("spec.policies.unhappy_policy_1".to_string()),
+                    message: Some("failing as expected".to_string()),
+                    reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+                },
+            ],
+            response
+                .status
+                .expect("should have status")
+                .details
+                .expect("should have details")
+                .causes
+        );
+    }
+
+    #[rstest]
+    #[case::enough_members_accept("group_policy_min_passing_happy", true)]
+    #[case::not_enough_members_accept("group_policy_min_passing_unhappy", false)]
+    fn validate_policy_group_with_min_passing(
+        #[case] policy_id: &str,
+        #[case] expected_allowed: bool,
+    ) {
+        let policy_id = PolicyID::Policy(policy_id.to_string());
+        let evaluation_environment = Arc::new(build_evaluation_environment());
+        let validate_request =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+
+        let response = evaluation_environment
+            .validate(&policy_id, &validate_request)
+            .expect("should not have errored")
+            .response;
+
+        assert_eq!(expected_allowed, response.allowed);
+        assert_eq!(
+            Some("1"),
+            response
+                .audit_annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(POLICY_GROUP_SCORE_AUDIT_ANNOTATION))
+                .map(String::as_str)
+        );
+        if !expected_allowed {
+            let status = response.status.expect("should have status");
+            assert_eq!(
+                Some(
+                    "not enough policies accepted the request (score 1 out of 2, 2 required)"
+                        .to_string()
+                ),
+                status.message
+            );
+            assert_eq!(
+                vec![
+                    admission_response::StatusCause {
+                        field: Some("spec.policies.happy_policy_1".to_string()),
+                        reason: Some(POLICY_GROUP_MEMBER_ALLOWED.to_string()),
+                        ..Default::default()
+                    },
+                    admission_response::StatusCause {
+                        field: Some("spec.policies.unhappy_policy_1".to_string()),
+                        message: Some("failing as expected".to_string()),
+                        reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+                    },
+                ],
+                status.details.expect("should have details").causes
+            );
+        }
+    }
+
+    #[test]
+    fn causes_of_nested_policy_group() {
+        let mut response = AdmissionResponse::reject(
+            "uid".to_owned(),
+            "the nested group rejected the request".to_owned(),
+            400,
+        );
+        response
+            .status
+            .as_mut()
+            .unwrap()
+            .details
+            .get_or_insert_with(Default::default)
+            .causes
+            .push(admission_response::StatusCause {
+                field: Some("spec.policies.unhappy_policy_1".to_string()),
+                message: Some("failing as expected".to_string()),
+                reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+            });
+
+        assert_eq!(
+            vec![
+                admission_response::StatusCause {
+                    field: Some("spec.policies.nested".to_string()),
+                    message: Some("the nested group rejected the request".to_string()),
+                    reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+                },
+                admission_response::StatusCause {
+                    field: Some("spec.policies.nested.policies.unhappy_policy_1".to_string()),
+                    message: Some("failing as expected".to_string()),
+                    reason: Some(POLICY_GROUP_MEMBER_DENIED.to_string()),
+                },
+            ],
+            policy_group_member_causes("nested", response)
+        );
+    }
+
+    #[rstest]
+    #[case::min_score_reached(3, true, "3")]
+    #[case::min_score_not_reached(4, false, "3")]
+    fn score_policy_group_members(
+        #[case] min_score: u64,
+        #[case] expected_allowed: bool,
+        #[case] expected_score: &str,
+    ) {
+        let weights = HashMap::from([
+            ("slsa".to_string(), 2),
+            ("sbom".to_string(), 1),
+            ("signature".to_string(), 2),
+        ]);
+        let member_responses = vec![
+            (
+                "sbom".to_string(),
+                AdmissionResponse {
+                    uid: "uid".to_string(),
+                    allowed: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "signature".to_string(),
+                AdmissionResponse::reject("uid".to_string(), "not signed".to_string(), 400),
+            ),
+            (
+                "slsa".to_string(),
+                AdmissionResponse {
+                    uid: "uid".to_string(),
+                    allowed: true,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        let response = score_policy_group(
+            "uid",
+            "not enough attestations",
+            min_score,
+            &weights,
+            &member_responses,
+        );
+
+        assert_eq!(expected_allowed, response.allowed);
+        assert_eq!(
+            Some(expected_score),
+            response
+                .audit_annotations
+                .as_ref()
+                .and_then(|annotations| annotations.get(POLICY_GROUP_SCORE_AUDIT_ANNOTATION))
+                .map(String::as_str)
+        );
+        if !expected_allowed {
+            assert_eq!(
+                Some("not enough attestations (score 3 out of 5, 4 required)".to_string()),
+                response.status.and_then(|status| status.message)
+            );
+        }
+    }
+
+    /// Evaluate a fake member of a policy group: the members accept or reject the request
+    /// according to their name, `trapped` fails like a policy hitting a trap and `broken`
+    /// cannot be evaluated
+    fn validate_fake_policy_group_member(
+        member_id: &PolicyID,
+        req: &ValidateRequest,
+    ) -> Result<AdmissionResponse> {
+        let PolicyID::PolicyGroupPolicy { name, .. } = member_id else {
+            unreachable!("the members of a policy group have a group")
+        };
+        match name.as_str() {
+            "happy" => Ok(AdmissionResponse {
+                uid: req.uid().to_owned(),
+                allowed: true,
+                ..Default::default()
+            }),
+            "unhappy" => Ok(AdmissionResponse::reject(
+                req.uid().to_owned(),
+                "failing as expected".to_owned(),
+                400,
+            )),
+            "trapped" => Ok(AdmissionResponse::reject(
+                req.uid().to_owned(),
+                "internal server error".to_owned(),
+                500,
+            )),
+            _ => Err(EvaluationError::WebAssemblyError(format!(
+                "cannot evaluate {member_id}"
+            ))),
+        }
+    }
+
+    #[rstest]
+    #[case::members_accept("happy()", &["happy", "unhappy", "trapped"], Some(true))]
+    #[case::member_rejects("happy() && unhappy()", &["happy", "unhappy", "trapped"], Some(false))]
+    #[case::member_fails("trapped() || happy()", &["happy", "unhappy", "trapped"], Some(true))]
+    #[case::short_circuit("unhappy() && trapped()", &["happy", "unhappy", "trapped"], Some(false))]
+    #[case::negation("!unhappy() && !trapped()", &["happy", "unhappy", "trapped"], Some(true))]
+    #[case::member_errors("happy() && broken()", &["happy", "unhappy", "broken"], None)]
+    fn concurrent_evaluation_of_policy_group_members_matches_the_sequential_one(
+        #[case] expression: &str,
+        #[case] members: &[&str],
+        #[case] expected_allowed: Option<bool>,
+    ) {
+        let policy_id = PolicyID::Policy("group".to_string());
+        let policies: Vec<String> = members.iter().map(ToString::to_string).collect();
+        let policy_group_expression = PolicyGroupExpression::new(expression, &policies)
+            .expect("the expression should compile");
+        let req =
+            ValidateRequest::AdmissionRequest(Box::new(build_admission_review_request().request));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .expect("cannot create the policy group workers");
+
+        let sequential =
+            resolve_policy_group_expression(&policy_group_expression, &policy_id, &[], |name| {
+                validate_fake_policy_group_member(&policy_group_member_id(&policy_id, name), &req)
+            });
+        let concurrent = validate_policy_group_members_on(
+            &pool,
+            &policy_id,
+            policies.clone(),
+            &req,
+            |member_id| validate_fake_policy_group_member(member_id, &req),
+        )
+        .map_err(ExpressionError::from)
+        .and_then(|member_responses| {
+            let (allowed, _) = resolve_policy_group_expression(
+                &policy_group_expression,
+                &policy_id,
+                &member_responses,
+                |name| panic!("{name} should have been evaluated concurrently"),
+            )?;
+            Ok((allowed, member_responses))
+        });
+
+        match (sequential, concurrent) {
+            (Ok((sequential_allowed, evaluated_members)), Ok((allowed, member_responses))) => {
+                assert_eq!(expected_allowed, Some(sequential_allowed));
+                assert_eq!(sequential_allowed, allowed);
+                for (name, response) in evaluated_members {
+                    let (_, concurrent_response) = member_responses
+                        .iter()
+                        .find(|(member, _)| *member == name)
+                        .expect("all the members are evaluated concurrently");
+                    assert_eq!(
+                        response.status.as_ref().and_then(|status| status.code),
+                        concurrent_response
+                            .status
+                            .as_ref()
+                            .and_then(|status| status.code)
+                    );
+                    assert_eq!(
+                        policy_group_member_causes(&name, response),
+                        policy_group_member_causes(&name, concurrent_response.clone())
+                    );
+                }
+            }
+            (Err(sequential_error), Err(error)) => {
+                assert_eq!(None, expected_allowed);
+                assert_eq!(sequential_error.to_string(), error.to_string());
+            }
+            (sequential, concurrent) => {
+                panic!("the evaluations differ: {sequential:?} and {concurrent:?}")
+            }
+        }
+    }
+
+    #[test]
+    fn nested_policy_group_member_id() {
+        let group_id = PolicyID::Policy("group".to_string());
+        let nested_group_id = policy_group_member_id(&group_id, "nested");
+        let member_id = policy_group_member_id(&nested_group_id, "member");
+
+        assert_eq!("group/nested", nested_group_id.to_string());
+        assert_eq!(
+            PolicyID::PolicyGroupPolicy {
+                group: "group".to_string(),
+                name: "nested/member".to_string(),
+            },
+            member_id
+        );
+        assert_eq!("group/nested/member", member_id.to_string());
+    }
 }
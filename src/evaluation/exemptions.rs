@@ -0,0 +1,255 @@
+use std::fmt;
+
+use anyhow::{Result, anyhow};
+use policy_evaluator::admission_request::AdmissionRequest;
+use regex::Regex;
+
+use crate::config::Exemptions;
+
+const SERVICE_ACCOUNT_USERNAME_PREFIX: &str = "system:serviceaccount:";
+
+/// Where an exemption has been defined
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExemptionScope {
+    /// The exemption applies to all the policies
+    Global,
+    /// The exemption applies only to the policy being evaluated
+    Policy,
+}
+
+impl fmt::Display for ExemptionScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExemptionScope::Global => write!(f, "global"),
+            ExemptionScope::Policy => write!(f, "policy"),
+        }
+    }
+}
+
+/// The attribute of the request that matched an exemption
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExemptionReason {
+    Namespace,
+    Username,
+    Group,
+    ServiceAccount,
+}
+
+impl fmt::Display for ExemptionReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExemptionReason::Namespace => write!(f, "namespace"),
+            ExemptionReason::Username => write!(f, "username"),
+            ExemptionReason::Group => write!(f, "group"),
+            ExemptionReason::ServiceAccount => write!(f, "service_account"),
+        }
+    }
+}
+
+/// Describes why a request is accepted without evaluating the policy
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Exemption {
+    pub(crate) scope: ExemptionScope,
+    pub(crate) reason: ExemptionReason,
+    /// The value of the request attribute that matched the exemption
+    pub(crate) value: String,
+}
+
+impl fmt::Display for Exemption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scope = match self.scope {
+            ExemptionScope::Global => "globally",
+            ExemptionScope::Policy => "by the policy",
+        };
+        write!(f, "{} '{}' is exempted {}", self.reason, self.value, scope)
+    }
+}
+
+/// The compiled version of the `Exemptions` defined by the user. All the patterns are
+/// regular expressions that must match the whole value.
+#[derive(Clone, Debug)]
+pub(crate) struct ExemptionMatcher {
+    scope: ExemptionScope,
+    namespaces: Vec<Regex>,
+    usernames: Vec<Regex>,
+    groups: Vec<Regex>,
+    service_accounts: Vec<Regex>,
+}
+
+impl ExemptionMatcher {
+    pub(crate) fn new(exemptions: &Exemptions, scope: ExemptionScope) -> Result<Self> {
+        Ok(ExemptionMatcher {
+            scope,
+            namespaces: compile_patterns(&exemptions.namespaces)?,
+            usernames: compile_patterns(&exemptions.usernames)?,
+            groups: compile_patterns(&exemptions.groups)?,
+            service_accounts: compile_patterns(&exemptions.service_accounts)?,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.namespaces.is_empty()
+            && self.usernames.is_empty()
+            && self.groups.is_empty()
+            && self.service_accounts.is_empty()
+    }
+
+    /// Return the first exemption matched by the request, if any. Service accounts are
+    /// identified as `<namespace>/<name>`.
+    pub(crate) fn exemption(&self, adm_req: &AdmissionRequest) -> Option<Exemption> {
+        let username = adm_req.user_info.username.as_deref();
+        let service_account = username
+            .and_then(|username| username.strip_prefix(SERVICE_ACCOUNT_USERNAME_PREFIX))
+            .and_then(|service_account| service_account.split_once(':'))
+            .map(|(namespace, name)| format!("{namespace}/{name}"));
+
+        let candidates = adm_req
+            .namespace
+            .iter()
+            .map(|namespace| (ExemptionReason::Namespace, namespace.as_str()))
+            .chain(username.map(|username| (ExemptionReason::Username, username)))
+            .chain(
+                adm_req
+                    .user_info
+                    .groups
+                    .iter()
+                    .flatten()
+                    .map(|group| (ExemptionReason::Group, group.as_str())),
+            )
+            .chain(
+                service_account
+                    .as_deref()
+                    .map(|service_account| (ExemptionReason::ServiceAccount, service_account)),
+            );
+
+        for (reason, value) in candidates {
+            let patterns = match reason {
+                ExemptionReason::Namespace => &self.namespaces,
+                ExemptionReason::Username => &self.usernames,
+                ExemptionReason::Group => &self.groups,
+                ExemptionReason::ServiceAccount => &self.service_accounts,
+            };
+            if patterns.iter().any(|pattern| pattern.is_match(value)) {
+                return Some(Exemption {
+                    scope: self.scope,
+                    reason,
+                    value: value.to_owned(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(&format!("^(?:{pattern})$"))
+                .map_err(|e| anyhow!("invalid exemption pattern '{}': {}", pattern, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+
+    fn admission_request(namespace: &str, username: &str, groups: &[&str]) -> AdmissionRequest {
+        let mut adm_req = build_admission_review_request().request;
+        adm_req.namespace = Some(namespace.to_owned());
+        adm_req.user_info.username = Some(username.to_owned());
+        adm_req.user_info.groups = Some(groups.iter().map(|g| g.to_string()).collect());
+        adm_req
+    }
+
+    #[rstest]
+    #[case::no_exemptions(Exemptions::default(), None)]
+    #[case::namespace(
+        Exemptions {
+            namespaces: vec!["kube-system".to_owned()],
+            ..Default::default()
+        },
+        Some((ExemptionReason::Namespace, "kube-system"))
+    )]
+    #[case::namespace_regex(
+        Exemptions {
+            namespaces: vec!["kube-.*".to_owned()],
+            ..Default::default()
+        },
+        Some((ExemptionReason::Namespace, "kube-system"))
+    )]
+    #[case::partial_match_is_not_enough(
+        Exemptions {
+            namespaces: vec!["kube".to_owned()],
+            ..Default::default()
+        },
+        None
+    )]
+    #[case::username(
+        Exemptions {
+            usernames: vec!["system:serviceaccount:ci:.*".to_owned()],
+            ..Default::default()
+        },
+        Some((ExemptionReason::Username, "system:serviceaccount:ci:deployer"))
+    )]
+    #[case::group(
+        Exemptions {
+            groups: vec!["system:masters".to_owned()],
+            ..Default::default()
+        },
+        Some((ExemptionReason::Group, "system:masters"))
+    )]
+    #[case::service_account(
+        Exemptions {
+            service_accounts: vec!["ci/.*".to_owned()],
+            ..Default::default()
+        },
+        Some((ExemptionReason::ServiceAccount, "ci/deployer"))
+    )]
+    fn match_exemptions(
+        #[case] exemptions: Exemptions,
+        #[case] expected: Option<(ExemptionReason, &str)>,
+    ) {
+        let exemption_matcher = ExemptionMatcher::new(&exemptions, ExemptionScope::Global).unwrap();
+        let adm_req = admission_request(
+            "kube-system",
+            "system:serviceaccount:ci:deployer",
+            &["system:serviceaccounts", "system:masters"],
+        );
+
+        let expected = expected.map(|(reason, value)| Exemption {
+            scope: ExemptionScope::Global,
+            reason,
+            value: value.to_owned(),
+        });
+        assert_eq!(expected, exemption_matcher.exemption(&adm_req));
+    }
+
+    #[test]
+    fn describe_exemption() {
+        let exemption = Exemption {
+            scope: ExemptionScope::Policy,
+            reason: ExemptionReason::ServiceAccount,
+            value: "ci/deployer".to_owned(),
+        };
+
+        assert_eq!(
+            "service_account 'ci/deployer' is exempted by the policy",
+            exemption.to_string()
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let exemptions = Exemptions {
+            groups: vec!["system:(".to_owned()],
+            ..Default::default()
+        };
+
+        assert!(ExemptionMatcher::new(&exemptions, ExemptionScope::Policy).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{Result, anyhow};
+use policy_evaluator::{admission_response::AdmissionResponse, policy_evaluator::ValidateRequest};
+
+const PLACEHOLDER_START: &str = "{{";
+const PLACEHOLDER_END: &str = "}}";
+
+/// The request and response fields that can be interpolated inside of a message
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TemplateVariable {
+    Kind,
+    Namespace,
+    Name,
+    Operation,
+    User,
+    PolicyMessage,
+    PolicyCode,
+}
+
+impl TemplateVariable {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "kind" => Some(TemplateVariable::Kind),
+            "namespace" => Some(TemplateVariable::Namespace),
+            "name" => Some(TemplateVariable::Name),
+            "operation" => Some(TemplateVariable::Operation),
+            "user" => Some(TemplateVariable::User),
+            "policyMessage" => Some(TemplateVariable::PolicyMessage),
+            "policyCode" => Some(TemplateVariable::PolicyCode),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Variable(TemplateVariable),
+}
+
+/// The custom rejection message of a policy. The message can interpolate the fields of the
+/// request and the rejection returned by the policy, using placeholders like
+/// `{{ .name }}`. Messages without placeholders are returned as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MessageTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+impl MessageTemplate {
+    pub(crate) fn new(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_owned()));
+            }
+            let placeholder = &rest[start + PLACEHOLDER_START.len()..];
+            let end = placeholder.find(PLACEHOLDER_END).ok_or_else(|| {
+                anyhow!(
+                    "invalid message template '{}': unclosed placeholder",
+                    template
+                )
+            })?;
+            let name = placeholder[..end].trim();
+            let variable = name
+                .strip_prefix('.')
+                .and_then(TemplateVariable::parse)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "invalid message template '{}': unknown placeholder '{}'",
+                        template,
+                        name
+                    )
+                })?;
+            parts.push(TemplatePart::Variable(variable));
+            rest = &placeholder[end + PLACEHOLDER_END.len()..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_owned()));
+        }
+
+        Ok(MessageTemplate { parts })
+    }
+
+    /// Build the message of the given rejection. The fields that are not available, like the
+    /// ones of a raw request, are replaced by an empty string.
+    pub(crate) fn render(
+        &self,
+        validate_request: &ValidateRequest,
+        admission_response: &AdmissionResponse,
+    ) -> String {
+        let adm_req = match validate_request {
+            ValidateRequest::AdmissionRequest(adm_req) => Some(adm_req),
+            ValidateRequest::Raw(_) => None,
+        };
+        let status = admission_response.status.as_ref();
+
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(literal) => literal.to_owned(),
+                TemplatePart::Variable(variable) => match variable {
+                    TemplateVariable::Kind => adm_req
+                        .map(|adm_req| adm_req.kind.kind.clone())
+                        .unwrap_or_default(),
+                    TemplateVariable::Namespace => adm_req
+                        .and_then(|adm_req| adm_req.namespace.clone())
+                        .unwrap_or_default(),
+                    TemplateVariable::Name => adm_req
+                        .and_then(|adm_req| adm_req.name.clone())
+                        .unwrap_or_default(),
+                    TemplateVariable::Operation => adm_req
+                        .map(|adm_req| adm_req.operation.clone())
+                        .unwrap_or_default(),
+                    TemplateVariable::User => adm_req
+                        .and_then(|adm_req| adm_req.user_info.username.clone())
+                        .unwrap_or_default(),
+                    TemplateVariable::PolicyMessage => status
+                        .and_then(|status| status.message.clone())
+                        .unwrap_or_default(),
+                    TemplateVariable::PolicyCode => status
+                        .and_then(|status| status.code)
+                        .map(|code| code.to_string())
+                        .unwrap_or_default(),
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+
+    fn rejection() -> AdmissionResponse {
+        AdmissionResponse::reject("uid".to_owned(), "privileged containers".to_owned(), 400)
+    }
+
+    #[rstest]
+    #[case::static_message("rejected by the policy", "rejected by the policy")]
+    #[case::request_fields(
+        "{{ .kind }} {{ .namespace }}/{{ .name }} ({{ .operation }} by {{ .user }})",
+        "Deployment default/nginx (UPDATE by alice)"
+    )]
+    #[case::policy_rejection(
+        "{{.policyMessage}} [{{.policyCode}}], see https://docs.example.com/psp",
+        "privileged containers [400], see https://docs.example.com/psp"
+    )]
+    fn render_template(#[case] template: &str, #[case] expected: &str) {
+        let mut adm_req = build_admission_review_request().request;
+        adm_req.kind.kind = "Deployment".to_owned();
+        adm_req.namespace = Some("default".to_owned());
+        adm_req.name = Some("nginx".to_owned());
+        adm_req.operation = "UPDATE".to_owned();
+        adm_req.user_info.username = Some("alice".to_owned());
+        let validate_request = ValidateRequest::AdmissionRequest(Box::new(adm_req));
+
+        let message_template = MessageTemplate::new(template).unwrap();
+
+        assert_eq!(
+            expected,
+            message_template.render(&validate_request, &rejection())
+        );
+    }
+
+    #[test]
+    fn render_template_of_raw_request() {
+        let validate_request = ValidateRequest::Raw(serde_json::json!({"foo": "bar"}));
+        let message_template =
+            MessageTemplate::new("{{ .kind }}{{ .name }} rejected: {{ .policyMessage }}").unwrap();
+
+        assert_eq!(
+            " rejected: privileged containers",
+            message_template.render(&validate_request, &rejection())
+        );
+    }
+
+    #[rstest]
+    #[case::unclosed_placeholder("{{ .name rejected")]
+    #[case::unknown_placeholder("{{ .owner }} rejected")]
+    #[case::missing_dot("{{ name }} rejected")]
+    fn invalid_template(#[case] template: &str) {
+        assert!(MessageTemplate::new(template).is_err());
+    }
+}
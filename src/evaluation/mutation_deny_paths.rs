@@ -0,0 +1,173 @@
+use anyhow::{Result, anyhow};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde_json::Value;
+
+/// The fields of the object a policy is not allowed to mutate, expressed as JSON pointer
+/// globs. Inside of the globs, `*` matches a single path segment while `**` matches any
+/// number of segments.
+#[derive(Clone, Debug)]
+pub(crate) struct MutationDenyPaths {
+    globs: GlobSet,
+}
+
+impl MutationDenyPaths {
+    pub(crate) fn new(patterns: &[String]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| anyhow!("invalid mutation deny path '{}': {}", pattern, e))?;
+            builder.add(glob);
+        }
+        let globs = builder
+            .build()
+            .map_err(|e| anyhow!("cannot build mutation deny paths: {}", e))?;
+
+        Ok(MutationDenyPaths { globs })
+    }
+
+    /// Return the first protected path whose value differs between the original and the
+    /// patched object, if any. Fields that are added or removed count as changed.
+    pub(crate) fn first_changed_path(&self, original: &Value, patched: &Value) -> Option<String> {
+        self.changed_path(String::new(), Some(original), Some(patched))
+    }
+
+    fn changed_path(
+        &self,
+        path: String,
+        original: Option<&Value>,
+        patched: Option<&Value>,
+    ) -> Option<String> {
+        // nothing changed below this point
+        if original == patched {
+            return None;
+        }
+        if self.globs.is_match(&path) {
+            return Some(path);
+        }
+
+        match (original, patched) {
+            (Some(Value::Object(_)), _) | (_, Some(Value::Object(_))) => {
+                let original = original.and_then(Value::as_object);
+                let patched = patched.and_then(Value::as_object);
+                let mut keys: Vec<&String> = original
+                    .into_iter()
+                    .chain(patched)
+                    .flat_map(|object| object.keys())
+                    .collect();
+                keys.sort();
+                keys.dedup();
+
+                keys.into_iter().find_map(|key| {
+                    self.changed_path(
+                        format!("{path}/{}", escape_segment(key)),
+                        original.and_then(|object| object.get(key)),
+                        patched.and_then(|object| object.get(key)),
+                    )
+                })
+            }
+            (Some(Value::Array(_)), _) | (_, Some(Value::Array(_))) => {
+                let original = original.and_then(Value::as_array);
+                let patched = patched.and_then(Value::as_array);
+                let len = original
+                    .map(Vec::len)
+                    .max(patched.map(Vec::len))
+                    .unwrap_or_default();
+
+                (0..len).find_map(|index| {
+                    self.changed_path(
+                        format!("{path}/{index}"),
+                        original.and_then(|array| array.get(index)),
+                        patched.and_then(|array| array.get(index)),
+                    )
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Escape a key to be used as a JSON pointer segment, as described by RFC 6901
+fn escape_segment(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use serde_json::json;
+
+    fn pod() -> Value {
+        json!({
+            "metadata": {
+                "name": "nginx",
+                "labels": {"app": "web"},
+                "ownerReferences": [{"kind": "ReplicaSet", "name": "nginx-1234"}]
+            },
+            "spec": {
+                "serviceAccountName": "default",
+                "containers": [{"name": "nginx", "image": "nginx:1.27"}]
+            }
+        })
+    }
+
+    #[rstest]
+    #[case::unrelated_field(
+        "/metadata/ownerReferences",
+        json!({"op": "add", "path": "/metadata/labels/tier", "value": "front"}),
+        None
+    )]
+    #[case::protected_field_replaced(
+        "/spec/serviceAccountName",
+        json!({"op": "replace", "path": "/spec/serviceAccountName", "value": "admin"}),
+        Some("/spec/serviceAccountName")
+    )]
+    #[case::child_of_protected_field(
+        "/metadata/ownerReferences",
+        json!({"op": "remove", "path": "/metadata/ownerReferences/0"}),
+        Some("/metadata/ownerReferences")
+    )]
+    #[case::parent_of_protected_field(
+        "/metadata/ownerReferences",
+        json!({"op": "replace", "path": "/metadata", "value": {"name": "nginx"}}),
+        Some("/metadata/ownerReferences")
+    )]
+    #[case::parent_rewritten_without_changes(
+        "/metadata/ownerReferences",
+        json!({"op": "replace", "path": "/metadata/ownerReferences", "value": [{"kind": "ReplicaSet", "name": "nginx-1234"}]}),
+        None
+    )]
+    #[case::single_segment_wildcard(
+        "/spec/containers/*/image",
+        json!({"op": "replace", "path": "/spec/containers/0/image", "value": "nginx:latest"}),
+        Some("/spec/containers/0/image")
+    )]
+    #[case::field_added(
+        "/metadata/annotations",
+        json!({"op": "add", "path": "/metadata/annotations", "value": {}}),
+        Some("/metadata/annotations")
+    )]
+    fn detect_changed_paths(
+        #[case] pattern: &str,
+        #[case] operation: Value,
+        #[case] expected: Option<&str>,
+    ) {
+        let mutation_deny_paths = MutationDenyPaths::new(&[pattern.to_string()]).unwrap();
+        let original = pod();
+        let mut patched = original.clone();
+        let patch: json_patch::Patch = serde_json::from_value(json!([operation])).unwrap();
+        json_patch::patch(&mut patched, &patch).unwrap();
+
+        assert_eq!(
+            expected.map(str::to_string),
+            mutation_deny_paths.first_changed_path(&original, &patched)
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(MutationDenyPaths::new(&["/metadata/[".to_string()]).is_err());
+    }
+}
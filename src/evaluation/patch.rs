@@ -0,0 +1,84 @@
+use anyhow::{Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::Value;
+
+/// Apply the given patch to the object. The patch is a base64 encoded JSONPatch, like
+/// the ones returned by the mutating policies.
+///
+/// The object is left untouched when the patch cannot be applied.
+pub(crate) fn apply_patch(object: &mut Value, encoded_patch: &str) -> Result<()> {
+    let patch = STANDARD
+        .decode(encoded_patch)
+        .map_err(|e| anyhow!("cannot decode patch: {}", e))?;
+    let patch: json_patch::Patch =
+        serde_json::from_slice(&patch).map_err(|e| anyhow!("cannot parse patch: {}", e))?;
+
+    json_patch::patch(object, &patch).map_err(|e| anyhow!("cannot apply patch: {}", e))
+}
+
+/// Compute the JSONPatch that turns `original` into `patched`, and return it
+/// base64 encoded
+pub(crate) fn encode_diff(original: &Value, patched: &Value) -> Result<String> {
+    let patch = json_patch::diff(original, patched);
+    let patch = serde_json::to_vec(&patch).map_err(|e| anyhow!("cannot encode patch: {}", e))?;
+
+    Ok(STANDARD.encode(patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encode(patch: Value) -> String {
+        STANDARD.encode(serde_json::to_vec(&patch).unwrap())
+    }
+
+    #[test]
+    fn apply_patches_in_sequence() {
+        let mut object = json!({"metadata": {"name": "nginx"}});
+
+        apply_patch(
+            &mut object,
+            &encode(json!([{"op": "add", "path": "/metadata/labels", "value": {"app": "web"}}])),
+        )
+        .unwrap();
+        apply_patch(
+            &mut object,
+            &encode(json!([{"op": "add", "path": "/metadata/labels/tier", "value": "front"}])),
+        )
+        .unwrap();
+
+        assert_eq!(
+            json!({"metadata": {"name": "nginx", "labels": {"app": "web", "tier": "front"}}}),
+            object
+        );
+    }
+
+    #[test]
+    fn failed_patch_leaves_object_untouched() {
+        let mut object = json!({"metadata": {"name": "nginx"}});
+
+        let result = apply_patch(
+            &mut object,
+            &encode(json!([
+                {"op": "add", "path": "/metadata/labels", "value": {}},
+                {"op": "remove", "path": "/spec"}
+            ])),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(json!({"metadata": {"name": "nginx"}}), object);
+    }
+
+    #[test]
+    fn diff_round_trip() {
+        let original = json!({"metadata": {"name": "nginx"}, "spec": {"replicas": 1}});
+        let patched = json!({"metadata": {"name": "nginx", "labels": {"app": "web"}}});
+
+        let mut object = original.clone();
+        apply_patch(&mut object, &encode_diff(&original, &patched).unwrap()).unwrap();
+
+        assert_eq!(patched, object);
+    }
+}
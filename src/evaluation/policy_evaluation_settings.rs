@@ -1,4 +1,7 @@
-use crate::config::PolicyOrPolicyGroupSettings;
+use crate::{
+    config::PolicyOrPolicyGroupSettings,
+    evaluation::{message_template::MessageTemplate, mutation_deny_paths::MutationDenyPaths},
+};
 use policy_evaluator::admission_response_handler::policy_mode::PolicyMode;
 
 /// Holds the evaluation settings of loaded Policy. These settings are taken straight from the
@@ -13,7 +16,11 @@ pub(crate) struct PolicyEvaluationSettings {
     /// The policy-specific settings provided by the user
     pub(crate) settings: PolicyOrPolicyGroupSettings,
     /// Determines a custom rejection message for the policy
-    pub(crate) custom_rejection_message: Option<String>,
+    pub(crate) custom_rejection_message: Option<MessageTemplate>,
     /// Timeout for the evaluation of the policy in seconds
     pub(crate) timeout_eval_seconds: Option<u64>,
+    /// The fields the policy is not allowed to mutate
+    pub(crate) mutation_deny_paths: Option<MutationDenyPaths>,
+    /// Whether the context-aware policy accepts the dry-run requests without being evaluated
+    pub(crate) skip_context_aware_resources_on_dry_run: bool,
 }
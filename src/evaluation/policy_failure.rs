@@ -0,0 +1,120 @@
+use policy_evaluator::admission_response::AdmissionResponse;
+
+/// The message of the rejections produced by policy-evaluator when a policy exceeds the
+/// time it's allowed to run for
+const POLICY_TIMEOUT_REJECTION_MESSAGE: &str =
+    "Policy execution interrupted because it exceeded the allowed execution time";
+
+/// The prefix of the message of the rejections produced by policy-evaluator when the
+/// WebAssembly module of a policy traps
+const POLICY_TRAP_REJECTION_PREFIX: &str = "internal server error: ";
+
+/// The failures that don't raise an `EvaluationError`: the request is rejected with the `500`
+/// code instead, like policy-evaluator does for the policies that time out or trap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PolicyFailure {
+    /// The policy exceeded the time it's allowed to run for
+    Timeout,
+    /// The WebAssembly module of the policy trapped
+    Trap,
+    /// The expression of the policy group failed, for example because it exceeded the
+    /// limits of the engine
+    PolicyGroupExpression,
+}
+
+impl PolicyFailure {
+    /// Returns the failure described by the response of a WebAssembly module, as returned by
+    /// policy-evaluator. It must be called on the response of the module only, the rejections
+    /// built by Policy Server are never failures of the module.
+    pub(crate) fn of_module_response(response: &AdmissionResponse) -> Option<PolicyFailure> {
+        if response.allowed {
+            return None;
+        }
+        let status = response.status.as_ref()?;
+        if status.code != Some(500) {
+            return None;
+        }
+        let message = status.message.as_deref().unwrap_or_default();
+        if message == POLICY_TIMEOUT_REJECTION_MESSAGE {
+            Some(PolicyFailure::Timeout)
+        } else if message.starts_with(POLICY_TRAP_REJECTION_PREFIX) {
+            Some(PolicyFailure::Trap)
+        } else {
+            None
+        }
+    }
+}
+
+/// The response of a policy, with the failure that made it reject the request. The failure
+/// is classified where the rejection is produced, the message of the rejection can then be
+/// replaced, for example by the custom rejection message of the policy.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PolicyResponse {
+    pub(crate) response: AdmissionResponse,
+    pub(crate) failure: Option<PolicyFailure>,
+}
+
+impl PolicyResponse {
+    /// The response of a WebAssembly module, see `PolicyFailure::of_module_response`
+    pub(crate) fn of_module(response: AdmissionResponse) -> Self {
+        PolicyResponse {
+            failure: PolicyFailure::of_module_response(&response),
+            response,
+        }
+    }
+}
+
+impl From<AdmissionResponse> for PolicyResponse {
+    fn from(response: AdmissionResponse) -> Self {
+        PolicyResponse {
+            response,
+            failure: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::accepted(
+        AdmissionResponse {
+            allowed: true,
+            ..Default::default()
+        },
+        None
+    )]
+    #[case::rejected(AdmissionResponse::reject("uid".to_owned(), "denied".to_owned(), 400), None)]
+    #[case::rejected_with_500(
+        AdmissionResponse::reject("uid".to_owned(), "denied".to_owned(), 500),
+        None
+    )]
+    #[case::timeout(
+        AdmissionResponse::reject("uid".to_owned(), POLICY_TIMEOUT_REJECTION_MESSAGE.to_owned(), 500),
+        Some(PolicyFailure::Timeout)
+    )]
+    #[case::rejection_mentioning_the_failure(
+        AdmissionResponse::reject(
+            "uid".to_owned(),
+            "the backend returned an internal server error: retry later".to_owned(),
+            500
+        ),
+        None
+    )]
+    #[case::trap(
+        AdmissionResponse::reject(
+            "uid".to_owned(),
+            format!("{POLICY_TRAP_REJECTION_PREFIX}wasm trap: unreachable"),
+            500
+        ),
+        Some(PolicyFailure::Trap)
+    )]
+    fn failure_of_module_response(
+        #[case] response: AdmissionResponse,
+        #[case] expected: Option<PolicyFailure>,
+    ) {
+        assert_eq!(expected, PolicyFailure::of_module_response(&response));
+    }
+}
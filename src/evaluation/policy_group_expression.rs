@@ -0,0 +1,478 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use anyhow::anyhow;
+use policy_evaluator::admission_response_handler::{
+    errors::{EvaluationError, Result},
+    policy_id::PolicyID,
+};
+
+/// The maximum number of operations an expression can perform
+const MAX_OPERATIONS: u64 = 10_000;
+/// The maximum nesting of an expression
+const MAX_EXPR_DEPTH: usize = 64;
+/// The expressions cannot define functions, they can only call the members of the group
+const MAX_CALL_LEVELS: usize = 8;
+
+/// The prefix of the message of the rejections of the policy groups whose expression failed
+const EXPRESSION_FAILURE_PREFIX: &str = "cannot evaluate the expression of policy group ";
+
+/// The reasons why the expression of a policy group cannot be evaluated
+#[derive(Debug)]
+pub(crate) enum ExpressionError {
+    /// A member of the group cannot be evaluated, the error is the one of the member
+    Member(EvaluationError),
+    /// The expression failed, for example because it exceeded the limits of the engine
+    Expression(String),
+}
+
+impl From<EvaluationError> for ExpressionError {
+    fn from(error: EvaluationError) -> Self {
+        ExpressionError::Member(error)
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::Member(error) => write!(f, "{error}"),
+            ExpressionError::Expression(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// The members called by an evaluation of an expression
+#[derive(Default)]
+struct MemberCalls {
+    /// The outcome of the members evaluated so far
+    outcomes: HashMap<String, bool>,
+    /// The member called by the expression before being evaluated. The run of the expression
+    /// is interrupted by the call.
+    pending: Option<String>,
+}
+
+/// The expression of a policy group, compiled when the group is registered. Each member of
+/// the group is a function of the expression, the request is accepted when the expression
+/// evaluates to `true`.
+///
+/// The Rhai engine doesn't load any package, the expressions can only use the operators of
+/// the language and call the members. Its limits bound the cost of an evaluation.
+pub(crate) struct PolicyGroupExpression {
+    members: Vec<String>,
+    ast: rhai::AST,
+}
+
+impl PolicyGroupExpression {
+    /// Compile the expression of a policy group made of the given members
+    pub(crate) fn new(expression: &str, members: &[String]) -> anyhow::Result<Self> {
+        let engine = build_engine(members, &Arc::default());
+        let ast = engine
+            .compile_expression(expression)
+            .map_err(|e| anyhow!("{e}"))?;
+
+        Ok(PolicyGroupExpression {
+            members: members.to_vec(),
+            ast,
+        })
+    }
+
+    /// Evaluate the expression. Each member is evaluated with `validate_member` the first
+    /// time the expression calls it, the members skipped by the short-circuit of the boolean
+    /// operators are never evaluated.
+    ///
+    /// The members are evaluated outside of the Rhai engine: a call to a member that has not
+    /// been evaluated yet interrupts the run of the expression, which is run again once the
+    /// member has been evaluated. The expressions are deterministic, each run repeats the
+    /// calls of the previous one.
+    ///
+    /// The error of a member that cannot be evaluated is returned as it is, inside of
+    /// `ExpressionError::Member`.
+    pub(crate) fn evaluate<F>(
+        &self,
+        policy_id: &PolicyID,
+        mut validate_member: F,
+    ) -> std::result::Result<bool, ExpressionError>
+    where
+        F: FnMut(&str) -> Result<bool>,
+    {
+        let member_calls: Arc<Mutex<MemberCalls>> = Arc::default();
+        let engine = build_engine(&self.members, &member_calls);
+
+        loop {
+            let outcome = engine.eval_ast::<bool>(&self.ast);
+            let pending = lock_member_calls(&member_calls).pending.take();
+            let Some(name) = pending else {
+                return outcome.map_err(|e| {
+                    ExpressionError::Expression(format!(
+                        "{EXPRESSION_FAILURE_PREFIX}{policy_id}: {e}"
+                    ))
+                });
+            };
+            let allowed = validate_member(&name)?;
+            lock_member_calls(&member_calls)
+                .outcomes
+                .insert(name, allowed);
+        }
+    }
+}
+
+/// Build the engine evaluating the expression of a policy group. The functions registered
+/// for the members return the outcome found in `member_calls`, the call to a member that
+/// has not been evaluated yet is recorded as pending and fails.
+///
+/// Building the engine is cheap, each evaluation builds its own one.
+fn build_engine(members: &[String], member_calls: &Arc<Mutex<MemberCalls>>) -> rhai::Engine {
+    let mut engine = rhai::Engine::new_raw();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH)
+        .set_max_call_levels(MAX_CALL_LEVELS);
+    for name in members {
+        let member = name.clone();
+        let member_calls = member_calls.clone();
+        engine.register_fn(
+            name.as_str(),
+            move || -> std::result::Result<bool, Box<rhai::EvalAltResult>> {
+                let mut member_calls = lock_member_calls(&member_calls);
+                if let Some(allowed) = member_calls.outcomes.get(&member) {
+                    return Ok(*allowed);
+                }
+                member_calls.pending = Some(member.clone());
+                Err(format!("policy group member {member} has not been evaluated yet").into())
+            },
+        );
+    }
+
+    engine
+}
+
+/// The member calls are never locked across the evaluation of a member, a poisoned lock
+/// still holds consistent calls
+fn lock_member_calls(member_calls: &Mutex<MemberCalls>) -> MutexGuard<'_, MemberCalls> {
+    member_calls.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Ensure the expression of a policy group allowed to mutate the requests applies only the
+/// mutations of the members whose outcome made the expression `true`. The patch of a member
+/// accepting the request is applied as soon as the member is evaluated, the members called
+/// after it receive the mutated object. The expression can only combine the members with
+/// `&&`, `||` and parentheses, where the left operand of `||` cannot have accepting members
+/// when it evaluates to `false`. For example, `(a() && b()) || c()` is rejected: `a` can
+/// accept the request before `b` rejects it, and `c` would receive the object mutated by `a`.
+pub(crate) fn validate_mutating_expression(expression: &str) -> anyhow::Result<()> {
+    let mut parser = MutatingExpressionParser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    parser.parse_or()?;
+    if let Some(token) = parser.tokens.get(parser.position) {
+        return Err(anyhow!("has an unexpected {token}"));
+    }
+
+    Ok(())
+}
+
+/// A token of the expressions of the policy groups allowed to mutate the requests
+#[derive(Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Identifier(identifier) => write!(f, "`{identifier}`"),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Not => write!(f, "`!`"),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+        }
+    }
+}
+
+/// Split the expression of a policy group allowed to mutate the requests into tokens,
+/// skipping the comments
+fn tokenize(expression: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                // The block comments can be nested
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('/') if chars.next_if_eq(&'*').is_some() => depth += 1,
+                        Some('*') if chars.next_if_eq(&'/').is_some() => depth -= 1,
+                        Some(_) => {}
+                        None => return Err(anyhow!("has an unterminated comment")),
+                    }
+                }
+            }
+            '&' if chars.next_if_eq(&'&').is_some() => tokens.push(Token::And),
+            '|' if chars.next_if_eq(&'|').is_some() => tokens.push(Token::Or),
+            '!' => tokens.push(Token::Not),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let mut identifier = String::from(c);
+                while let Some(c) = chars.next_if(|c| *c == '_' || c.is_ascii_alphanumeric()) {
+                    identifier.push(c);
+                }
+                tokens.push(Token::Identifier(identifier));
+            }
+            c => {
+                return Err(anyhow!(
+                    "uses `{c}`, only the members, `true`, `false`, `&&`, `||` and parentheses can be used"
+                ));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse the expression of a policy group allowed to mutate the requests, see
+/// `validate_mutating_expression`. Each rule returns whether the parsed operand is
+/// clean when it evaluates to `false`: none of the members it called accepted the request,
+/// their mutations have not been applied.
+struct MutatingExpressionParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl MutatingExpressionParser {
+    fn next_token(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn next_if_eq(&mut self, expected: &Token) -> bool {
+        let matches = self.tokens.get(self.position) == Some(expected);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<bool> {
+        let mut clean_on_false = self.parse_and()?;
+        while self.next_if_eq(&Token::Or) {
+            if !clean_on_false {
+                return Err(anyhow!(
+                    "uses `||` after members that can accept the request when the left operand is `false`, like `(a() && b()) || c()`"
+                ));
+            }
+            clean_on_false = self.parse_and()?;
+        }
+
+        Ok(clean_on_false)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<bool> {
+        let mut clean_on_false = self.parse_operand()?;
+        while self.next_if_eq(&Token::And) {
+            self.parse_operand()?;
+            clean_on_false = false;
+        }
+
+        Ok(clean_on_false)
+    }
+
+    fn parse_operand(&mut self) -> anyhow::Result<bool> {
+        match self.next_token() {
+            Some(Token::Open) => {
+                let clean_on_false = self.parse_or()?;
+                if !self.next_if_eq(&Token::Close) {
+                    return Err(anyhow!("has an unbalanced `(`"));
+                }
+                Ok(clean_on_false)
+            }
+            Some(Token::Identifier(identifier))
+                if identifier == "true" || identifier == "false" =>
+            {
+                Ok(true)
+            }
+            // The members rejecting the request, and the nested groups, never mutate it
+            Some(Token::Identifier(_)) => {
+                if !(self.next_if_eq(&Token::Open) && self.next_if_eq(&Token::Close)) {
+                    return Err(anyhow!(
+                        "uses an identifier that is not a call to a member of the group"
+                    ));
+                }
+                Ok(true)
+            }
+            Some(Token::Not) => Err(anyhow!(
+                "negates an operand with `!`, which can apply the mutations of the members that made it `false`"
+            )),
+            Some(token) => Err(anyhow!("has an unexpected {token}")),
+            None => Err(anyhow!("ends unexpectedly")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+
+    fn members(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[rstest]
+    #[case::or_short_circuit("happy() || unhappy()", true, &["happy"])]
+    #[case::and_short_circuit("unhappy() && happy()", false, &["unhappy"])]
+    #[case::all_called("happy() && unhappy()", false, &["happy", "unhappy"])]
+    #[case::called_once("unhappy() || (happy() && unhappy())", false, &["unhappy", "happy"])]
+    #[case::negation("!unhappy() && happy()", true, &["unhappy", "happy"])]
+    #[case::call_inside_of_comment("/* unhappy() */ happy() // unhappy()", true, &["happy"])]
+    #[case::no_call("true || happy()", true, &[])]
+    fn evaluate_calls_the_members_lazily(
+        #[case] expression: &str,
+        #[case] expected_allowed: bool,
+        #[case] expected_calls: &[&str],
+    ) {
+        let policy_id = PolicyID::Policy("group".to_string());
+        let policy_group_expression =
+            PolicyGroupExpression::new(expression, &members(&["happy", "unhappy"])).unwrap();
+        let mut calls: Vec<String> = Vec::new();
+
+        let allowed = policy_group_expression
+            .evaluate(&policy_id, |name| {
+                calls.push(name.to_owned());
+                Ok(name == "happy")
+            })
+            .expect("the expression should be evaluated");
+
+        assert_eq!(expected_allowed, allowed);
+        assert_eq!(members(expected_calls), calls);
+    }
+
+    #[test]
+    fn evaluate_propagates_the_evaluation_errors() {
+        let policy_id = PolicyID::Policy("group".to_string());
+        let policy_group_expression =
+            PolicyGroupExpression::new("happy() && unhappy()", &members(&["happy", "unhappy"]))
+                .unwrap();
+
+        let result = policy_group_expression.evaluate(&policy_id, |name| match name {
+            "happy" => Ok(true),
+            _ => Err(EvaluationError::WebAssemblyError("boom".to_string())),
+        });
+
+        assert!(matches!(
+            result,
+            Err(ExpressionError::Member(EvaluationError::WebAssemblyError(message)))
+                if message == "boom"
+        ));
+    }
+
+    #[test]
+    fn evaluate_nested_expressions() {
+        let policy_id = PolicyID::Policy("group".to_string());
+        let nested_policy_id = PolicyID::Policy("group/nested".to_string());
+        let policy_group_expression =
+            PolicyGroupExpression::new("happy() && nested()", &members(&["happy", "nested"]))
+                .unwrap();
+        let nested_policy_group_expression =
+            PolicyGroupExpression::new("happy() && !unhappy()", &members(&["happy", "unhappy"]))
+                .unwrap();
+        let mut nested_calls: Vec<String> = Vec::new();
+
+        let allowed = policy_group_expression
+            .evaluate(&policy_id, |name| match name {
+                "nested" => Ok(nested_policy_group_expression
+                    .evaluate(&nested_policy_id, |name| {
+                        nested_calls.push(name.to_owned());
+                        Ok(name == "happy")
+                    })
+                    .expect("the nested expression should be evaluated")),
+                _ => Ok(true),
+            })
+            .expect("the expression should be evaluated");
+
+        assert!(allowed);
+        assert_eq!(members(&["happy", "unhappy"]), nested_calls);
+    }
+
+    #[test]
+    fn evaluate_on_multiple_threads() {
+        let policy_id = PolicyID::Policy("group".to_string());
+        let policy_group_expression =
+            PolicyGroupExpression::new("first() && second()", &members(&["first", "second"]))
+                .unwrap();
+
+        std::thread::scope(|scope| {
+            let evaluations: Vec<_> = [true, false]
+                .into_iter()
+                .map(|allowed| {
+                    let policy_group_expression = &policy_group_expression;
+                    let policy_id = &policy_id;
+                    scope.spawn(move || {
+                        (0..100).all(|_| {
+                            policy_group_expression
+                                .evaluate(policy_id, |_| Ok(allowed))
+                                .unwrap()
+                                == allowed
+                        })
+                    })
+                })
+                .collect();
+            for evaluation in evaluations {
+                assert!(evaluation.join().unwrap());
+            }
+        });
+    }
+
+    #[test]
+    fn new_rejects_syntax_errors() {
+        assert!(PolicyGroupExpression::new("happy() &&", &members(&["happy"])).is_err());
+    }
+
+    #[test]
+    fn evaluate_rejects_unknown_members() {
+        let policy_id = PolicyID::Policy("group".to_string());
+        let policy_group_expression =
+            PolicyGroupExpression::new("happy() && unknown()", &members(&["happy"])).unwrap();
+
+        let result = policy_group_expression.evaluate(&policy_id, |_| Ok(true));
+
+        assert!(matches!(
+            result,
+            Err(ExpressionError::Expression(message))
+                if message.starts_with("cannot evaluate the expression of policy group group")
+        ));
+    }
+
+    #[rstest]
+    #[case::single_member("a()", true)]
+    #[case::conjunction("a() && (b() && c())", true)]
+    #[case::disjunction("a() || b() || c()", true)]
+    #[case::conjunction_of_disjunction("a() && (b() || c())", true)]
+    #[case::literal("true && a()", true)]
+    #[case::comments("/* (a() && b()) || */ a() // || !c()", true)]
+    #[case::disjunction_after_conjunction("(a() && b()) || c()", false)]
+    #[case::negation("!a() || b()", false)]
+    #[case::comparison("a() != b()", false)]
+    #[case::variable("a() && b", false)]
+    #[case::unbalanced_parenthesis("(a() && b()", false)]
+    fn validate_mutating_expressions(#[case] expression: &str, #[case] valid: bool) {
+        assert_eq!(valid, validate_mutating_expression(expression).is_ok());
+    }
+}
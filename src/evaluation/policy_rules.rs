@@ -0,0 +1,94 @@
+use policy_evaluator::{
+    admission_request::AdmissionRequest,
+    policy_metadata::{Operation, Rule},
+};
+
+/// Returns `true` when at least one of the given rules matches the request.
+///
+/// The rules are evaluated using the same semantics of the ones defined inside of a
+/// `ValidatingWebhookConfiguration`:
+/// - `*` matches all the API groups, versions and operations
+/// - `*` matches all the resources, but not their subresources
+/// - `pods/*` matches all the subresources of pods, `*/scale` matches all the scale
+///   subresources and `*/*` matches everything
+pub(crate) fn rules_match_request(rules: &[Rule], adm_req: &AdmissionRequest) -> bool {
+    rules.iter().any(|rule| rule_matches_request(rule, adm_req))
+}
+
+fn rule_matches_request(rule: &Rule, adm_req: &AdmissionRequest) -> bool {
+    let sub_resource = adm_req.sub_resource.as_deref().unwrap_or_default();
+
+    rule.api_groups
+        .iter()
+        .any(|group| group == "*" || group == &adm_req.resource.group)
+        && rule
+            .api_versions
+            .iter()
+            .any(|version| version == "*" || version == &adm_req.resource.version)
+        && rule
+            .operations
+            .iter()
+            .any(|operation| operation_matches(operation, &adm_req.operation))
+        && rule
+            .resources
+            .iter()
+            .any(|resource| resource_matches(resource, &adm_req.resource.resource, sub_resource))
+}
+
+fn operation_matches(operation: &Operation, requested_operation: &str) -> bool {
+    match operation {
+        Operation::All => true,
+        Operation::Create => requested_operation == "CREATE",
+        Operation::Update => requested_operation == "UPDATE",
+        Operation::Delete => requested_operation == "DELETE",
+        Operation::Connect => requested_operation == "CONNECT",
+    }
+}
+
+fn resource_matches(pattern: &str, resource: &str, sub_resource: &str) -> bool {
+    let (resource_pattern, sub_resource_pattern) = pattern.split_once('/').unwrap_or((pattern, ""));
+
+    (resource_pattern == "*" || resource_pattern == resource)
+        && (sub_resource_pattern == "*" || sub_resource_pattern == sub_resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use rstest::*;
+
+    fn rule(api_group: &str, resource: &str, operation: Operation) -> Rule {
+        Rule {
+            api_groups: vec![api_group.to_string()],
+            api_versions: vec!["v1".to_string()],
+            resources: vec![resource.to_string()],
+            operations: vec![operation],
+        }
+    }
+
+    // the test request targets the `scale` subresource of a `apps/v1` deployment
+    #[rstest]
+    #[case::exact_match(rule("apps", "deployments/scale", Operation::Update), true)]
+    #[case::all_operations(rule("apps", "deployments/scale", Operation::All), true)]
+    #[case::all_groups(rule("*", "deployments/scale", Operation::Update), true)]
+    #[case::all_subresources(rule("apps", "deployments/*", Operation::Update), true)]
+    #[case::all_scale_subresources(rule("apps", "*/scale", Operation::Update), true)]
+    #[case::everything(rule("apps", "*/*", Operation::Update), true)]
+    #[case::wrong_operation(rule("apps", "deployments/scale", Operation::Create), false)]
+    #[case::wrong_group(rule("batch", "deployments/scale", Operation::Update), false)]
+    #[case::resource_without_subresource(rule("apps", "deployments", Operation::Update), false)]
+    #[case::wildcard_does_not_match_subresources(rule("apps", "*", Operation::Update), false)]
+    fn match_request(#[case] rule: Rule, #[case] expected: bool) {
+        let adm_req = build_admission_review_request().request;
+
+        assert_eq!(expected, rules_match_request(&[rule], &adm_req));
+    }
+
+    #[test]
+    fn no_rules_do_not_match() {
+        let adm_req = build_admission_review_request().request;
+
+        assert!(!rules_match_request(&[], &adm_req));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use policy_evaluator::admission_response_handler::policy_id::PolicyID;
+use sha2::{Digest, Sha256};
+
+use crate::config::CanaryRoutingKey;
+
+/// The variant of a policy that has been selected to evaluate a request
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PolicyVariant {
+    /// The main version of the policy
+    Stable,
+    /// The canary version of the policy, identified by the given ID
+    Canary(PolicyID),
+}
+
+impl PolicyVariant {
+    /// Returns the ID of the policy that has to be evaluated, given the ID of the main policy
+    pub(crate) fn policy_id<'a>(&'a self, stable_policy_id: &'a PolicyID) -> &'a PolicyID {
+        match self {
+            PolicyVariant::Stable => stable_policy_id,
+            PolicyVariant::Canary(canary_policy_id) => canary_policy_id,
+        }
+    }
+}
+
+impl fmt::Display for PolicyVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyVariant::Stable => write!(f, "stable"),
+            PolicyVariant::Canary(_) => write!(f, "canary"),
+        }
+    }
+}
+
+/// Describes how the requests are split between a policy and its canary
+#[derive(Clone, Debug)]
+pub(crate) struct PolicyCanaryRouting {
+    /// The ID used to register the canary inside of the `EvaluationEnvironment`
+    pub(crate) canary_policy_id: PolicyID,
+    /// The percentage of requests that are evaluated by the canary
+    pub(crate) weight: u8,
+    /// The request attribute used to pick the variant
+    pub(crate) routing_key: CanaryRoutingKey,
+}
+
+impl PolicyCanaryRouting {
+    /// Pick the variant that evaluates the request identified by the given values
+    pub(crate) fn select(&self, request_uid: &str, namespace: Option<&str>) -> PolicyVariant {
+        let routing_value = match self.routing_key {
+            CanaryRoutingKey::RequestUid => request_uid,
+            CanaryRoutingKey::Namespace => namespace.unwrap_or(request_uid),
+        };
+
+        if routing_bucket(routing_value) < u64::from(self.weight) {
+            PolicyVariant::Canary(self.canary_policy_id.clone())
+        } else {
+            PolicyVariant::Stable
+        }
+    }
+}
+
+/// Map the given value to a bucket between 0 and 99.
+///
+/// A sha256 digest is used instead of the `std` hasher because the result must not
+/// change across restarts or releases of Policy Server.
+fn routing_bucket(value: &str) -> u64 {
+    let digest = Sha256::digest(value.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+
+    u64::from_be_bytes(prefix) % 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn canary_routing(weight: u8, routing_key: CanaryRoutingKey) -> PolicyCanaryRouting {
+        PolicyCanaryRouting {
+            canary_policy_id: PolicyID::Policy("policy/canary".to_string()),
+            weight,
+            routing_key,
+        }
+    }
+
+    #[rstest]
+    #[case::never_canary(0, PolicyVariant::Stable)]
+    #[case::always_canary(100, PolicyVariant::Canary(PolicyID::Policy("policy/canary".to_string())))]
+    fn select_with_boundary_weights(#[case] weight: u8, #[case] expected: PolicyVariant) {
+        let routing = canary_routing(weight, CanaryRoutingKey::RequestUid);
+
+        for uid in ["a", "b", "c", "d", "e"] {
+            assert_eq!(expected, routing.select(uid, None));
+        }
+    }
+
+    #[test]
+    fn select_is_stable() {
+        let routing = canary_routing(50, CanaryRoutingKey::RequestUid);
+
+        let first = routing.select("request-uid", None);
+        for _ in 0..10 {
+            assert_eq!(first, routing.select("request-uid", None));
+        }
+    }
+
+    #[test]
+    fn select_by_namespace_ignores_request_uid() {
+        let routing = canary_routing(50, CanaryRoutingKey::Namespace);
+
+        let expected = routing.select("uid-1", Some("team-a"));
+        for uid in ["uid-2", "uid-3", "uid-4"] {
+            assert_eq!(expected, routing.select(uid, Some("team-a")));
+        }
+    }
+
+    #[test]
+    fn weight_is_approximated() {
+        let routing = canary_routing(10, CanaryRoutingKey::RequestUid);
+
+        let canary_requests = (0..10_000)
+            .map(|i| routing.select(&format!("uid-{i}"), None))
+            .filter(|variant| matches!(variant, PolicyVariant::Canary(_)))
+            .count();
+
+        assert!(
+            (800..1200).contains(&canary_requests),
+            "unexpected number of canary requests: {canary_requests}"
+        );
+    }
+}
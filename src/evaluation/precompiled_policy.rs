@@ -1,7 +1,10 @@
 use anyhow::{Result, anyhow};
 use lazy_static::lazy_static;
 use policy_evaluator::{
-    ProtocolVersion, policy_evaluator::PolicyExecutionMode, policy_metadata::Metadata, wasmtime,
+    ProtocolVersion,
+    policy_evaluator::PolicyExecutionMode,
+    policy_metadata::{Metadata, Rule},
+    wasmtime,
 };
 use semver::{BuildMetadata, Prerelease, Version};
 use sha2::{Digest, Sha256};
@@ -39,6 +42,10 @@ pub(crate) struct PrecompiledPolicy {
 
     /// sha256 digest of the precompiled module
     pub digest: String,
+
+    /// The rules declared inside of the policy metadata, they describe the
+    /// requests the policy is interested in
+    pub rules: Vec<Rule>,
 }
 
 impl PrecompiledPolicy {
@@ -62,6 +69,7 @@ impl PrecompiledPolicy {
             precompiled_module,
             execution_mode,
             digest: format!("{digest:x}"),
+            rules: metadata.rules,
         })
     }
 }
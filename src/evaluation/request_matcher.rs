@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, LabelSelectorRequirement};
+use policy_evaluator::{admission_request::AdmissionRequest, policy_metadata::Rule};
+use serde_json::Value;
+
+use crate::evaluation::policy_rules::rules_match_request;
+
+type Labels = BTreeMap<String, String>;
+
+/// Decides whether a request is relevant for a policy, before the policy is evaluated.
+///
+/// The conditions follow the semantics of the ones defined inside of a
+/// `ValidatingWebhookConfiguration`, all of them must be satisfied.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RequestMatcher {
+    /// The rules the request must match
+    pub(crate) rules: Option<Vec<Rule>>,
+    /// The selector the labels of the namespace of the request must match
+    pub(crate) namespace_selector: Option<LabelSelector>,
+    /// The selector the labels of the object, or of the old object, must match
+    pub(crate) object_selector: Option<LabelSelector>,
+}
+
+impl RequestMatcher {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_none() && self.namespace_selector.is_none() && self.object_selector.is_none()
+    }
+
+    /// Returns `true` when the request satisfies all the conditions.
+    ///
+    /// `namespace_labels` is invoked only when the labels of the namespace are required, it
+    /// returns `None` when they cannot be obtained. In that case the namespace selector is
+    /// considered to be satisfied, the policy is then evaluated.
+    pub(crate) fn matches<F>(&self, adm_req: &AdmissionRequest, namespace_labels: F) -> bool
+    where
+        F: FnOnce(&str) -> Option<Labels>,
+    {
+        if let Some(rules) = &self.rules
+            && !rules_match_request(rules, adm_req)
+        {
+            return false;
+        }
+
+        if let Some(object_selector) = &self.object_selector {
+            let objects = [&adm_req.old_object, &adm_req.object];
+            if !objects
+                .into_iter()
+                .flatten()
+                .any(|object| label_selector_matches(object_selector, &object_labels(object)))
+            {
+                return false;
+            }
+        }
+
+        if let Some(namespace_selector) = &self.namespace_selector {
+            let is_namespace = adm_req.resource.group.is_empty()
+                && adm_req.resource.resource == "namespaces"
+                && adm_req.sub_resource.is_none();
+            let labels = if is_namespace {
+                adm_req
+                    .object
+                    .as_ref()
+                    .or(adm_req.old_object.as_ref())
+                    .map(object_labels)
+            } else {
+                match adm_req.namespace.as_deref() {
+                    // cluster wide resources are not filtered by the namespace selector
+                    None | Some("") => return true,
+                    Some(namespace) => namespace_labels(namespace),
+                }
+            };
+            if let Some(labels) = labels
+                && !label_selector_matches(namespace_selector, &labels)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+fn object_labels(object: &Value) -> Labels {
+    object
+        .pointer("/metadata/labels")
+        .and_then(Value::as_object)
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|(key, value)| Some((key.to_owned(), value.as_str()?.to_owned())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns `true` when the labels satisfy the selector. An empty selector matches
+/// everything.
+pub(crate) fn label_selector_matches(selector: &LabelSelector, labels: &Labels) -> bool {
+    let match_labels = selector
+        .match_labels
+        .iter()
+        .flatten()
+        .all(|(key, value)| labels.get(key) == Some(value));
+
+    match_labels
+        && selector
+            .match_expressions
+            .iter()
+            .flatten()
+            .all(|requirement| requirement_matches(requirement, labels))
+}
+
+fn requirement_matches(requirement: &LabelSelectorRequirement, labels: &Labels) -> bool {
+    let value = labels.get(&requirement.key);
+    let values = requirement.values.as_deref().unwrap_or_default();
+
+    match requirement.operator.as_str() {
+        "In" => value.is_some_and(|value| values.contains(value)),
+        "NotIn" => !value.is_some_and(|value| values.contains(value)),
+        "Exists" => value.is_some(),
+        "DoesNotExist" => value.is_none(),
+        _ => false,
+    }
+}
+
+/// Ensure the operators of the selector are known, and that they are given the right
+/// number of values
+pub(crate) fn validate_label_selector(selector: &LabelSelector) -> Result<()> {
+    for requirement in selector.match_expressions.iter().flatten() {
+        let has_values = requirement
+            .values
+            .as_ref()
+            .is_some_and(|values| !values.is_empty());
+        match (requirement.operator.as_str(), has_values) {
+            ("In" | "NotIn", true) | ("Exists" | "DoesNotExist", false) => {}
+            ("In" | "NotIn" | "Exists" | "DoesNotExist", _) => {
+                return Err(anyhow!(
+                    "invalid values for the '{}' operator of the '{}' label selector requirement",
+                    requirement.operator,
+                    requirement.key
+                ));
+            }
+            (operator, _) => {
+                return Err(anyhow!(
+                    "unknown operator '{}' in the '{}' label selector requirement",
+                    operator,
+                    requirement.key
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::build_admission_review_request;
+    use policy_evaluator::policy_metadata::Operation;
+    use rstest::*;
+    use serde_json::json;
+
+    fn selector(selector: Value) -> LabelSelector {
+        serde_json::from_value(selector).unwrap()
+    }
+
+    fn labels(labels: &[(&str, &str)]) -> Labels {
+        labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case::empty_selector(json!({}), true)]
+    #[case::match_labels(json!({"matchLabels": {"app": "web"}}), true)]
+    #[case::match_labels_wrong_value(json!({"matchLabels": {"app": "db"}}), false)]
+    #[case::operator_in(json!({"matchExpressions": [{"key": "tier", "operator": "In", "values": ["front", "back"]}]}), true)]
+    #[case::operator_not_in(json!({"matchExpressions": [{"key": "tier", "operator": "NotIn", "values": ["front"]}]}), false)]
+    #[case::operator_not_in_missing_label(json!({"matchExpressions": [{"key": "team", "operator": "NotIn", "values": ["ops"]}]}), true)]
+    #[case::operator_exists(json!({"matchExpressions": [{"key": "app", "operator": "Exists"}]}), true)]
+    #[case::operator_does_not_exist(json!({"matchExpressions": [{"key": "app", "operator": "DoesNotExist"}]}), false)]
+    #[case::all_requirements(json!({"matchLabels": {"app": "web"}, "matchExpressions": [{"key": "team", "operator": "Exists"}]}), false)]
+    fn match_label_selector(#[case] label_selector: Value, #[case] expected: bool) {
+        let labels = labels(&[("app", "web"), ("tier", "front")]);
+
+        assert_eq!(
+            expected,
+            label_selector_matches(&selector(label_selector), &labels)
+        );
+    }
+
+    #[rstest]
+    #[case::valid(json!({"matchExpressions": [{"key": "app", "operator": "In", "values": ["web"]}]}), true)]
+    #[case::missing_values(json!({"matchExpressions": [{"key": "app", "operator": "In"}]}), false)]
+    #[case::unexpected_values(json!({"matchExpressions": [{"key": "app", "operator": "Exists", "values": ["web"]}]}), false)]
+    #[case::unknown_operator(json!({"matchExpressions": [{"key": "app", "operator": "Gt", "values": ["1"]}]}), false)]
+    fn validate_selector(#[case] label_selector: Value, #[case] is_valid: bool) {
+        assert_eq!(
+            is_valid,
+            validate_label_selector(&selector(label_selector)).is_ok()
+        );
+    }
+
+    /// The test request targets the `scale` subresource of a deployment inside of the
+    /// `default` namespace
+    fn admission_request(object_labels: Value) -> AdmissionRequest {
+        let mut adm_req = build_admission_review_request().request;
+        adm_req.namespace = Some("default".to_owned());
+        adm_req.object = Some(json!({"metadata": {"labels": object_labels}}));
+        adm_req
+    }
+
+    #[rstest]
+    #[case::no_conditions(RequestMatcher::default(), true)]
+    #[case::rules_match(
+        RequestMatcher {
+            rules: Some(vec![Rule {
+                api_groups: vec!["apps".to_owned()],
+                api_versions: vec!["v1".to_owned()],
+                resources: vec!["deployments/scale".to_owned()],
+                operations: vec![Operation::Update],
+            }]),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::rules_do_not_match(
+        RequestMatcher {
+            rules: Some(vec![Rule {
+                api_groups: vec![String::new()],
+                api_versions: vec!["v1".to_owned()],
+                resources: vec!["pods".to_owned()],
+                operations: vec![Operation::Create],
+            }]),
+            ..Default::default()
+        },
+        false
+    )]
+    #[case::object_selector_matches(
+        RequestMatcher {
+            object_selector: Some(selector(json!({"matchLabels": {"app": "web"}}))),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::object_selector_does_not_match(
+        RequestMatcher {
+            object_selector: Some(selector(json!({"matchLabels": {"app": "db"}}))),
+            ..Default::default()
+        },
+        false
+    )]
+    #[case::namespace_selector_matches(
+        RequestMatcher {
+            namespace_selector: Some(selector(json!({"matchLabels": {"env": "prod"}}))),
+            ..Default::default()
+        },
+        true
+    )]
+    #[case::namespace_selector_does_not_match(
+        RequestMatcher {
+            namespace_selector: Some(selector(json!({"matchLabels": {"env": "dev"}}))),
+            ..Default::default()
+        },
+        false
+    )]
+    fn match_request(#[case] request_matcher: RequestMatcher, #[case] expected: bool) {
+        let adm_req = admission_request(json!({"app": "web"}));
+
+        assert_eq!(
+            expected,
+            request_matcher.matches(&adm_req, |namespace| {
+                assert_eq!("default", namespace);
+                Some(labels(&[("env", "prod")]))
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::cluster_wide_resource(None, None, true)]
+    #[case::namespace_labels_not_available(Some("default"), None, true)]
+    #[case::namespace_labels_available(Some("default"), Some(labels(&[("env", "dev")])), false)]
+    fn match_namespace_selector(
+        #[case] namespace: Option<&str>,
+        #[case] namespace_labels: Option<Labels>,
+        #[case] expected: bool,
+    ) {
+        let request_matcher = RequestMatcher {
+            namespace_selector: Some(selector(json!({"matchLabels": {"env": "prod"}}))),
+            ..Default::default()
+        };
+        let mut adm_req = admission_request(json!({}));
+        adm_req.namespace = namespace.map(str::to_owned);
+
+        assert_eq!(
+            expected,
+            request_matcher.matches(&adm_req, |_namespace| namespace_labels)
+        );
+    }
+}
@@ -9,7 +9,10 @@ mod test_utils;
 mod cli;
 
 pub mod api;
+pub mod break_glass;
 pub mod config;
+pub mod decision_log;
+pub mod decision_webhook;
 pub mod metrics;
 pub mod profiling;
 pub mod tracing;
@@ -18,6 +21,7 @@ use ::tracing::{Level, debug, info, trace, warn};
 use anyhow::{Result, anyhow};
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     routing::{get, post},
 };
 use axum_server::tls_rustls::RustlsConfig;
@@ -39,7 +43,9 @@ use tokio::{
 use tower_http::trace::{self, TraceLayer};
 
 use crate::api::handlers::{
-    audit_handler, pprof_get_cpu, pprof_get_heap, readiness_handler, validate_handler,
+    audit_batch_handler, audit_handler, break_glass_activate_handler,
+    break_glass_deactivate_handler, break_glass_status_handler, pprof_get_cpu, pprof_get_heap,
+    readiness_handler, validate_all_handler, validate_handler, validate_raw_batch_handler,
     validate_raw_handler,
 };
 use crate::api::state::ApiServerState;
@@ -49,6 +55,9 @@ use config::Config;
 
 use tikv_jemallocator::Jemalloc;
 
+/// The maximum size of the body of an audit batch, which can hold many resources
+const AUDIT_BATCH_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
@@ -153,7 +162,10 @@ impl PolicyServer {
             } => timeout_eval_seconds.is_some(),
             config::PolicyOrPolicyGroup::PolicyGroup { policies, .. } => policies
                 .values()
-                .any(|member| member.timeout_eval_seconds.is_some()),
+                .any(config::PolicyGroupMember::has_timeout),
+            config::PolicyOrPolicyGroup::MutationPipeline { steps, .. } => {
+                steps.iter().any(|step| step.timeout_eval_seconds.is_some())
+            }
         });
         if config.policy_evaluation_limit_seconds.is_some() || any_policy_has_timeout {
             wasmtime_config.epoch_interruption(true);
@@ -175,11 +187,12 @@ impl PolicyServer {
             &precompiled_policies,
             callback_sender_channel.clone(),
         )
-        .with_continue_on_errors(config.continue_on_errors);
-        if let Some(namespace) = config.always_accept_admission_reviews_on_namespace {
-            evaluation_environment_builder = evaluation_environment_builder
-                .with_always_accept_admission_reviews_on_namespace(namespace);
-        }
+        .with_continue_on_errors(config.continue_on_errors)
+        .with_verify_mutation_idempotency(config.verify_mutation_idempotency)
+        .with_enforce_metadata_rules(config.enforce_metadata_rules)
+        .with_monitor_mode_warnings(config.monitor_mode_warnings)
+        .with_policy_group_workers(config.policy_group_pool_size)
+        .with_exemptions(config.exemptions.clone());
         if let Some(limit) = config.policy_evaluation_limit_seconds {
             evaluation_environment_builder =
                 evaluation_environment_builder.with_global_policy_evaluation_limit_seconds(limit);
@@ -206,7 +219,13 @@ impl PolicyServer {
 
         let state = Arc::new(ApiServerState {
             semaphore: Semaphore::new(config.pool_size),
+            pool_size: config.pool_size,
+            queue_timeout: config.pool_queue_timeout,
             evaluation_environment: Arc::new(evaluation_environment),
+            break_glass_token: config
+                .break_glass
+                .as_ref()
+                .and_then(|break_glass| break_glass.token.clone()),
         });
 
         let tls_config = if let Some(tls_config) = config.tls_config {
@@ -216,9 +235,18 @@ impl PolicyServer {
         };
 
         let mut router = Router::new()
+            .route(
+                "/audit",
+                post(audit_batch_handler).layer(DefaultBodyLimit::max(AUDIT_BATCH_BODY_LIMIT)),
+            )
             .route("/audit/{policy_id}", post(audit_handler))
+            .route("/validate", post(validate_all_handler))
             .route("/validate/{policy_id}", post(validate_handler))
             .route("/validate_raw/{policy_id}", post(validate_raw_handler))
+            .route(
+                "/validate_raw_batch/{policy_id}",
+                post(validate_raw_batch_handler),
+            )
             .with_state(state.clone())
             .layer(
                 TraceLayer::new_for_http()
@@ -235,6 +263,18 @@ impl PolicyServer {
             router = Router::new().merge(router).merge(pprof_router);
         }
 
+        if state.break_glass_token.is_some() {
+            let break_glass_router = Router::new()
+                .route(
+                    "/break-glass",
+                    get(break_glass_status_handler)
+                        .post(break_glass_activate_handler)
+                        .delete(break_glass_deactivate_handler),
+                )
+                .with_state(state.clone());
+            router = Router::new().merge(router).merge(break_glass_router);
+        }
+
         let readiness_probe_router = Router::new().route("/readiness", get(readiness_handler));
 
         Ok(Self {
@@ -8,6 +8,9 @@ use anyhow::Result;
 use anyhow::anyhow;
 use clap::ArgMatches;
 use policy_server::PolicyServer;
+use policy_server::break_glass::setup_break_glass;
+use policy_server::decision_log::setup_decision_log;
+use policy_server::decision_webhook::setup_decision_webhook;
 use policy_server::metrics::setup_metrics;
 use policy_server::tracing::setup_tracing;
 
@@ -32,6 +35,10 @@ async fn main() -> Result<()> {
         setup_metrics()?;
     };
 
+    if let Some(decision_log_config) = config.decision_log.clone() {
+        setup_decision_log(decision_log_config)?;
+    }
+
     if config.daemon {
         info!("Running instance as a daemon");
 
@@ -54,6 +61,15 @@ async fn main() -> Result<()> {
         info!("Detached from shell, now running in background.");
     }
 
+    // The task delivering the events is spawned only after the process has been daemonized
+    if let Some(decision_webhook_config) = config.decision_webhook.clone() {
+        setup_decision_webhook(decision_webhook_config)?;
+    }
+
+    if let Some(break_glass_config) = config.break_glass.clone() {
+        setup_break_glass(break_glass_config)?;
+    }
+
     let api_server = PolicyServer::new_from_config(config).await?;
     api_server.run().await?;
 
@@ -6,6 +6,10 @@ mod policy_evaluations_total;
 pub use policy_evaluations_total::add_policy_evaluation;
 mod policy_evaluations_latency;
 pub use policy_evaluations_latency::record_policy_latency;
+mod decision_webhook_dropped_events_total;
+pub use decision_webhook_dropped_events_total::add_dropped_decision_events;
+mod policy_evaluation_errors_total;
+pub(crate) use policy_evaluation_errors_total::add_policy_evaluation_error;
 
 use crate::config::build_client_tls_config_from_env;
 
@@ -34,13 +38,19 @@ pub trait PolicyEvaluationMetric: Into<Vec<KeyValue>> {}
 pub(crate) struct PolicyEvaluation {
     pub(crate) policy_name: String,
     pub(crate) policy_mode: String,
+    pub(crate) policy_variant: String,
     pub(crate) resource_kind: String,
     pub(crate) resource_namespace: Option<String>,
     pub(crate) resource_request_operation: String,
     pub(crate) accepted: bool,
     pub(crate) mutated: bool,
     pub(crate) request_origin: String,
+    pub(crate) dry_run: bool,
     pub(crate) error_code: Option<u16>,
+    /// Why the request has been accepted without evaluating the policy
+    pub(crate) exemption_reason: Option<String>,
+    /// The policy has been forced into monitor mode by the break-glass override
+    pub(crate) break_glass: bool,
 }
 
 impl PolicyEvaluationMetric for &PolicyEvaluation {}
@@ -51,6 +61,7 @@ impl Into<Vec<KeyValue>> for &PolicyEvaluation {
         let mut baggage = vec![
             KeyValue::new("policy_name", self.policy_name.clone()),
             KeyValue::new("policy_mode", self.policy_mode.clone()),
+            KeyValue::new("policy_variant", self.policy_variant.clone()),
             KeyValue::new("resource_kind", self.resource_kind.clone()),
             KeyValue::new(
                 "resource_request_operation",
@@ -59,6 +70,7 @@ impl Into<Vec<KeyValue>> for &PolicyEvaluation {
             KeyValue::new("accepted", self.accepted),
             KeyValue::new("mutated", self.mutated),
             KeyValue::new("request_origin", self.request_origin.clone()),
+            KeyValue::new("dry_run", self.dry_run),
         ];
         if let Some(resource_namespace) = &self.resource_namespace {
             baggage.append(&mut vec![KeyValue::new(
@@ -69,6 +81,15 @@ impl Into<Vec<KeyValue>> for &PolicyEvaluation {
         if let Some(error_code) = self.error_code {
             baggage.append(&mut vec![KeyValue::new("error_code", error_code as i64)]);
         }
+        if let Some(exemption_reason) = &self.exemption_reason {
+            baggage.append(&mut vec![KeyValue::new(
+                "exemption_reason",
+                exemption_reason.clone(),
+            )]);
+        }
+        if self.break_glass {
+            baggage.append(&mut vec![KeyValue::new("break_glass", true)]);
+        }
         baggage
     }
 }
@@ -77,9 +98,12 @@ impl Into<Vec<KeyValue>> for &PolicyEvaluation {
 pub(crate) struct RawPolicyEvaluation {
     pub(crate) policy_name: String,
     pub(crate) policy_mode: String,
+    pub(crate) policy_variant: String,
     pub(crate) accepted: bool,
     pub(crate) mutated: bool,
     pub(crate) error_code: Option<u16>,
+    /// The policy has been forced into monitor mode by the break-glass override
+    pub(crate) break_glass: bool,
 }
 
 impl PolicyEvaluationMetric for &RawPolicyEvaluation {}
@@ -90,6 +114,7 @@ impl Into<Vec<KeyValue>> for &RawPolicyEvaluation {
         let mut baggage = vec![
             KeyValue::new("policy_name", self.policy_name.clone()),
             KeyValue::new("policy_mode", self.policy_mode.clone()),
+            KeyValue::new("policy_variant", self.policy_variant.clone()),
             KeyValue::new("accepted", self.accepted),
             KeyValue::new("mutated", self.mutated),
         ];
@@ -97,6 +122,29 @@ impl Into<Vec<KeyValue>> for &RawPolicyEvaluation {
         if let Some(error_code) = self.error_code {
             baggage.append(&mut vec![KeyValue::new("error_code", error_code as i64)]);
         }
+        if self.break_glass {
+            baggage.append(&mut vec![KeyValue::new("break_glass", true)]);
+        }
+        baggage
+    }
+}
+
+/// A request that could not be evaluated, identified by the code of the API error
+#[derive(Clone)]
+pub(crate) struct PolicyEvaluationError {
+    /// The policy targeted by the request, not known when evaluating all the matching
+    /// policies
+    pub(crate) policy_name: Option<String>,
+    pub(crate) error_code: String,
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<Vec<KeyValue>> for &PolicyEvaluationError {
+    fn into(self) -> Vec<KeyValue> {
+        let mut baggage = vec![KeyValue::new("error_code", self.error_code.clone())];
+        if let Some(policy_name) = &self.policy_name {
+            baggage.push(KeyValue::new("policy_name", policy_name.clone()));
+        }
         baggage
     }
 }
@@ -0,0 +1,13 @@
+use lazy_static::lazy_static;
+use opentelemetry::{KeyValue, metrics::Counter};
+
+lazy_static! {
+    static ref DECISION_WEBHOOK_DROPPED_EVENTS_TOTAL: Counter<u64> =
+        opentelemetry::global::meter(super::METER_NAME)
+            .u64_counter("kubewarden_decision_webhook_dropped_events_total")
+            .build();
+}
+
+pub fn add_dropped_decision_events(count: u64, reason: &str) {
+    DECISION_WEBHOOK_DROPPED_EVENTS_TOTAL.add(count, &[KeyValue::new("reason", reason.to_owned())]);
+}
@@ -0,0 +1,15 @@
+use lazy_static::lazy_static;
+use opentelemetry::{KeyValue, metrics::Counter};
+
+use crate::metrics::PolicyEvaluationError;
+
+lazy_static! {
+    static ref POLICY_EVALUATION_ERRORS_TOTAL: Counter<u64> =
+        opentelemetry::global::meter(super::METER_NAME)
+            .u64_counter("kubewarden_policy_evaluation_errors_total")
+            .build();
+}
+
+pub(crate) fn add_policy_evaluation_error(policy_evaluation_error: &PolicyEvaluationError) {
+    POLICY_EVALUATION_ERRORS_TOTAL.add(1, &Into::<Vec<KeyValue>>::into(policy_evaluation_error));
+}
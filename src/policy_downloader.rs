@@ -17,7 +17,7 @@ use policy_evaluator::{
 use sigstore::trust::sigstore::SigstoreTrustRoot;
 use tracing::{debug, error, info};
 
-use crate::config::PolicyOrPolicyGroup;
+use crate::config::{PolicyGroupMember, PolicyOrPolicyGroup};
 
 /// A Map with the `policy.url` as key,
 /// and a `PathBuf` as value. The `PathBuf` points to the location where
@@ -231,7 +231,8 @@ async fn create_verifier(
 /// Group policies need to be flattened into a single list of policies to download
 ///
 /// Return a map with the name of the policy as key, and the its download url as value.
-/// Sub-policies are named as `group_name/sub_policy_name`
+/// Sub-policies and the steps of mutation pipelines are named as `group_name/sub_policy_name`,
+/// canaries are named as `policy_name/canary`
 fn policies_to_download(
     policies: &HashMap<String, PolicyOrPolicyGroup>,
 ) -> HashMap<String, String> {
@@ -239,15 +240,23 @@ fn policies_to_download(
 
     for (name, policy) in policies {
         match policy {
-            PolicyOrPolicyGroup::Policy { module: url, .. } => {
+            PolicyOrPolicyGroup::Policy {
+                module: url,
+                canary,
+                ..
+            } => {
                 flattened_policies.insert(name.to_owned(), url.to_owned());
+                if let Some(canary) = canary {
+                    flattened_policies.insert(format!("{name}/canary"), canary.module.to_owned());
+                }
             }
             PolicyOrPolicyGroup::PolicyGroup { policies, .. } => {
-                for (sub_policy_name, sub_policy) in policies {
-                    flattened_policies.insert(
-                        format!("{name}/#{sub_policy_name}"),
-                        sub_policy.module.to_owned(),
-                    );
+                flatten_policy_group_members(name, policies, &mut flattened_policies);
+            }
+            PolicyOrPolicyGroup::MutationPipeline { steps, .. } => {
+                for step in steps {
+                    flattened_policies
+                        .insert(format!("{name}/#{}", step.name), step.module.to_owned());
                 }
             }
         }
@@ -256,6 +265,28 @@ fn policies_to_download(
     flattened_policies
 }
 
+/// Add the modules of the members of a policy group, including the ones of the nested
+/// groups, to the flattened policies
+fn flatten_policy_group_members(
+    group_name: &str,
+    policies: &HashMap<String, PolicyGroupMember>,
+    flattened_policies: &mut HashMap<String, String>,
+) {
+    for (sub_policy_name, sub_policy) in policies {
+        let name = format!("{group_name}/#{sub_policy_name}");
+        match sub_policy {
+            PolicyGroupMember::Policy { module, .. } => {
+                flattened_policies.insert(name, module.to_owned());
+            }
+            PolicyGroupMember::PolicyGroup { policies, .. } => {
+                flatten_policy_group_members(&name, policies, flattened_policies);
+            }
+            // The references are resolved when the configuration is loaded
+            PolicyGroupMember::Reference { .. } => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
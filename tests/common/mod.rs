@@ -9,7 +9,7 @@ use policy_evaluator::admission_response_handler::policy_mode::PolicyMode;
 use policy_evaluator::policy_evaluator::PolicySettings;
 use policy_server::{
     PolicyServer,
-    config::{Config, PolicyGroupMember, PolicyOrPolicyGroup},
+    config::{Config, Exemptions, PolicyGroupMember, PolicyOrPolicyGroup, RequestFilter},
 };
 use serde_json::json;
 use tempfile::tempdir;
@@ -42,6 +42,11 @@ pub(crate) fn default_test_config() -> Config {
                 context_aware_resources: BTreeSet::new(),
                 message: None,
                 timeout_eval_seconds: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: None,
             },
         ),
         (
@@ -60,6 +65,11 @@ pub(crate) fn default_test_config() -> Config {
                 context_aware_resources: BTreeSet::new(),
                 message: None,
                 timeout_eval_seconds: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: None,
             },
         ),
         (
@@ -77,21 +87,34 @@ pub(crate) fn default_test_config() -> Config {
                 ),
                 context_aware_resources: BTreeSet::new(),
                 message: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: None,
             },
         ),
         (
             "group-policy-just-pod-privileged".to_owned(),
             PolicyOrPolicyGroup::PolicyGroup {
-                expression: "pod_privileged() && true".to_string(),
+                expression: Some("pod_privileged() && true".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "The group policy rejected your request".to_string(),
                 policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
                 policies: HashMap::from([(
                     "pod_privileged".to_string(),
-                    PolicyGroupMember {
+                    PolicyGroupMember::Policy {
                         module: "ghcr.io/kubewarden/tests/pod-privileged:v0.2.1".to_owned(),
                         settings: None,
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        weight: None,
                     },
                 )]),
             },
@@ -99,12 +122,19 @@ pub(crate) fn default_test_config() -> Config {
         (
             "group-policy-just-raw-mutation".to_owned(),
             PolicyOrPolicyGroup::PolicyGroup {
-                expression: "raw_mutation() && true".to_string(),
+                expression: Some("raw_mutation() && true".to_string()),
+                min_passing: None,
+                min_score: None,
                 message: "The group policy rejected your request".to_string(),
                 policy_mode: PolicyMode::Protect,
+                allowed_to_mutate: None,
+                concurrent_evaluation: false,
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
                 policies: HashMap::from([(
                     "raw_mutation".to_string(),
-                    PolicyGroupMember {
+                    PolicyGroupMember::Policy {
                         module: "ghcr.io/kubewarden/tests/raw-mutation-policy:v0.1.0".to_owned(),
                         settings: Some(
                             PolicySettings::try_from(&json!({
@@ -115,6 +145,7 @@ pub(crate) fn default_test_config() -> Config {
                         ),
                         context_aware_resources: BTreeSet::new(),
                         timeout_eval_seconds: None,
+                        weight: None,
                     },
                 )]),
             },
@@ -134,6 +165,11 @@ pub(crate) fn default_test_config() -> Config {
                 ),
                 context_aware_resources: BTreeSet::new(),
                 message: None,
+                mutation_deny_paths: Vec::new(),
+                enforcement_schedule: None,
+                skip_context_aware_resources_on_dry_run: false,
+                request_filter: RequestFilter::default(),
+                canary: None,
             },
         ),
     ]);
@@ -145,10 +181,12 @@ pub(crate) fn default_test_config() -> Config {
         policies,
         policies_download_dir: tempdir().unwrap().keep(),
         ignore_kubernetes_connection_failure: true,
-        always_accept_admission_reviews_on_namespace: None,
+        exemptions: Exemptions::default(),
         policy_evaluation_limit_seconds: Some(2),
         tls_config: None,
         pool_size: 2,
+        pool_queue_timeout: None,
+        policy_group_pool_size: 2,
         metrics_enabled: false,
         sigstore_cache_dir: tempdir().unwrap().keep(),
         verification_config: None,
@@ -161,6 +199,12 @@ pub(crate) fn default_test_config() -> Config {
         daemon_stderr_file: None,
         enable_pprof: false,
         continue_on_errors: false,
+        verify_mutation_idempotency: false,
+        enforce_metadata_rules: false,
+        monitor_mode_warnings: false,
+        decision_log: None,
+        decision_webhook: None,
+        break_glass: None,
     }
 }
 
@@ -22,7 +22,10 @@ use policy_evaluator::{
     admission_response_handler::policy_mode::PolicyMode, policy_evaluator::PolicySettings,
     policy_fetcher::verify::config::VerificationConfigV1,
 };
-use policy_server::{api::admission_review::AdmissionReviewResponse, config::PolicyOrPolicyGroup};
+use policy_server::{
+    api::admission_review::AdmissionReviewResponse,
+    config::{PolicyOrPolicyGroup, RequestFilter},
+};
 use regex::Regex;
 use rstest::*;
 use serde_json::json;
@@ -67,6 +70,46 @@ async fn test_validate() {
     )
 }
 
+#[rstest]
+#[case::v1("admission.k8s.io/v1", 200)]
+#[case::v1beta1("admission.k8s.io/v1beta1", 200)]
+#[case::unknown_version("admission.k8s.io/v2", 400)]
+#[tokio::test]
+async fn test_validate_admission_review_version(
+    #[case] api_version: &str,
+    #[case] expected_status: u16,
+) {
+    setup();
+
+    let config = default_test_config();
+    let app = app(config).await;
+
+    let mut admission_review: serde_json::Value =
+        serde_json::from_str(include_str!("data/pod_with_privileged_containers.json")).unwrap();
+    admission_review["apiVersion"] = json!(api_version);
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate/pod-privileged")
+        .body(Body::from(admission_review.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), expected_status);
+    if expected_status == 200 {
+        let admission_review_response: AdmissionReviewResponse =
+            serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes())
+                .unwrap();
+        assert_eq!(
+            admission_review_response.api_version.as_deref(),
+            Some(api_version)
+        );
+        assert!(!admission_review_response.response.allowed);
+    }
+}
+
 #[tokio::test]
 async fn test_validate_custom_rejection_message() {
     setup();
@@ -82,6 +125,11 @@ async fn test_validate_custom_rejection_message() {
             context_aware_resources: BTreeSet::new(),
             message: Some("Custom error message".to_owned()),
             timeout_eval_seconds: None,
+            mutation_deny_paths: Vec::new(),
+            enforcement_schedule: None,
+            skip_context_aware_resources_on_dry_run: false,
+            request_filter: RequestFilter::default(),
+            canary: None,
         },
     );
     let app = app(config).await;
@@ -176,14 +224,54 @@ async fn test_validate_policy_group(#[case] payload: &str, #[case] expected_allo
             .details
             .expect("details should be filled")
             .causes;
-        assert_eq!(1, causes.len());
         assert_eq!(
-            Some("Privileged container is not allowed".to_string()),
-            causes[0].message,
+            vec![StatusCause {
+                field: Some("spec.policies.pod_privileged".to_string()),
+                message: Some("Privileged container is not allowed".to_string()),
+                reason: Some("Denied".to_string()),
+            }],
+            causes
         );
     }
 }
 
+#[tokio::test]
+async fn test_validate_all_matching_policies() {
+    setup();
+
+    let config = default_test_config();
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/validate")
+        .body(Body::from(include_str!(
+            "data/pod_with_privileged_containers.json"
+        )))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let admission_review_response: AdmissionReviewResponse =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+
+    assert!(!admission_review_response.response.allowed);
+    let message = admission_review_response
+        .response
+        .status
+        .expect("status should be filled")
+        .message
+        .expect("message should be filled");
+    assert!(message.contains("pod-privileged: Privileged container is not allowed"));
+    assert!(
+        message
+            .contains("group-policy-just-pod-privileged: The group policy rejected your request")
+    );
+}
+
 #[tokio::test]
 async fn test_validate_policy_not_found() {
     setup();
@@ -203,6 +291,11 @@ async fn test_validate_policy_not_found() {
     let response = app.oneshot(request).await.unwrap();
 
     assert_eq!(response.status(), 404);
+
+    let api_error: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(api_error["code"], "policy_not_found");
+    assert_eq!(api_error["status"], 404);
 }
 
 #[tokio::test]
@@ -222,6 +315,10 @@ async fn test_validate_invalid_payload() {
     let response = app.oneshot(request).await.unwrap();
 
     assert_eq!(response.status(), 422);
+
+    let api_error: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(api_error["code"], "invalid_request");
 }
 
 #[tokio::test]
@@ -254,6 +351,150 @@ async fn test_validate_raw() {
     );
 }
 
+#[rstest]
+#[case::newline_delimited(
+    format!(
+        "{}\n{}\nnot json\n",
+        json!({"request": {"user": "tonio", "action": "eats", "resource": "banana"}}),
+        json!({"request": {"user": "tonio", "action": "eats", "resource": "apple"}})
+    ),
+    vec![Some(200), Some(200), Some(400)]
+)]
+#[case::array(
+    format!(
+        "[{}, {}]",
+        json!({"request": {"user": "tonio", "action": "eats", "resource": "banana"}}),
+        json!({"request": {"user": "tonio", "action": "eats", "resource": "apple"}})
+    ),
+    vec![Some(200), Some(200)]
+)]
+#[case::invalid_array("[42]".to_owned(), vec![Some(400)])]
+#[tokio::test]
+async fn test_validate_raw_batch(#[case] body: String, #[case] expected_codes: Vec<Option<u64>>) {
+    setup();
+
+    let config = default_test_config();
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .uri("/validate_raw_batch/raw-mutation")
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/x-ndjson"
+    );
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let codes: Vec<Option<u64>> = String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let raw_review_response: serde_json::Value = serde_json::from_str(line).unwrap();
+            let response = &raw_review_response["response"];
+            if response["allowed"] == true {
+                assert!(response["patch"].is_string());
+                Some(200)
+            } else {
+                assert_eq!(
+                    "invalid_request",
+                    response["status"]["details"]["causes"][0]["reason"]
+                );
+                response["status"]["code"].as_u64()
+            }
+        })
+        .collect();
+
+    assert_eq!(codes, expected_codes);
+}
+
+#[tokio::test]
+async fn test_validate_raw_batch_policy_not_found() {
+    setup();
+
+    let config = default_test_config();
+    let app = app(config).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .uri("/validate_raw_batch/does_not_exist")
+        .body(Body::from(include_str!("data/raw_review.json")))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_validate_raw_batch_policy_initialization_failed() {
+    setup();
+
+    let mut config = default_test_config();
+    config.policies.insert(
+        "wrong_url".to_owned(),
+        PolicyOrPolicyGroup::Policy {
+            module: "ghcr.io/kubewarden/tests/not_existing:v0.1.0".to_owned(),
+            policy_mode: PolicyMode::Protect,
+            allowed_to_mutate: None,
+            settings: None,
+            context_aware_resources: BTreeSet::new(),
+            message: None,
+            timeout_eval_seconds: None,
+            mutation_deny_paths: Vec::new(),
+            enforcement_schedule: None,
+            skip_context_aware_resources_on_dry_run: false,
+            request_filter: RequestFilter::default(),
+            canary: None,
+        },
+    );
+    config.continue_on_errors = true;
+
+    let app = app(config).await;
+
+    let body = format!(
+        "{}\n{}\n",
+        json!({"request": {"user": "tonio", "action": "eats", "resource": "banana"}}),
+        json!({"request": {"user": "tonio", "action": "eats", "resource": "apple"}})
+    );
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .uri("/validate_raw_batch/wrong_url")
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    // Like `/validate_raw`, each request is rejected with the initialization error
+    assert_eq!(response.status(), 200);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let lines: Vec<String> = String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        let raw_review_response: serde_json::Value = serde_json::from_str(&line).unwrap();
+        let response = &raw_review_response["response"];
+        assert_eq!(false, response["allowed"]);
+        assert_eq!(500, response["status"]["code"]);
+        assert_eq!(
+            "policy_initialization_failed",
+            response["status"]["details"]["causes"][0]["reason"]
+        );
+    }
+}
+
 #[tokio::test]
 async fn test_validate_policy_group_does_not_do_mutation() {
     setup();
@@ -325,7 +566,13 @@ async fn test_validate_raw_policy_not_found() {
 }
 
 #[tokio::test]
-async fn test_validate_raw_invalid_payload() {
+#[rstest]
+#[case::missing_fields("{}", 422)]
+#[case::malformed_json("{", 400)]
+async fn test_validate_raw_invalid_payload(
+    #[case] body: &'static str,
+    #[case] expected_status: u16,
+) {
     setup();
 
     let config = default_test_config();
@@ -335,12 +582,17 @@ async fn test_validate_raw_invalid_payload() {
         .method(http::Method::POST)
         .header(header::CONTENT_TYPE, "application/json")
         .uri("/validate_raw/raw-mutation")
-        .body(Body::from("{}"))
+        .body(Body::from(body))
         .unwrap();
 
     let response = app.oneshot(request).await.unwrap();
 
-    assert_eq!(response.status(), 422);
+    assert_eq!(response.status(), expected_status);
+
+    let api_error: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(api_error["code"], "invalid_request");
+    assert_eq!(api_error["status"], expected_status);
 }
 
 #[tokio::test]
@@ -399,7 +651,10 @@ async fn test_audit_policy_not_found() {
 }
 
 #[tokio::test]
-async fn test_audit_invalid_payload() {
+#[rstest]
+#[case::missing_fields("{}", 422)]
+#[case::malformed_json("{", 400)]
+async fn test_audit_invalid_payload(#[case] body: &'static str, #[case] expected_status: u16) {
     setup();
 
     let config = default_test_config();
@@ -409,12 +664,93 @@ async fn test_audit_invalid_payload() {
         .method(http::Method::POST)
         .header(header::CONTENT_TYPE, "application/json")
         .uri("/audit/pod-privileged")
-        .body(Body::from("{}"))
+        .body(Body::from(body))
         .unwrap();
 
     let response = app.oneshot(request).await.unwrap();
 
-    assert_eq!(response.status(), 422);
+    assert_eq!(response.status(), expected_status);
+
+    let api_error: serde_json::Value =
+        serde_json::from_slice(&response.into_body().collect().await.unwrap().to_bytes()).unwrap();
+    assert_eq!(api_error["code"], "invalid_request");
+    assert_eq!(api_error["status"], expected_status);
+}
+
+#[tokio::test]
+async fn test_audit_batch() {
+    setup();
+
+    let config = default_test_config();
+    let app = app(config).await;
+
+    let privileged: serde_json::Value =
+        serde_json::from_str(include_str!("data/pod_with_privileged_containers.json")).unwrap();
+    let unprivileged: serde_json::Value =
+        serde_json::from_str(include_str!("data/pod_without_privileged_containers.json")).unwrap();
+    let audit_batch = json!({
+        "policies": ["pod-privileged"],
+        "resources": [
+            {"request": privileged["request"]},
+            {"request": unprivileged["request"], "policies": ["does_not_exist"]},
+        ],
+    });
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/audit")
+        .body(Body::from(audit_batch.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let results: Vec<serde_json::Value> = String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["uid"], privileged["request"]["uid"]);
+    assert_eq!(results[0]["results"][0]["policyId"], "pod-privileged");
+    assert_eq!(results[0]["results"][0]["response"]["allowed"], false);
+
+    assert_eq!(results[1]["uid"], unprivileged["request"]["uid"]);
+    assert_eq!(results[1]["results"][0]["response"]["allowed"], true);
+    assert_eq!(results[1]["results"][1]["policyId"], "does_not_exist");
+    assert_eq!(
+        results[1]["results"][1]["error"]["code"],
+        "policy_not_found"
+    );
+}
+
+#[tokio::test]
+async fn test_audit_batch_resource_without_policies() {
+    setup();
+
+    let config = default_test_config();
+    let app = app(config).await;
+
+    let privileged: serde_json::Value =
+        serde_json::from_str(include_str!("data/pod_with_privileged_containers.json")).unwrap();
+    let audit_batch = json!({
+        "resources": [{"request": privileged["request"]}],
+    });
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .header(header::CONTENT_TYPE, "application/json")
+        .uri("/audit")
+        .body(Body::from(audit_batch.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), 400);
 }
 
 #[tokio::test]
@@ -471,6 +807,13 @@ async fn test_timeout_protection_reject() {
                     .to_owned()
             ),
             code: Some(500),
+            details: Some(StatusDetails {
+                causes: vec![StatusCause {
+                    reason: Some("policy_timeout".to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
             ..Default::default()
         })
     );
@@ -511,6 +854,13 @@ async fn test_timeout_protection_policy_specific_reject() {
                     .to_owned()
             ),
             code: Some(500),
+            details: Some(StatusDetails {
+                causes: vec![StatusCause {
+                    reason: Some("policy_timeout".to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
             ..Default::default()
         })
     );
@@ -556,6 +906,11 @@ async fn test_verified_policy() {
             context_aware_resources: BTreeSet::new(),
             message: None,
             timeout_eval_seconds: None,
+            mutation_deny_paths: Vec::new(),
+            enforcement_schedule: None,
+            skip_context_aware_resources_on_dry_run: false,
+            request_filter: RequestFilter::default(),
+            canary: None,
         },
     )]);
     config.verification_config = Some(verification_config);
@@ -595,6 +950,11 @@ async fn test_policy_with_invalid_settings() {
             context_aware_resources: BTreeSet::new(),
             message: None,
             timeout_eval_seconds: None,
+            mutation_deny_paths: Vec::new(),
+            enforcement_schedule: None,
+            skip_context_aware_resources_on_dry_run: false,
+            request_filter: RequestFilter::default(),
+            canary: None,
         },
     );
     config.continue_on_errors = true;
@@ -642,6 +1002,11 @@ async fn test_policy_with_wrong_url() {
             context_aware_resources: BTreeSet::new(),
             message: None,
             timeout_eval_seconds: None,
+            mutation_deny_paths: Vec::new(),
+            enforcement_schedule: None,
+            skip_context_aware_resources_on_dry_run: false,
+            request_filter: RequestFilter::default(),
+            canary: None,
         },
     );
     config.continue_on_errors = true;